@@ -0,0 +1,163 @@
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity ^0.8.20;
+
+/// @notice A node of the KeySpace Indexed Merkle Tree (IMT).
+struct ImtNode {
+    /// @dev The node index in the IMT.
+    uint64 index;
+    /// @dev The node key (the KeySpace id).
+    bytes32 key;
+    /// @dev The node value (the KeySpace record value).
+    bytes32 value;
+    /// @dev The key of the next node in key order (0 for the last node).
+    bytes32 nextKey;
+}
+
+/// @notice A proof that a node is in the IMT.
+struct ImtInclusionProof {
+    /// @dev The IMT root (including the size).
+    bytes32 root;
+    /// @dev The IMT size (including the 0 node).
+    uint64 size;
+    /// @dev The node proven to be in the IMT.
+    ImtNode node;
+    /// @dev The node siblings from the leaves to the top of the IMT (absent siblings are set to 0).
+    bytes32[] siblings;
+    /// @dev The bitmap of the present `siblings` (bit `i` is set if `siblings[i]` is present).
+    uint256 siblingsBitmap;
+}
+
+/// @notice A proof that a key is not in the IMT.
+struct ImtExclusionProof {
+    /// @dev The IMT root (including the size).
+    bytes32 root;
+    /// @dev The IMT size (including the 0 node).
+    uint64 size;
+    /// @dev The low nullifier node of `nodeKey`.
+    ImtNode lnNode;
+    /// @dev The low nullifier node siblings from the leaves to the top of the IMT (absent siblings are
+    /// set to 0).
+    bytes32[] lnSiblings;
+    /// @dev The bitmap of the present `lnSiblings` (bit `i` is set if `lnSiblings[i]` is present).
+    uint256 lnSiblingsBitmap;
+    /// @dev The key proven not to be in the IMT.
+    bytes32 nodeKey;
+}
+
+/// @notice Verifies the KeySpace IMT proofs against a KeySpace root (e.g. `KeyStore.root`).
+///
+/// @dev Mirrors the `keyspace-imt` crate verification when used with the keccak256 hasher.
+library ImtVerifier {
+    ////////////////////////////////////////////////////////////////////////////////////////////////////
+    //                                              ERRORS                                            //
+    ////////////////////////////////////////////////////////////////////////////////////////////////////
+
+    /// @notice Thrown when the proof root does not match the expected root.
+    error StaleRoot();
+
+    /// @notice Thrown when the number of siblings does not match the IMT size.
+    error SizeMismatch();
+
+    /// @notice Thrown when the proven node is not in the IMT.
+    error NodeDoesNotExist();
+
+    /// @notice Thrown when the key proven not to be in the IMT is actually in it.
+    error NodeAlreadyExist();
+
+    /// @notice Thrown when the low nullifier node is not in the IMT or is not the one of the proven key.
+    error InvalidLowNullifier();
+
+    ////////////////////////////////////////////////////////////////////////////////////////////////////
+    //                                         INTERNAL FUNCTIONS                                     //
+    ////////////////////////////////////////////////////////////////////////////////////////////////////
+
+    /// @notice Verifies an IMT inclusion proof against the given `root`.
+    /// @param root The expected IMT root.
+    /// @param proof The inclusion proof.
+    /// @return The value of the proven node.
+    function verifyInclusion(bytes32 root, ImtInclusionProof memory proof) internal pure returns (bytes32) {
+        if (proof.root != root) {
+            revert StaleRoot();
+        }
+
+        if (!nodeExists(proof.root, proof.size, proof.node, proof.siblings, proof.siblingsBitmap)) {
+            revert NodeDoesNotExist();
+        }
+
+        return proof.node.value;
+    }
+
+    /// @notice Verifies an IMT exclusion proof against the given `root`.
+    /// @param root The expected IMT root.
+    /// @param proof The exclusion proof.
+    function verifyExclusion(bytes32 root, ImtExclusionProof memory proof) internal pure {
+        if (proof.root != root) {
+            revert StaleRoot();
+        }
+
+        if (!nodeExists(proof.root, proof.size, proof.lnNode, proof.lnSiblings, proof.lnSiblingsBitmap)) {
+            revert InvalidLowNullifier();
+        }
+
+        if (proof.lnNode.key == proof.nodeKey) {
+            revert NodeAlreadyExist();
+        }
+
+        if (!isLnOf(proof.lnNode, proof.nodeKey)) {
+            revert InvalidLowNullifier();
+        }
+    }
+
+    /// @notice Returns true if the given `node` is part of the IMT committed to in `root`.
+    /// @param root The IMT root (including the size).
+    /// @param size The IMT size (including the 0 node).
+    /// @param node The node.
+    /// @param siblings The node siblings.
+    /// @param siblingsBitmap The bitmap of the present `siblings`.
+    function nodeExists(
+        bytes32 root,
+        uint64 size,
+        ImtNode memory node,
+        bytes32[] memory siblings,
+        uint256 siblingsBitmap
+    ) internal pure returns (bool) {
+        // Make sure the siblings match the IMT size.
+        if (size == 0 || siblings.length != depth(size) || siblingsBitmap >> siblings.length != 0) {
+            revert SizeMismatch();
+        }
+
+        bytes32 hash = nodeHash(node);
+        uint64 index = node.index;
+        for (uint256 level; level < siblings.length; level++) {
+            if ((siblingsBitmap >> level) & 1 == 0) {
+                hash = keccak256(abi.encodePacked(hash));
+            } else if (index % 2 == 0) {
+                hash = keccak256(abi.encodePacked(hash, siblings[level]));
+            } else {
+                hash = keccak256(abi.encodePacked(siblings[level], hash));
+            }
+
+            index /= 2;
+        }
+
+        return root == keccak256(abi.encodePacked(hash, size));
+    }
+
+    /// @notice Returns the IMT leaf hash of the given `node`.
+    /// @dev The node index is intentionally not hashed.
+    function nodeHash(ImtNode memory node) internal pure returns (bytes32) {
+        return keccak256(abi.encodePacked(node.key, node.value, node.nextKey));
+    }
+
+    /// @notice Returns true if the given `node` is the low nullifier of `key`.
+    function isLnOf(ImtNode memory node, bytes32 key) internal pure returns (bool) {
+        return node.key < key && (node.nextKey > key || node.nextKey == bytes32(0));
+    }
+
+    /// @notice Returns the depth of an IMT of the given `size`.
+    function depth(uint64 size) internal pure returns (uint256 depth_) {
+        while (uint256(1) << depth_ < size) {
+            depth_++;
+        }
+    }
+}
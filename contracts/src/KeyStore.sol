@@ -48,12 +48,14 @@ contract KeyStore {
     // /// @param currentValue The KeySpace record current value.
     // /// @param newValue The KeySpace record new value to set.
     // /// @param zkVmVkHash The zkVM record program verifier key hash.
+    // /// @param storageHash The KeySpace record storage hash, committed to by `currentValue`.
     // /// @param proof The record program proof wrapped in a PLONK BN254.
     // event ForcedTransactionSubmitted(
     //     bytes32 indexed keySpaceId,
     //     bytes32 indexed currentValue,
     //     bytes32 indexed newValue,
     //     bytes32 zkVmVkHash,
+    //     bytes32 storageHash,
     //     bytes proof
     // );
 
@@ -91,10 +93,14 @@ contract KeyStore {
 
     // /// @notice Submits a forced transaction.
     // /// @param forcedTx The forced transaction to submit.
+    // /// @param storageHash The KeySpace record storage hash, needed by the nodes to verify the `proof`.
     // /// @param proof The record program proof wrapped in a PLONK BN254.
-    // function submitForcedTransaction(Transaction calldata forcedTx, bytes32 zkVmVkHash, bytes calldata proof)
-    //     external
-    // {
+    // function submitForcedTransaction(
+    //     Transaction calldata forcedTx,
+    //     bytes32 zkVmVkHash,
+    //     bytes32 storageHash,
+    //     bytes calldata proof
+    // ) external {
     //     validateProof(proof);
 
     //     bytes32 newForcedTxsCommitment = keccak256(
@@ -112,6 +118,7 @@ contract KeyStore {
     //         currentValue: forcedTx.currentValue,
     //         newValue: forcedTx.newValue,
     //         zkVmVkHash: zkVmVkHash,
+    //         storageHash: storageHash,
     //         proof: proof
     //     });
     // }
@@ -0,0 +1,101 @@
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity ^0.8.20;
+
+import {Test} from "forge-std/Test.sol";
+
+import {ImtExclusionProof, ImtInclusionProof, ImtVerifier} from "../src/ImtVerifier.sol";
+
+/// @dev The test vectors are generated from the Rust imt with:
+///      `cargo run --example imt-test-vectors -- contracts/test/vectors/imt.json`
+contract ImtVerifierTest is Test {
+    string json;
+
+    function setUp() public {
+        json = vm.readFile(string.concat(vm.projectRoot(), "/test/vectors/imt.json"));
+    }
+
+    function testVerifyInclusion() public view {
+        bytes32[] memory roots = vm.parseJsonBytes32Array(json, ".inclusionRoots");
+        bytes[] memory proofs = vm.parseJsonBytesArray(json, ".inclusionProofs");
+        assertEq(roots.length, proofs.length);
+
+        for (uint256 i; i < proofs.length; i++) {
+            ImtInclusionProof memory proof = abi.decode(proofs[i], (ImtInclusionProof));
+            assertEq(this.verifyInclusion(roots[i], proof), proof.node.value);
+        }
+    }
+
+    function testVerifyExclusion() public view {
+        bytes32[] memory roots = vm.parseJsonBytes32Array(json, ".exclusionRoots");
+        bytes[] memory proofs = vm.parseJsonBytesArray(json, ".exclusionProofs");
+        assertEq(roots.length, proofs.length);
+
+        for (uint256 i; i < proofs.length; i++) {
+            this.verifyExclusion(roots[i], abi.decode(proofs[i], (ImtExclusionProof)));
+        }
+    }
+
+    function testVerifyInclusionRevertsOnStaleRoot() public {
+        ImtInclusionProof memory proof = lastInclusionProof();
+
+        vm.expectRevert(ImtVerifier.StaleRoot.selector);
+        this.verifyInclusion(bytes32(uint256(proof.root) + 1), proof);
+    }
+
+    function testVerifyInclusionRevertsOnSizeMismatch() public {
+        ImtInclusionProof memory proof = lastInclusionProof();
+        proof.size *= 2;
+
+        vm.expectRevert(ImtVerifier.SizeMismatch.selector);
+        this.verifyInclusion(proof.root, proof);
+    }
+
+    function testVerifyInclusionRevertsOnTamperedNode() public {
+        ImtInclusionProof memory proof = lastInclusionProof();
+        proof.node.value = keccak256("tampered");
+
+        vm.expectRevert(ImtVerifier.NodeDoesNotExist.selector);
+        this.verifyInclusion(proof.root, proof);
+    }
+
+    function testVerifyExclusionRevertsOnExistingKey() public {
+        ImtExclusionProof memory proof = lastExclusionProof();
+        proof.nodeKey = proof.lnNode.key;
+
+        vm.expectRevert(ImtVerifier.NodeAlreadyExist.selector);
+        this.verifyExclusion(proof.root, proof);
+    }
+
+    function testVerifyExclusionRevertsOnInvalidLowNullifier() public {
+        ImtExclusionProof memory proof = lastExclusionProof();
+        proof.nodeKey = proof.lnNode.nextKey;
+
+        vm.expectRevert(ImtVerifier.InvalidLowNullifier.selector);
+        this.verifyExclusion(proof.root, proof);
+    }
+
+    ////////////////////////////////////////////////////////////////////////////////////////////////////
+    //                                              HELPERS                                           //
+    ////////////////////////////////////////////////////////////////////////////////////////////////////
+
+    /// @dev External wrapper so that the library reverts can be caught by `vm.expectRevert`.
+    function verifyInclusion(bytes32 root, ImtInclusionProof calldata proof) external pure returns (bytes32) {
+        return ImtVerifier.verifyInclusion(root, proof);
+    }
+
+    /// @dev External wrapper so that the library reverts can be caught by `vm.expectRevert`.
+    function verifyExclusion(bytes32 root, ImtExclusionProof calldata proof) external pure {
+        ImtVerifier.verifyExclusion(root, proof);
+    }
+
+    function lastInclusionProof() internal view returns (ImtInclusionProof memory) {
+        bytes[] memory proofs = vm.parseJsonBytesArray(json, ".inclusionProofs");
+        return abi.decode(proofs[proofs.length - 1], (ImtInclusionProof));
+    }
+
+    /// @dev The last exclusion proof is the one of a deleted key, so its low nullifier has a next key.
+    function lastExclusionProof() internal view returns (ImtExclusionProof memory) {
+        bytes[] memory proofs = vm.parseJsonBytesArray(json, ".exclusionProofs");
+        return abi.decode(proofs[proofs.length - 1], (ImtExclusionProof));
+    }
+}
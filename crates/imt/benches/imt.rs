@@ -0,0 +1,112 @@
+//! Benchmarks of the imt operations over the in-memory [BTreeImtStorage].
+//!
+//! The benchmarked tree sizes can be overridden with a comma separated list of sizes:
+//! `IMT_BENCH_SIZES=1000,1000000 cargo bench -p keyspace-imt --features test-utils`.
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use std::env;
+use tiny_keccak::{Hasher, Keccak};
+
+use keyspace_imt::{storage::btree_imt_storage::BTreeImtStorage, tree::Imt, Hash256};
+
+type BenchImt = Imt<Keccak, Hash256, Hash256, BTreeImtStorage<Hash256, Hash256>>;
+
+const DEFAULT_SIZES: [u64; 3] = [1_000, 100_000, 1_000_000];
+
+/// Returns the tree sizes to benchmark.
+fn sizes() -> Vec<u64> {
+    env::var("IMT_BENCH_SIZES").map_or(DEFAULT_SIZES.to_vec(), |sizes| {
+        sizes
+            .split(',')
+            .map(|size| size.trim().parse().expect("invalid IMT_BENCH_SIZES"))
+            .collect()
+    })
+}
+
+/// Returns the i-th benchmark key, uniformly distributed over the key space.
+fn key(i: u64) -> Hash256 {
+    let mut hasher = Keccak::v256();
+    hasher.update(&i.to_be_bytes());
+
+    let mut key = [0; 32];
+    hasher.finalize(&mut key);
+    key
+}
+
+/// Builds an imt with `size` random (key; value) leaves: the keys `key(1)` to `key(size)`.
+fn build_imt(size: u64) -> BenchImt {
+    let leaves = (1..=size).map(|i| (key(i), key(i)));
+    Imt::from_leaves(Keccak::v256, BTreeImtStorage::default(), leaves).expect("from_leaves failed")
+}
+
+fn bench_imt(c: &mut Criterion) {
+    for size in sizes() {
+        let mut imt = build_imt(size);
+
+        let mut group = c.benchmark_group("imt");
+
+        // NOTE: Inserting grows the tree, which is negligible compared to its size.
+        let mut next_key = size;
+        group.bench_function(BenchmarkId::new("insert_node", size), |b| {
+            b.iter(|| {
+                next_key += 1;
+                imt.insert_node(key(next_key), [42; 32])
+                    .expect("insert failed")
+            })
+        });
+
+        let mut i = 0;
+        group.bench_function(BenchmarkId::new("update_node", size), |b| {
+            b.iter(|| {
+                i = i % size + 1;
+                imt.update_node(key(i), [42; 32]).expect("update failed")
+            })
+        });
+
+        group.bench_function(BenchmarkId::new("inclusion_proof", size), |b| {
+            b.iter(|| {
+                i = i % size + 1;
+                imt.inclusion_proof(key(i)).expect("inclusion proof failed")
+            })
+        });
+
+        group.bench_function(BenchmarkId::new("exclusion_proof", size), |b| {
+            b.iter(|| {
+                i = i % size + 1;
+                imt.exclusion_proof(key(u64::MAX - i))
+                    .expect("exclusion proof failed")
+            })
+        });
+
+        let proof = imt.inclusion_proof(key(1)).expect("inclusion proof failed");
+        group.bench_function(BenchmarkId::new("verify_inclusion_proof", size), |b| {
+            b.iter(|| proof.verify(Keccak::v256).expect("verify failed"))
+        });
+
+        let proof = imt
+            .exclusion_proof(key(u64::MAX))
+            .expect("exclusion proof failed");
+        group.bench_function(BenchmarkId::new("verify_exclusion_proof", size), |b| {
+            b.iter(|| proof.verify(Keccak::v256).expect("verify failed"))
+        });
+
+        group.finish();
+    }
+
+    // Only bench the bulk build at the smallest size as it is the most expensive operation.
+    let size = sizes().into_iter().min().unwrap_or(DEFAULT_SIZES[0]);
+    c.bench_function(&format!("imt/from_leaves/{size}"), |b| {
+        b.iter_batched(
+            || (1..=size).map(|i| (key(i), key(i))).collect::<Vec<_>>(),
+            |leaves| Imt::from_leaves(Keccak::v256, BTreeImtStorage::default(), leaves),
+            BatchSize::LargeInput,
+        )
+    });
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(20);
+    targets = bench_imt
+}
+criterion_main!(benches);
@@ -0,0 +1,39 @@
+/// How the depth of an imt is derived from its size.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ImtDepth {
+    /// The depth grows with the imt size, so that the tree is as shallow as possible.
+    #[default]
+    Dynamic,
+    /// The depth is fixed and the empty subtrees are hashed as zero subtrees.
+    ///
+    /// All the proofs have the same number of siblings, none of them being missing.
+    Fixed(u8),
+}
+
+impl ImtDepth {
+    /// Returns the depth of an imt of the given `size`.
+    pub fn at(&self, size: u64) -> u8 {
+        match self {
+            ImtDepth::Dynamic => depth(size),
+            ImtDepth::Fixed(depth) => *depth,
+        }
+    }
+
+    /// Returns the maximum size of an imt (including the 0 node).
+    pub fn capacity(&self) -> u64 {
+        match self {
+            ImtDepth::Dynamic => u64::MAX,
+            ImtDepth::Fixed(depth) => 1_u64.checked_shl(*depth as _).unwrap_or(u64::MAX),
+        }
+    }
+}
+
+/// Computes the depth of the tree based on its provided `size`.
+pub(crate) fn depth(size: u64) -> u8 {
+    let depth = (u64::BITS - size.leading_zeros() - 1) as u8;
+    if size == (1_u64 << depth) {
+        depth
+    } else {
+        depth + 1
+    }
+}
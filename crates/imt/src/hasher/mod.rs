@@ -0,0 +1,4 @@
+#[cfg(feature = "poseidon")]
+pub mod poseidon;
+#[cfg(feature = "sha256")]
+pub mod sha256;
@@ -0,0 +1,133 @@
+use ark_bn254::Fr;
+use light_poseidon::PoseidonBytesHasher;
+
+use crate::Hasher;
+
+/// The maximum number of inputs supported by the circom Poseidon parameters.
+const MAX_INPUTS: usize = 12;
+
+/// The inputs are split in chunks of 31 bytes so that each chunk always fits in a BN254 scalar
+/// field element.
+const CHUNK_LEN: usize = 31;
+
+/// A Poseidon (BN254, circom parameters) [Hasher] to use as the imt hash function.
+///
+/// The hashed bytes are buffered and only hashed when finalizing. They are split in chunks of
+/// 31 bytes prefixed by the total bytes length, and absorbed 12 inputs at a time (the previous
+/// hash being the first input of each subsequent permutation).
+#[derive(Debug, Clone, Default)]
+pub struct Poseidon {
+    data: Vec<u8>,
+}
+
+impl Poseidon {
+    /// Creates a new [Poseidon] hasher.
+    pub fn bn254() -> Self {
+        Self::default()
+    }
+}
+
+impl Hasher for Poseidon {
+    fn update(&mut self, input: &[u8]) {
+        self.data.extend_from_slice(input);
+    }
+
+    /// Writes the 32 bytes (big endian) field element digest in `output`.
+    ///
+    /// Panics if `output` is not 32 bytes long.
+    fn finalize(self, output: &mut [u8]) {
+        let len = (self.data.len() as u64).to_be_bytes();
+        let mut inputs = std::iter::once(len.as_slice()).chain(self.data.chunks(CHUNK_LEN));
+
+        let mut hash = poseidon(&inputs.by_ref().take(MAX_INPUTS).collect::<Vec<_>>());
+        loop {
+            let next_inputs = inputs.by_ref().take(MAX_INPUTS - 1).collect::<Vec<_>>();
+            if next_inputs.is_empty() {
+                break;
+            }
+
+            hash = poseidon(&[&[hash.as_slice()], next_inputs.as_slice()].concat());
+        }
+
+        output.copy_from_slice(&hash);
+    }
+}
+
+/// Hashes the given `inputs` (at most [MAX_INPUTS] big endian field elements).
+fn poseidon(inputs: &[&[u8]]) -> [u8; 32] {
+    light_poseidon::Poseidon::<Fr>::new_circom(inputs.len())
+        .and_then(|mut poseidon| poseidon.hash_bytes_be(inputs))
+        .expect("poseidon hash failed")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{storage::btree_imt_storage::BTreeImtStorage, tree::Imt};
+
+    #[test]
+    fn test_hash() {
+        let mut hasher = Poseidon::bn254();
+        hasher.update(&[1; 32]);
+        hasher.update(&[2; 32]);
+
+        let mut hash = [0; 32];
+        hasher.finalize(&mut hash);
+
+        // Manually hash the chunks to get the expected result.
+        let expected_hash = poseidon(&[
+            &64_u64.to_be_bytes(),
+            &[1; 31],
+            &[
+                1, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2,
+                2, 2, 2,
+            ],
+            &[2, 2],
+        ]);
+        assert_eq!(hash, expected_hash, "hashes do not match");
+    }
+
+    #[test]
+    fn test_hash_long_input() {
+        let mut hasher = Poseidon::bn254();
+        hasher.update(&[0xff; 31 * 20]);
+
+        let mut hash = [0; 32];
+        hasher.finalize(&mut hash);
+
+        // Manually hash the chunks to get the expected result.
+        let len = (31_u64 * 20).to_be_bytes();
+        let mut expected_hash =
+            poseidon(&[&[len.as_slice()], [[0xff; 31].as_slice(); 11].as_slice()].concat());
+        expected_hash = poseidon(
+            &[
+                &[expected_hash.as_slice()],
+                [[0xff; 31].as_slice(); 9].as_slice(),
+            ]
+            .concat(),
+        );
+        assert_eq!(hash, expected_hash, "hashes do not match");
+    }
+
+    #[test]
+    fn test_imt_proofs() {
+        let storage = BTreeImtStorage::default();
+        let mut imt = Imt::writer(Poseidon::bn254, storage).expect("imt writer failed");
+
+        // Ensure the proofs generated by an imt using [Poseidon] verify.
+        for key in [[1; 32], [3; 32], [2; 32]] {
+            let proof = imt.insert_node(key, [42; 32]).expect("insert failed");
+            let res = proof.verify(Poseidon::bn254, proof.old_root);
+            assert!(matches!(res, Ok(root) if root == imt.root().expect("root failed")));
+        }
+
+        let proof = imt.update_node([2; 32], [43; 32]).expect("update failed");
+        let res = proof.verify(Poseidon::bn254, proof.old_root);
+        assert!(matches!(res, Ok(root) if root == imt.root().expect("root failed")));
+
+        let proof = imt
+            .inclusion_proof([2; 32])
+            .expect("inclusion proof failed");
+        assert!(proof.verify(Poseidon::bn254).is_ok());
+    }
+}
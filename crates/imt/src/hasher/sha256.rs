@@ -0,0 +1,72 @@
+use sha2::Digest;
+
+use crate::Hasher;
+
+/// A SHA-256 [Hasher] to use as the imt hash function.
+#[derive(Debug, Clone, Default)]
+pub struct Sha256(sha2::Sha256);
+
+impl Sha256 {
+    /// Creates a new [Sha256] hasher.
+    pub fn v256() -> Self {
+        Self::default()
+    }
+}
+
+impl Hasher for Sha256 {
+    fn update(&mut self, input: &[u8]) {
+        self.0.update(input);
+    }
+
+    /// Writes the 32 bytes digest in `output`.
+    ///
+    /// Panics if `output` is not 32 bytes long.
+    fn finalize(self, output: &mut [u8]) {
+        output.copy_from_slice(&self.0.finalize());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{storage::btree_imt_storage::BTreeImtStorage, tree::Imt};
+
+    #[test]
+    fn test_hash() {
+        let mut hasher = Sha256::v256();
+        hasher.update(b"a");
+        hasher.update(b"bc");
+
+        let mut hash = [0; 32];
+        hasher.finalize(&mut hash);
+
+        let expected_hash = [
+            0xba, 0x78, 0x16, 0xbf, 0x8f, 0x01, 0xcf, 0xea, 0x41, 0x41, 0x40, 0xde, 0x5d, 0xae,
+            0x22, 0x23, 0xb0, 0x03, 0x61, 0xa3, 0x96, 0x17, 0x7a, 0x9c, 0xb4, 0x10, 0xff, 0x61,
+            0xf2, 0x00, 0x15, 0xad,
+        ];
+        assert_eq!(hash, expected_hash, "hashes do not match");
+    }
+
+    #[test]
+    fn test_imt_proofs() {
+        let storage = BTreeImtStorage::default();
+        let mut imt = Imt::writer(Sha256::v256, storage).expect("imt writer failed");
+
+        // Ensure the proofs generated by an imt using [Sha256] verify.
+        for key in [[1; 32], [3; 32], [2; 32]] {
+            let proof = imt.insert_node(key, [42; 32]).expect("insert failed");
+            let res = proof.verify(Sha256::v256, proof.old_root);
+            assert!(matches!(res, Ok(root) if root == imt.root().expect("root failed")));
+        }
+
+        let proof = imt.update_node([2; 32], [43; 32]).expect("update failed");
+        let res = proof.verify(Sha256::v256, proof.old_root);
+        assert!(matches!(res, Ok(root) if root == imt.root().expect("root failed")));
+
+        let proof = imt
+            .inclusion_proof([2; 32])
+            .expect("inclusion proof failed");
+        assert!(proof.verify(Sha256::v256).is_ok());
+    }
+}
@@ -1,12 +1,22 @@
+#![cfg_attr(not(feature = "std"), no_std)]
 #![feature(trait_alias)]
 
+extern crate alloc;
+
 use tiny_keccak::Hasher;
 
+pub mod depth;
+pub mod hasher;
 pub mod node;
 pub mod proof;
+#[cfg(feature = "std")]
 pub mod storage;
+#[cfg(feature = "std")]
 pub mod tree;
 
+#[cfg(test)]
+mod proptests;
+
 pub type Hash256 = [u8; 32];
 
 pub trait NodeKey = Default + Clone + Ord + AsRef<[u8]>;
@@ -1,4 +1,4 @@
-use std::fmt::Debug;
+use core::fmt::Debug;
 
 use serde::{Deserialize, Serialize};
 
@@ -6,6 +6,12 @@ use crate::Hash256;
 
 use super::{Hasher, NodeKey, NodeValue};
 
+/// The leaf hash cached at the index of a deleted [ImtNode].
+///
+/// Deleted nodes leave their index empty (the imt size is not decremented), so their leaf hash
+/// is replaced by this value which can not be obtained by hashing an actual [ImtNode].
+pub const DELETED_NODE_HASH: Hash256 = [0; 32];
+
 /// A node in the [crate::tree::Imt].
 #[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
 pub struct ImtNode<K, V> {
@@ -0,0 +1,206 @@
+use alloc::vec::Vec;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    depth::ImtDepth,
+    node::{ImtNode, DELETED_NODE_HASH},
+    Hash256, Hasher, NodeKey, NodeValue,
+};
+
+use super::{
+    ensure_siblings_depth, imt_root_from_leaf, imt_root_from_node, node_exists, ProofError,
+    ProofResult,
+};
+
+/// Deletion proof that can be verified for correctness.
+///
+/// The deletion is performed in two steps: the deleted node leaf is first cleared (replaced by
+/// [DELETED_NODE_HASH]) and the ln node is then updated to point to the deleted node `next_key`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct DeleteProof<K, V> {
+    pub old_root: Hash256,
+    pub size: u64,
+    pub node: ImtNode<K, V>,
+    pub node_siblings: Vec<Option<Hash256>>,
+
+    /// NOTE: The `ln_siblings` are the ones obtained AFTER clearing the deleted node leaf.
+    pub ln_node: ImtNode<K, V>,
+    pub ln_siblings: Vec<Option<Hash256>>,
+}
+
+impl<K, V> DeleteProof<K, V>
+where
+    K: NodeKey,
+    V: NodeValue,
+{
+    /// Verifies the [DeleteProof] and returns the new updated root.
+    ///
+    /// Before performing the deletion, the state is checked to make sure it is coherent.
+    pub fn verify<H: Hasher>(
+        &self,
+        hasher_factory: fn() -> H,
+        old_root: Hash256,
+    ) -> ProofResult<Hash256> {
+        self.verify_with_depth(hasher_factory, old_root, ImtDepth::Dynamic)
+    }
+
+    /// Same as [DeleteProof::verify] but for an imt with the given [ImtDepth].
+    pub fn verify_with_depth<H: Hasher>(
+        &self,
+        hasher_factory: fn() -> H,
+        old_root: Hash256,
+        depth: ImtDepth,
+    ) -> ProofResult<Hash256> {
+        // Make sure the ImtMutate old_root matches the expected old_root.
+        if old_root != self.old_root {
+            return Err(ProofError::StaleRoot);
+        }
+
+        // Make sure the 0 node is not being deleted.
+        if self.node.key == K::default() {
+            return Err(ProofError::NodeNotDeletable);
+        }
+
+        // Make sure the siblings match the imt size.
+        ensure_siblings_depth(depth, self.size, &self.node_siblings)?;
+        ensure_siblings_depth(depth, self.size, &self.ln_siblings)?;
+
+        // Verify that the node to delete is in the imt.
+        if !node_exists(
+            &self.old_root,
+            hasher_factory,
+            self.size,
+            &self.node,
+            &self.node_siblings,
+        ) {
+            return Err(ProofError::NodeDoesNotExist);
+        }
+
+        // Compute the intermediate root obtained after clearing the deleted node leaf.
+        let cleared_root = imt_root_from_leaf(
+            hasher_factory,
+            self.size,
+            self.node.index,
+            DELETED_NODE_HASH,
+            &self.node_siblings,
+        );
+
+        // Verify that the provided ln node is valid.
+        if self.ln_node.next_key != self.node.key
+            || !node_exists(
+                &cleared_root,
+                hasher_factory,
+                self.size,
+                &self.ln_node,
+                &self.ln_siblings,
+            )
+        {
+            return Err(ProofError::InvalidLowNullifier);
+        }
+
+        // Compute the new root from the updated ln node.
+        let updated_ln = ImtNode {
+            next_key: self.node.next_key.clone(),
+            ..self.ln_node.clone()
+        };
+
+        Ok(imt_root_from_node(
+            hasher_factory,
+            self.size,
+            &updated_ln,
+            &self.ln_siblings,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tiny_keccak::Keccak;
+
+    use crate::{proof::ProofError, storage::btree_imt_storage::BTreeImtStorage, tree::Imt};
+
+    #[test]
+    fn test_verify_invalid_old_root() {
+        // Instanciate an imt with a few nodes.
+        let storage = BTreeImtStorage::default();
+        let mut imt = Imt::writer(Keccak::v256, storage).expect("imt writer failed");
+        imt.insert_node([1; 32], [42; 32])
+            .expect("insert [1] failed");
+        imt.insert_node([2; 32], [42; 32])
+            .expect("insert [2] failed");
+        imt.insert_node([3; 32], [42; 32])
+            .expect("insert [3] failed");
+
+        // Create a DeleteProof and call `.verify()` with a different `old_root`.
+        let sut = imt.delete_node([2; 32]).expect("delete [2] failed");
+        let res = sut.verify(Keccak::v256, [0xff; 32]);
+        assert!(matches!(res, Err(ProofError::StaleRoot)));
+    }
+
+    #[test]
+    fn test_verify_node_does_not_exist() {
+        // Instanciate an imt with a few nodes.
+        let storage = BTreeImtStorage::default();
+        let mut imt = Imt::writer(Keccak::v256, storage).expect("imt writer failed");
+        imt.insert_node([1; 32], [42; 32])
+            .expect("insert [1] failed");
+        imt.insert_node([2; 32], [42; 32])
+            .expect("insert [2] failed");
+        imt.insert_node([3; 32], [42; 32])
+            .expect("insert [3] failed");
+
+        // Create a DeleteProof for a node that is not in the imt.
+        let mut sut = imt.delete_node([2; 32]).expect("delete [2] failed");
+        sut.node.key = [4; 32];
+        let res = sut.verify(Keccak::v256, sut.old_root);
+        assert!(matches!(res, Err(ProofError::NodeDoesNotExist)));
+    }
+
+    #[test]
+    fn test_verify_invalid_ln() {
+        // Instanciate an imt with a few nodes.
+        let storage = BTreeImtStorage::default();
+        let mut imt = Imt::writer(Keccak::v256, storage).expect("imt writer failed");
+        let insert_1 = imt
+            .insert_node([1; 32], [42; 32])
+            .expect("insert [1] failed");
+        imt.insert_node([2; 32], [42; 32])
+            .expect("insert [2] failed");
+        imt.insert_node([3; 32], [42; 32])
+            .expect("insert [3] failed");
+
+        // Use a `ln_node` that does not point to the deleted node.
+        let mut sut = imt.delete_node([3; 32]).expect("delete [3] failed");
+        sut.ln_node = insert_1.node;
+        let res = sut.verify(Keccak::v256, sut.old_root);
+        assert!(matches!(res, Err(ProofError::InvalidLowNullifier)));
+
+        // Use `ln_siblings` that are not the ones obtained after clearing the deleted node.
+        let mut sut = imt.delete_node([2; 32]).expect("delete [2] failed");
+        sut.ln_siblings[0] = Some([0xff; 32]);
+        let res = sut.verify(Keccak::v256, sut.old_root);
+        assert!(matches!(res, Err(ProofError::InvalidLowNullifier)));
+    }
+
+    #[test]
+    fn test_verify() {
+        let storage = BTreeImtStorage::default();
+        let mut imt = Imt::writer(Keccak::v256, storage).expect("imt writer failed");
+        let keys = vec![
+            [1; 32], [2; 32], [3; 32], [4; 32], [5; 32], [10; 32], [15; 32], [11; 32], [20; 32],
+            [16; 32], [25; 32],
+        ];
+
+        keys.iter().for_each(|node_key| {
+            imt.insert_node(*node_key, [42; 32])
+                .expect("insert [42] failed");
+        });
+
+        // Delete all the keys from the imt and ensure verifying the returned [DeleteProof] succeed.
+        keys.into_iter().for_each(|node_key| {
+            let sut = imt.delete_node(node_key).expect("delete failed");
+            let res = sut.verify(Keccak::v256, sut.old_root);
+            assert!(matches!(res, Ok(root) if root == imt.root().expect("root failed")));
+        });
+    }
+}
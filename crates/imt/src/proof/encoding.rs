@@ -0,0 +1,441 @@
+use alloc::{vec, vec::Vec};
+use thiserror::Error;
+
+use crate::{node::ImtNode, Hash256, NodeKey, NodeValue};
+
+use super::{
+    exclusion::ExclusionProof, inclusion::InclusionProof, insert::InsertProof, update::UpdateProof,
+};
+
+// Canonical compact binary encoding of the imt proofs.
+//
+// Every encoded proof starts with the encoding version byte followed by the proof tag byte.
+// The fields are then encoded in their declaration order:
+//   - `u64` values are LEB128 varints (without any superfluous trailing zero byte),
+//   - [Hash256] values are their 32 raw bytes,
+//   - node keys and values are prefixed with their varint length,
+//   - siblings are prefixed with their varint count and a bitmap (one bit per sibling, LSB first,
+//     zero padded) flagging the present ones, followed by the present hashes only.
+
+/// The version of the proof encoding.
+pub const ENCODING_VERSION: u8 = 1;
+
+const INCLUSION_PROOF_TAG: u8 = 0;
+const EXCLUSION_PROOF_TAG: u8 = 1;
+const INSERT_PROOF_TAG: u8 = 2;
+const UPDATE_PROOF_TAG: u8 = 3;
+
+/// The reasons for which an encoded imt proof can be rejected.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    #[error("unsupported encoding version `{0}`")]
+    UnsupportedVersion(u8),
+    #[error("unexpected proof tag `{0}`")]
+    UnexpectedTag(u8),
+    #[error("unexpected end of input")]
+    UnexpectedEnd,
+    #[error("invalid varint")]
+    InvalidVarint,
+    #[error("invalid siblings bitmap")]
+    InvalidBitmap,
+    #[error("invalid {0}")]
+    InvalidBytes(&'static str),
+    #[error("{0} trailing bytes")]
+    TrailingBytes(usize),
+}
+
+pub type DecodeResult<T> = Result<T, DecodeError>;
+
+/// Trait for the imt proofs that can be encoded to (and decoded from) the compact binary format.
+pub trait CompactEncoding: Sized {
+    /// Encodes the proof.
+    fn to_bytes(&self) -> Vec<u8>;
+
+    /// Decodes a proof from the given `bytes`, which must be exactly one encoded proof.
+    fn from_bytes(bytes: &[u8]) -> DecodeResult<Self>;
+}
+
+impl<K, V> CompactEncoding for InclusionProof<K, V>
+where
+    K: NodeKey + for<'a> TryFrom<&'a [u8]>,
+    V: NodeValue + for<'a> TryFrom<&'a [u8]>,
+{
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut encoder = Encoder::new(INCLUSION_PROOF_TAG);
+        encoder.hash(&self.root);
+        encoder.varint(self.size);
+        encoder.node(&self.node);
+        encoder.siblings(&self.siblings);
+        encoder.bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> DecodeResult<Self> {
+        let mut decoder = Decoder::new(bytes, INCLUSION_PROOF_TAG)?;
+        let proof = Self {
+            root: decoder.hash()?,
+            size: decoder.varint()?,
+            node: decoder.node()?,
+            siblings: decoder.siblings()?,
+        };
+
+        decoder.finish()?;
+        Ok(proof)
+    }
+}
+
+impl<K, V> CompactEncoding for ExclusionProof<K, V>
+where
+    K: NodeKey + for<'a> TryFrom<&'a [u8]>,
+    V: NodeValue + for<'a> TryFrom<&'a [u8]>,
+{
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut encoder = Encoder::new(EXCLUSION_PROOF_TAG);
+        encoder.hash(&self.root);
+        encoder.varint(self.size);
+        encoder.node(&self.ln_node);
+        encoder.siblings(&self.ln_siblings);
+        encoder.data(&self.node_key);
+        encoder.bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> DecodeResult<Self> {
+        let mut decoder = Decoder::new(bytes, EXCLUSION_PROOF_TAG)?;
+        let proof = Self {
+            root: decoder.hash()?,
+            size: decoder.varint()?,
+            ln_node: decoder.node()?,
+            ln_siblings: decoder.siblings()?,
+            node_key: decoder.data("node key")?,
+        };
+
+        decoder.finish()?;
+        Ok(proof)
+    }
+}
+
+impl<K, V> CompactEncoding for InsertProof<K, V>
+where
+    K: NodeKey + for<'a> TryFrom<&'a [u8]>,
+    V: NodeValue + for<'a> TryFrom<&'a [u8]>,
+{
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut encoder = Encoder::new(INSERT_PROOF_TAG);
+        encoder.hash(&self.old_root);
+        encoder.varint(self.old_size);
+        encoder.node(&self.ln_node);
+        encoder.siblings(&self.ln_siblings);
+        encoder.node(&self.node);
+        encoder.siblings(&self.node_siblings);
+        encoder.siblings(&self.updated_ln_siblings);
+        encoder.bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> DecodeResult<Self> {
+        let mut decoder = Decoder::new(bytes, INSERT_PROOF_TAG)?;
+        let proof = Self {
+            old_root: decoder.hash()?,
+            old_size: decoder.varint()?,
+            ln_node: decoder.node()?,
+            ln_siblings: decoder.siblings()?,
+            node: decoder.node()?,
+            node_siblings: decoder.siblings()?,
+            updated_ln_siblings: decoder.siblings()?,
+        };
+
+        decoder.finish()?;
+        Ok(proof)
+    }
+}
+
+impl<K, V> CompactEncoding for UpdateProof<K, V>
+where
+    K: NodeKey + for<'a> TryFrom<&'a [u8]>,
+    V: NodeValue + for<'a> TryFrom<&'a [u8]>,
+{
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut encoder = Encoder::new(UPDATE_PROOF_TAG);
+        encoder.hash(&self.old_root);
+        encoder.varint(self.size);
+        encoder.node(&self.node);
+        encoder.siblings(&self.node_siblings);
+        encoder.data(&self.new_value);
+        encoder.bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> DecodeResult<Self> {
+        let mut decoder = Decoder::new(bytes, UPDATE_PROOF_TAG)?;
+        let proof = Self {
+            old_root: decoder.hash()?,
+            size: decoder.varint()?,
+            node: decoder.node()?,
+            node_siblings: decoder.siblings()?,
+            new_value: decoder.data("new value")?,
+        };
+
+        decoder.finish()?;
+        Ok(proof)
+    }
+}
+
+/// Writes the proof fields to the compact binary format.
+struct Encoder {
+    bytes: Vec<u8>,
+}
+
+impl Encoder {
+    /// Creates a new [Encoder] for the proof with the given `tag`.
+    fn new(tag: u8) -> Self {
+        Self {
+            bytes: vec![ENCODING_VERSION, tag],
+        }
+    }
+
+    fn varint(&mut self, mut value: u64) {
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+
+            if value == 0 {
+                self.bytes.push(byte);
+                return;
+            }
+
+            self.bytes.push(byte | 0x80);
+        }
+    }
+
+    fn hash(&mut self, hash: &Hash256) {
+        self.bytes.extend_from_slice(hash);
+    }
+
+    fn data(&mut self, data: &impl AsRef<[u8]>) {
+        let data = data.as_ref();
+        self.varint(data.len() as u64);
+        self.bytes.extend_from_slice(data);
+    }
+
+    fn node<K: AsRef<[u8]>, V: AsRef<[u8]>>(&mut self, node: &ImtNode<K, V>) {
+        self.varint(node.index);
+        self.data(&node.key);
+        self.data(&node.value);
+        self.data(&node.next_key);
+    }
+
+    fn siblings(&mut self, siblings: &[Option<Hash256>]) {
+        self.varint(siblings.len() as u64);
+
+        let mut bitmap = vec![0_u8; siblings.len().div_ceil(8)];
+        for (i, sibling) in siblings.iter().enumerate() {
+            if sibling.is_some() {
+                bitmap[i / 8] |= 1 << (i % 8);
+            }
+        }
+        self.bytes.extend_from_slice(&bitmap);
+
+        for sibling in siblings.iter().flatten() {
+            self.hash(sibling);
+        }
+    }
+}
+
+/// Reads the proof fields from the compact binary format.
+struct Decoder<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Decoder<'a> {
+    /// Creates a new [Decoder] for the proof with the given `tag`.
+    fn new(bytes: &'a [u8], tag: u8) -> DecodeResult<Self> {
+        let mut decoder = Self { bytes };
+
+        let version = decoder.take(1)?[0];
+        if version != ENCODING_VERSION {
+            return Err(DecodeError::UnsupportedVersion(version));
+        }
+
+        let proof_tag = decoder.take(1)?[0];
+        if proof_tag != tag {
+            return Err(DecodeError::UnexpectedTag(proof_tag));
+        }
+
+        Ok(decoder)
+    }
+
+    /// Makes sure all the bytes have been decoded.
+    fn finish(self) -> DecodeResult<()> {
+        match self.bytes.len() {
+            0 => Ok(()),
+            len => Err(DecodeError::TrailingBytes(len)),
+        }
+    }
+
+    fn take(&mut self, len: usize) -> DecodeResult<&'a [u8]> {
+        if self.bytes.len() < len {
+            return Err(DecodeError::UnexpectedEnd);
+        }
+
+        let (bytes, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(bytes)
+    }
+
+    fn varint(&mut self) -> DecodeResult<u64> {
+        let mut value = 0_u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.take(1)?[0];
+            let bits = (byte & 0x7f) as u64;
+
+            // Reject overflowing and non canonical (zero terminated) varints.
+            if bits << shift >> shift != bits || (shift > 0 && byte == 0) {
+                return Err(DecodeError::InvalidVarint);
+            }
+
+            value |= bits << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+
+        Err(DecodeError::InvalidVarint)
+    }
+
+    fn len(&mut self) -> DecodeResult<usize> {
+        let len = self.varint()?;
+        usize::try_from(len).map_err(|_| DecodeError::UnexpectedEnd)
+    }
+
+    fn hash(&mut self) -> DecodeResult<Hash256> {
+        Ok(self
+            .take(32)?
+            .try_into()
+            .expect("hash should be 32 bytes long"))
+    }
+
+    fn data<T>(&mut self, what: &'static str) -> DecodeResult<T>
+    where
+        T: for<'b> TryFrom<&'b [u8]>,
+    {
+        let len = self.len()?;
+        T::try_from(self.take(len)?).map_err(|_| DecodeError::InvalidBytes(what))
+    }
+
+    fn node<K, V>(&mut self) -> DecodeResult<ImtNode<K, V>>
+    where
+        K: for<'b> TryFrom<&'b [u8]>,
+        V: for<'b> TryFrom<&'b [u8]>,
+    {
+        Ok(ImtNode {
+            index: self.varint()?,
+            key: self.data("node key")?,
+            value: self.data("node value")?,
+            next_key: self.data("node next key")?,
+        })
+    }
+
+    fn siblings(&mut self) -> DecodeResult<Vec<Option<Hash256>>> {
+        let count = self.len()?;
+        let bitmap = self.take(count.div_ceil(8))?;
+
+        // Make sure the padding bits are not set.
+        if count % 8 != 0 && bitmap[count / 8] >> (count % 8) != 0 {
+            return Err(DecodeError::InvalidBitmap);
+        }
+
+        (0..count)
+            .map(|i| match bitmap[i / 8] & (1 << (i % 8)) {
+                0 => Ok(None),
+                _ => self.hash().map(Some),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tiny_keccak::Keccak;
+
+    use crate::{storage::btree_imt_storage::BTreeImtStorage, tree::Imt};
+
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        // Instanciate an imt with a few nodes.
+        let storage = BTreeImtStorage::default();
+        let mut imt = Imt::writer(Keccak::v256, storage).expect("imt writer failed");
+        for key in [[1; 32], [3; 32], [5; 32]] {
+            imt.insert_node(key, [42; 32]).expect("insert failed");
+        }
+
+        let insert = imt
+            .insert_node([4; 32], [42; 32])
+            .expect("insert [4] failed");
+        assert!(insert.node_siblings.contains(&None));
+        let bytes = insert.to_bytes();
+        assert_eq!(InsertProof::from_bytes(&bytes), Ok(insert.clone()));
+
+        let update = imt
+            .update_node([1; 32], [43; 32])
+            .expect("update [1] failed");
+        assert_eq!(UpdateProof::from_bytes(&update.to_bytes()), Ok(update));
+
+        let inclusion = imt
+            .inclusion_proof([5; 32])
+            .expect("inclusion proof failed");
+        assert_eq!(
+            InclusionProof::from_bytes(&inclusion.to_bytes()),
+            Ok(inclusion)
+        );
+
+        let exclusion = imt
+            .exclusion_proof([2; 32])
+            .expect("exclusion proof failed");
+        let bytes = exclusion.to_bytes();
+        assert_eq!(ExclusionProof::from_bytes(&bytes), Ok(exclusion));
+
+        // Ensure a proof can not be decoded as another kind of proof.
+        let res = InclusionProof::<[u8; 32], [u8; 32]>::from_bytes(&bytes);
+        assert_eq!(res, Err(DecodeError::UnexpectedTag(EXCLUSION_PROOF_TAG)));
+    }
+
+    #[test]
+    fn test_decode_invalid_bytes() {
+        // Instanciate an imt with a few nodes.
+        let storage = BTreeImtStorage::default();
+        let mut imt = Imt::writer(Keccak::v256, storage).expect("imt writer failed");
+        for key in [[1; 32], [3; 32], [5; 32]] {
+            imt.insert_node(key, [42; 32]).expect("insert failed");
+        }
+
+        let bytes = imt
+            .inclusion_proof([3; 32])
+            .expect("inclusion proof failed")
+            .to_bytes();
+        let decode = InclusionProof::<[u8; 32], [u8; 32]>::from_bytes;
+
+        let mut sut = bytes.clone();
+        sut[0] = ENCODING_VERSION + 1;
+        assert_eq!(
+            decode(&sut),
+            Err(DecodeError::UnsupportedVersion(ENCODING_VERSION + 1))
+        );
+
+        let sut = &bytes[..bytes.len() - 1];
+        assert_eq!(decode(sut), Err(DecodeError::UnexpectedEnd));
+
+        let mut sut = bytes.clone();
+        sut.push(0);
+        assert_eq!(decode(&sut), Err(DecodeError::TrailingBytes(1)));
+
+        // Ensure non canonical varints are rejected (the size follows the version, tag and root).
+        let mut sut = bytes.clone();
+        sut[34] |= 0x80;
+        sut.insert(35, 0);
+        assert_eq!(decode(&sut), Err(DecodeError::InvalidVarint));
+
+        // Ensure a key with an invalid length is rejected.
+        let mut sut = bytes.clone();
+        sut[36] = 31;
+        assert_eq!(decode(&sut), Err(DecodeError::InvalidBytes("node key")));
+    }
+}
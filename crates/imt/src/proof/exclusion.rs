@@ -1,8 +1,10 @@
-use anyhow::{ensure, Result};
+use alloc::vec::Vec;
 use serde::{Deserialize, Serialize};
 use tiny_keccak::Hasher;
 
-use crate::{node::ImtNode, proof::node_exists, Hash256, NodeKey, NodeValue};
+use crate::{depth::ImtDepth, node::ImtNode, Hash256, NodeKey, NodeValue};
+
+use super::{ensure_siblings_depth, node_exists, ProofError, ProofResult};
 
 #[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
 pub struct ExclusionProof<K, V> {
@@ -18,22 +20,35 @@ where
     K: NodeKey,
     V: NodeValue,
 {
-    pub fn verify<H: Hasher>(&self, hasher_factory: fn() -> H) -> Result<()> {
-        ensure!(
-            node_exists(
-                &self.root,
-                hasher_factory,
-                self.size,
-                &self.ln_node,
-                &self.ln_siblings,
-            ),
-            "ln node does not exist"
-        );
-
-        ensure!(
-            self.ln_node.is_ln_of(&self.node_key),
-            "ln node is invalid for the given key"
-        );
+    pub fn verify<H: Hasher>(&self, hasher_factory: fn() -> H) -> ProofResult<()> {
+        self.verify_with_depth(hasher_factory, ImtDepth::Dynamic)
+    }
+
+    /// Same as [ExclusionProof::verify] but for an imt with the given [ImtDepth].
+    pub fn verify_with_depth<H: Hasher>(
+        &self,
+        hasher_factory: fn() -> H,
+        depth: ImtDepth,
+    ) -> ProofResult<()> {
+        ensure_siblings_depth(depth, self.size, &self.ln_siblings)?;
+
+        if !node_exists(
+            &self.root,
+            hasher_factory,
+            self.size,
+            &self.ln_node,
+            &self.ln_siblings,
+        ) {
+            return Err(ProofError::InvalidLowNullifier);
+        }
+
+        if self.ln_node.key == self.node_key {
+            return Err(ProofError::NodeAlreadyExist);
+        }
+
+        if !self.ln_node.is_ln_of(&self.node_key) {
+            return Err(ProofError::InvalidLowNullifier);
+        }
 
         Ok(())
     }
@@ -1,10 +1,10 @@
-use anyhow::{ensure, Ok, Result};
+use alloc::vec::Vec;
 use serde::{Deserialize, Serialize};
 use tiny_keccak::Hasher;
 
-use crate::{node::ImtNode, Hash256, NodeKey, NodeValue};
+use crate::{depth::ImtDepth, node::ImtNode, Hash256, NodeKey, NodeValue};
 
-use super::node_exists;
+use super::{ensure_siblings_depth, node_exists, ProofError, ProofResult};
 
 #[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
 pub struct InclusionProof<K, V> {
@@ -19,17 +19,27 @@ where
     K: NodeKey,
     V: NodeValue,
 {
-    pub fn verify<H: Hasher>(&self, hasher_factory: fn() -> H) -> Result<()> {
-        ensure!(
-            node_exists(
-                &self.root,
-                hasher_factory,
-                self.size,
-                &self.node,
-                &self.siblings,
-            ),
-            "node does not exist"
-        );
+    pub fn verify<H: Hasher>(&self, hasher_factory: fn() -> H) -> ProofResult<()> {
+        self.verify_with_depth(hasher_factory, ImtDepth::Dynamic)
+    }
+
+    /// Same as [InclusionProof::verify] but for an imt with the given [ImtDepth].
+    pub fn verify_with_depth<H: Hasher>(
+        &self,
+        hasher_factory: fn() -> H,
+        depth: ImtDepth,
+    ) -> ProofResult<()> {
+        ensure_siblings_depth(depth, self.size, &self.siblings)?;
+
+        if !node_exists(
+            &self.root,
+            hasher_factory,
+            self.size,
+            &self.node,
+            &self.siblings,
+        ) {
+            return Err(ProofError::NodeDoesNotExist);
+        }
 
         Ok(())
     }
@@ -1,9 +1,9 @@
-use anyhow::{anyhow, ensure, Result};
+use alloc::vec::Vec;
 use serde::{Deserialize, Serialize};
 
-use crate::{node::ImtNode, Hash256, Hasher, NodeKey, NodeValue};
+use crate::{depth::ImtDepth, node::ImtNode, Hash256, Hasher, NodeKey, NodeValue};
 
-use super::{imt_root_from_node, node_exists};
+use super::{ensure_siblings_depth, imt_root_from_node, node_exists, ProofError, ProofResult};
 
 /// Insertion proof that can be verified for correctness.
 #[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
@@ -32,15 +32,34 @@ where
         &self,
         hasher_factory: fn() -> H,
         old_root: Hash256,
-    ) -> Result<Hash256> {
+    ) -> ProofResult<Hash256> {
+        self.verify_with_depth(hasher_factory, old_root, ImtDepth::Dynamic)
+    }
+
+    /// Same as [InsertProof::verify] but for an imt with the given [ImtDepth].
+    pub fn verify_with_depth<H: Hasher>(
+        &self,
+        hasher_factory: fn() -> H,
+        old_root: Hash256,
+        depth: ImtDepth,
+    ) -> ProofResult<Hash256> {
         // Make sure the ImtMutate old_root matches the expected old_root.
-        ensure!(old_root == self.old_root, "ImtMutate.old_root is stale");
+        if old_root != self.old_root {
+            return Err(ProofError::StaleRoot);
+        }
+
+        let new_size = self
+            .old_size
+            .checked_add(1)
+            .ok_or(ProofError::SizeOverflow)?;
+
+        // Make sure the siblings match the imt sizes.
+        ensure_siblings_depth(depth, self.old_size, &self.ln_siblings)?;
+        ensure_siblings_depth(depth, new_size, &self.node_siblings)?;
+        ensure_siblings_depth(depth, new_size, &self.updated_ln_siblings)?;
 
         // Verify that the provided ln node is valid.
-        ensure!(
-            self.is_valid_ln(hasher_factory),
-            "ImtMutate.ln_node is invalid"
-        );
+        self.verify_ln(hasher_factory)?;
 
         // Compute the updated root from the node and the updated ln node.
         let updated_ln = ImtNode {
@@ -48,11 +67,6 @@ where
             ..self.ln_node.clone()
         };
 
-        let new_size = self
-            .old_size
-            .checked_add(1)
-            .ok_or(anyhow!("imt size overflow"))?;
-
         let root_from_node =
             imt_root_from_node(hasher_factory, new_size, &self.node, &self.node_siblings);
 
@@ -64,24 +78,28 @@ where
         );
 
         // Make sure both roots are equal.
-        ensure!(
-            root_from_node == root_from_updated_ln,
-            "ImtMutate.updated_ln_siblings is invalid"
-        );
+        if root_from_node != root_from_updated_ln {
+            return Err(ProofError::SiblingsMismatch);
+        }
 
         Ok(root_from_node)
     }
 
-    /// Returns `true` if `self.ln_node` is a valid ln node for `self.node`.
-    fn is_valid_ln<H: Hasher>(&self, hasher_factory: fn() -> H) -> bool {
-        self.ln_node.is_ln_of(&self.node.key)
-            && node_exists(
-                &self.old_root,
-                hasher_factory,
-                self.old_size,
-                &self.ln_node,
-                &self.ln_siblings,
-            )
+    /// Verifies that `self.ln_node` is a valid ln node for `self.node`.
+    fn verify_ln<H: Hasher>(&self, hasher_factory: fn() -> H) -> ProofResult<()> {
+        let ln_exists = node_exists(
+            &self.old_root,
+            hasher_factory,
+            self.old_size,
+            &self.ln_node,
+            &self.ln_siblings,
+        );
+
+        match ln_exists {
+            true if self.ln_node.key == self.node.key => Err(ProofError::NodeAlreadyExist),
+            true if self.ln_node.is_ln_of(&self.node.key) => Ok(()),
+            _ => Err(ProofError::InvalidLowNullifier),
+        }
     }
 }
 
@@ -89,13 +107,15 @@ where
 mod tests {
     use tiny_keccak::Keccak;
 
-    use crate::{node::ImtNode, storage::btree_imt_storage::BTreeImtStorage, tree::Imt};
+    use crate::{
+        node::ImtNode, proof::ProofError, storage::btree_imt_storage::BTreeImtStorage, tree::Imt,
+    };
 
     #[test]
     fn test_verify_invalid_old_root() {
         // Instanciate an imt with a few nodes.
         let storage = BTreeImtStorage::default();
-        let mut imt = Imt::writer(Keccak::v256, storage);
+        let mut imt = Imt::writer(Keccak::v256, storage).expect("imt writer failed");
         imt.insert_node([1; 32], [42; 32])
             .expect("insert [1] failed");
         imt.insert_node([2; 32], [42; 32])
@@ -108,23 +128,23 @@ mod tests {
             .insert_node([4; 32], [42; 32])
             .expect("insert [4] failed");
         let res = sut.verify(Keccak::v256, [0xff; 32]);
-        assert!(matches!(res, Err(e) if e.to_string() == "ImtMutate.old_root is stale"));
+        assert!(matches!(res, Err(ProofError::StaleRoot)));
 
         // Create an InsertProof and call `.verify()` with a different `old_root`.
-        let old_root = imt.root();
+        let old_root = imt.root().expect("root failed");
         let mut sut = imt
             .insert_node([5; 32], [42; 32])
             .expect("insert [5] failed");
         sut.old_root = [0xff; 32];
         let res = sut.verify(Keccak::v256, old_root);
-        assert!(matches!(res, Err(e) if e.to_string() == "ImtMutate.old_root is stale"));
+        assert!(matches!(res, Err(ProofError::StaleRoot)));
     }
 
     #[test]
     fn test_verify_invalid_ln() {
         // Instanciate an imt with a few nodes.
         let storage = BTreeImtStorage::default();
-        let mut imt = Imt::writer(Keccak::v256, storage);
+        let mut imt = Imt::writer(Keccak::v256, storage).expect("imt writer failed");
 
         let insert_1 = imt
             .insert_node([1; 32], [42; 32])
@@ -140,7 +160,7 @@ mod tests {
             .expect("insert [4] failed");
         sut.ln_node = ln_node;
         let res = sut.verify(Keccak::v256, sut.old_root);
-        assert!(matches!(res, Err(e) if e.to_string() == "ImtMutate.ln_node is invalid"));
+        assert!(matches!(res, Err(ProofError::InvalidLowNullifier)));
 
         // Use a `ln_node` with an invalid `next_key`.
         let ln_node = insert_1.node;
@@ -149,7 +169,7 @@ mod tests {
             .expect("insert [6] failed");
         sut.ln_node = ln_node;
         let res = sut.verify(Keccak::v256, sut.old_root);
-        assert!(matches!(res, Err(e) if e.to_string() == "ImtMutate.ln_node is invalid"));
+        assert!(matches!(res, Err(ProofError::InvalidLowNullifier)));
 
         // Use a `ln_node` that is not in the tree.
         let ln_node = ImtNode {
@@ -163,14 +183,38 @@ mod tests {
             .expect("insert [8] failed");
         sut.ln_node = ln_node;
         let res = sut.verify(Keccak::v256, sut.old_root);
-        assert!(matches!(res, Err(e) if e.to_string() == "ImtMutate.ln_node is invalid"));
+        assert!(matches!(res, Err(ProofError::InvalidLowNullifier)));
+    }
+
+    #[test]
+    fn test_verify_node_already_exist() {
+        // Instanciate an imt with a few nodes.
+        let storage = BTreeImtStorage::default();
+        let mut imt = Imt::writer(Keccak::v256, storage).expect("imt writer failed");
+        imt.insert_node([1; 32], [42; 32])
+            .expect("insert [1] failed");
+        imt.insert_node([2; 32], [42; 32])
+            .expect("insert [2] failed");
+
+        // Create an InsertProof for a key that is already in the imt, using the existing node as ln.
+        let inclusion = imt
+            .inclusion_proof([2; 32])
+            .expect("inclusion proof failed");
+        let mut sut = imt
+            .insert_node([3; 32], [42; 32])
+            .expect("insert [3] failed");
+        sut.node.key = [2; 32];
+        sut.ln_node = inclusion.node;
+        sut.ln_siblings = inclusion.siblings;
+        let res = sut.verify(Keccak::v256, sut.old_root);
+        assert!(matches!(res, Err(ProofError::NodeAlreadyExist)));
     }
 
     #[test]
     fn test_verify_invalid_updated_ln_siblings() {
         // Instanciate an imt with a few nodes.
         let storage = BTreeImtStorage::default();
-        let mut imt = Imt::writer(Keccak::v256, storage);
+        let mut imt = Imt::writer(Keccak::v256, storage).expect("imt writer failed");
         imt.insert_node([1; 32], [42; 32])
             .expect("insert [1] failed");
         imt.insert_node([2; 32], [42; 32])
@@ -185,15 +229,13 @@ mod tests {
             .expect("insert [4] failed");
         sut.updated_ln_siblings[0] = Some([0xff; 32]);
         let res = sut.verify(Keccak::v256, sut.old_root);
-        assert!(
-            matches!(res, Err(e) if e.to_string() == "ImtMutate.updated_ln_siblings is invalid")
-        );
+        assert!(matches!(res, Err(ProofError::SiblingsMismatch)));
     }
 
     #[test]
     fn test_verify() {
         let storage = BTreeImtStorage::default();
-        let mut imt = Imt::writer(Keccak::v256, storage);
+        let mut imt = Imt::writer(Keccak::v256, storage).expect("imt writer failed");
         let keys = vec![
             [1; 32], [2; 32], [3; 32], [4; 32], [5; 32], [10; 32], [15; 32], [11; 32], [20; 32],
             [16; 32], [25; 32],
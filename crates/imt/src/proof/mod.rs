@@ -1,11 +1,46 @@
+pub mod delete;
+pub mod encoding;
 pub mod exclusion;
 pub mod inclusion;
 pub mod insert;
+pub mod multi;
 pub mod mutate;
 pub mod node;
 pub mod update;
 
-use crate::{node::ImtNode, Hash256, Hasher, NodeKey, NodeValue};
+use alloc::{string::String, vec::Vec};
+use thiserror::Error;
+
+use crate::{depth::ImtDepth, node::ImtNode, Hash256, Hasher, NodeKey, NodeValue};
+
+/// The reasons for which an imt proof can be rejected.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ProofError {
+    #[error("old root is stale")]
+    StaleRoot,
+    #[error("size does not match the siblings")]
+    SizeMismatch,
+    #[error("imt size overflow")]
+    SizeOverflow,
+    #[error("node is already in the imt")]
+    NodeAlreadyExist,
+    #[error("node is not in the imt")]
+    NodeDoesNotExist,
+    #[error("node can not be deleted")]
+    NodeNotDeletable,
+    #[error("low nullifier is invalid")]
+    InvalidLowNullifier,
+    #[error("siblings are invalid")]
+    SiblingsMismatch,
+    #[error("nodes are not sorted by index")]
+    UnsortedNodes,
+    #[error("no key to prove")]
+    EmptyKeys,
+    #[error("key `{0}` is not proven by any node")]
+    MissingKey(String),
+}
+
+pub type ProofResult<T> = Result<T, ProofError>;
 
 /// Computes the imt root from the given [ImtNode] and its siblings.
 fn imt_root_from_node<H, K, V>(
@@ -19,9 +54,26 @@ where
     K: NodeKey,
     V: NodeValue,
 {
-    let mut hash = node.hash(hasher_factory());
+    imt_root_from_leaf(
+        hasher_factory,
+        size,
+        node.index,
+        node.hash(hasher_factory()),
+        siblings,
+    )
+}
 
-    let mut index = node.index;
+/// Computes the imt root from the given leaf `hash` at `index` and its siblings.
+fn imt_root_from_leaf<H>(
+    hasher_factory: fn() -> H,
+    size: u64,
+    mut index: u64,
+    mut hash: Hash256,
+    siblings: &Vec<Option<Hash256>>,
+) -> Hash256
+where
+    H: Hasher,
+{
     for sibling in siblings {
         let node_hash = Some(hash);
 
@@ -31,30 +83,87 @@ where
             (sibling, &node_hash)
         };
 
-        let mut hasher = hasher_factory();
-        match (left, right) {
-            (None, None) => unreachable!(),
-            (None, Some(right)) => hasher.update(right),
-            (Some(left), None) => hasher.update(left),
-            (Some(left), Some(right)) => {
-                hasher.update(left);
-                hasher.update(right);
-            }
-        };
-
-        hasher.finalize(&mut hash);
+        hash = hash_children(hasher_factory, left, right);
 
         index /= 2;
     }
 
+    root_with_size(hasher_factory, &hash, size)
+}
+
+/// Hashes the given `left` and `right` children hashes, skipping the missing one (if any).
+pub(crate) fn hash_children<H>(
+    hasher_factory: fn() -> H,
+    left: &Option<Hash256>,
+    right: &Option<Hash256>,
+) -> Hash256
+where
+    H: Hasher,
+{
     let mut hasher = hasher_factory();
-    hasher.update(&hash);
-    hasher.update(&size.to_be_bytes());
+    match (left, right) {
+        (None, None) => unreachable!(),
+        (None, Some(right)) => hasher.update(right),
+        (Some(left), None) => hasher.update(left),
+        (Some(left), Some(right)) => {
+            hasher.update(left);
+            hasher.update(right);
+        }
+    };
+
+    let mut hash = [0; 32];
     hasher.finalize(&mut hash);
+    hash
+}
+
+/// Computes the imt root (including the size) from the tree `root` and its `size`.
+pub(crate) fn root_with_size<H>(hasher_factory: fn() -> H, root: &Hash256, size: u64) -> Hash256
+where
+    H: Hasher,
+{
+    let mut hasher = hasher_factory();
+    hasher.update(root);
+    hasher.update(&size.to_be_bytes());
 
+    let mut hash = [0; 32];
+    hasher.finalize(&mut hash);
     hash
 }
 
+/// Makes sure the given `size` fits in an imt of the given [ImtDepth].
+fn ensure_size(depth: ImtDepth, size: u64) -> ProofResult<()> {
+    if size == 0 {
+        return Err(ProofError::SizeMismatch);
+    }
+
+    if size > depth.capacity() {
+        return Err(ProofError::SizeOverflow);
+    }
+
+    Ok(())
+}
+
+/// Makes sure the number of `siblings` matches the depth of an imt of the given `size`.
+///
+/// In a fixed depth imt, all the `siblings` must be known.
+fn ensure_siblings_depth(
+    depth: ImtDepth,
+    size: u64,
+    siblings: &[Option<Hash256>],
+) -> ProofResult<()> {
+    ensure_size(depth, size)?;
+
+    if siblings.len() != depth.at(size) as usize {
+        return Err(ProofError::SizeMismatch);
+    }
+
+    if matches!(depth, ImtDepth::Fixed(_)) && siblings.iter().any(Option::is_none) {
+        return Err(ProofError::SiblingsMismatch);
+    }
+
+    Ok(())
+}
+
 /// Returns `true` if the given [ImtNode] is part of the tree commited to in `root`.
 fn node_exists<H, K, V>(
     root: &Hash256,
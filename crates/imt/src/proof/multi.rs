@@ -0,0 +1,272 @@
+use alloc::{format, vec::Vec};
+use serde::{Deserialize, Serialize};
+
+use crate::{depth::ImtDepth, node::ImtNode, Hash256, Hasher, NodeKey, NodeValue};
+
+use super::{ensure_size, hash_children, root_with_size, ProofError, ProofResult};
+
+/// A proof of inclusion or exclusion for multiple keys against the same imt root.
+///
+/// Each key is proven either by its own [ImtNode] (inclusion) or by its low nullifier [ImtNode]
+/// (exclusion). All the proven nodes share a single deduplicated list of siblings.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct MultiProof<K, V> {
+    pub root: Hash256,
+    pub size: u64,
+    pub keys: Vec<K>,
+    /// The nodes proving the `keys`, sorted by index and deduplicated.
+    pub nodes: Vec<ImtNode<K, V>>,
+    /// The siblings needed to compute the root from the `nodes`, sorted by level and then by index.
+    pub siblings: Vec<Option<Hash256>>,
+}
+
+impl<K, V> MultiProof<K, V>
+where
+    K: NodeKey,
+    V: NodeValue,
+{
+    /// Verifies the [MultiProof] and returns, for each of the `keys`, its value if it is in the imt
+    /// or `None` if it is not.
+    pub fn verify<H: Hasher>(&self, hasher_factory: fn() -> H) -> ProofResult<Vec<Option<V>>> {
+        self.verify_with_depth(hasher_factory, ImtDepth::Dynamic)
+    }
+
+    /// Same as [MultiProof::verify] but for an imt with the given [ImtDepth].
+    pub fn verify_with_depth<H: Hasher>(
+        &self,
+        hasher_factory: fn() -> H,
+        depth: ImtDepth,
+    ) -> ProofResult<Vec<Option<V>>> {
+        // Make sure there is something to prove.
+        let Some(first_key) = self.keys.first() else {
+            return Err(ProofError::EmptyKeys);
+        };
+
+        if self.nodes.is_empty() {
+            return Err(ProofError::MissingKey(format!("{:?}", first_key.as_ref())));
+        }
+
+        // Make sure the nodes are sorted by index and deduplicated.
+        if self
+            .nodes
+            .windows(2)
+            .any(|nodes| nodes[0].index >= nodes[1].index)
+        {
+            return Err(ProofError::UnsortedNodes);
+        }
+
+        ensure_size(depth, self.size)?;
+
+        // Make sure no sibling is missing in a fixed depth imt.
+        if matches!(depth, ImtDepth::Fixed(_)) && self.siblings.iter().any(Option::is_none) {
+            return Err(ProofError::SiblingsMismatch);
+        }
+
+        // Compute the root from the nodes, level by level.
+        let mut hashes = self
+            .nodes
+            .iter()
+            .map(|node| (node.index, node.hash(hasher_factory())))
+            .collect::<Vec<_>>();
+
+        let mut siblings = self.siblings.iter();
+        for _ in 0..depth.at(self.size) {
+            let mut parent_hashes = Vec::with_capacity(hashes.len());
+
+            let mut i = 0;
+            while i < hashes.len() {
+                let (index, hash) = hashes[i];
+
+                // Use the right node hash when both children are known, else consume a sibling.
+                let (left, right) = match hashes.get(i + 1) {
+                    Some((right_index, right_hash))
+                        if index % 2 == 0 && *right_index == index + 1 =>
+                    {
+                        i += 1;
+                        (Some(hash), Some(*right_hash))
+                    }
+                    _ => {
+                        let sibling = *siblings.next().ok_or(ProofError::SiblingsMismatch)?;
+
+                        if index % 2 == 0 {
+                            (Some(hash), sibling)
+                        } else {
+                            (sibling, Some(hash))
+                        }
+                    }
+                };
+
+                parent_hashes.push((index / 2, hash_children(hasher_factory, &left, &right)));
+                i += 1;
+            }
+
+            hashes = parent_hashes;
+        }
+
+        if siblings.next().is_some() {
+            return Err(ProofError::SiblingsMismatch);
+        }
+
+        // NOTE: Nodes with indices out of the imt bounds do not collapse to a single root hash.
+        let root = match hashes[..] {
+            [(0, hash)] => root_with_size(hasher_factory, &hash, self.size),
+            _ => return Err(ProofError::SizeMismatch),
+        };
+
+        if root != self.root {
+            return Err(ProofError::NodeDoesNotExist);
+        }
+
+        // Find the node proving each key.
+        self.keys
+            .iter()
+            .map(|key| {
+                if let Some(node) = self.nodes.iter().find(|node| node.key == *key) {
+                    Ok(Some(node.value.clone()))
+                } else if self.nodes.iter().any(|node| node.is_ln_of(key)) {
+                    Ok(None)
+                } else {
+                    Err(ProofError::MissingKey(format!("{:?}", key.as_ref())))
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tiny_keccak::Keccak;
+
+    use crate::{
+        proof::{node::NodeProof, ProofError},
+        storage::btree_imt_storage::BTreeImtStorage,
+        tree::Imt,
+    };
+
+    #[test]
+    fn test_verify_invalid_root() {
+        // Instanciate an imt with a few nodes.
+        let storage = BTreeImtStorage::default();
+        let mut imt = Imt::writer(Keccak::v256, storage).expect("imt writer failed");
+        imt.insert_node([1; 32], [42; 32])
+            .expect("insert [1] failed");
+        imt.insert_node([3; 32], [42; 32])
+            .expect("insert [3] failed");
+
+        // Create a MultiProof and use a different `root`.
+        let mut sut = imt
+            .multi_proof([[1; 32], [2; 32]])
+            .expect("multi proof failed");
+        sut.root = [0xff; 32];
+        let res = sut.verify(Keccak::v256);
+        assert!(matches!(res, Err(ProofError::NodeDoesNotExist)));
+    }
+
+    #[test]
+    fn test_verify_invalid_siblings() {
+        // Instanciate an imt with a few nodes.
+        let storage = BTreeImtStorage::default();
+        let mut imt = Imt::writer(Keccak::v256, storage).expect("imt writer failed");
+        for i in 1..=6 {
+            imt.insert_node([i; 32], [42; 32])
+                .unwrap_or_else(|_| panic!("insert [{}] failed", i));
+        }
+
+        // Create a MultiProof and remove one of its siblings.
+        let mut sut = imt
+            .multi_proof([[1; 32], [4; 32]])
+            .expect("multi proof failed");
+        sut.siblings.pop();
+        let res = sut.verify(Keccak::v256);
+        assert!(matches!(res, Err(ProofError::SiblingsMismatch)));
+
+        // Create a MultiProof and add an extra sibling.
+        let mut sut = imt
+            .multi_proof([[1; 32], [4; 32]])
+            .expect("multi proof failed");
+        sut.siblings.push(None);
+        let res = sut.verify(Keccak::v256);
+        assert!(matches!(res, Err(ProofError::SiblingsMismatch)));
+    }
+
+    #[test]
+    fn test_verify_missing_key() {
+        // Instanciate an imt with a few nodes.
+        let storage = BTreeImtStorage::default();
+        let mut imt = Imt::writer(Keccak::v256, storage).expect("imt writer failed");
+        imt.insert_node([1; 32], [42; 32])
+            .expect("insert [1] failed");
+        imt.insert_node([3; 32], [42; 32])
+            .expect("insert [3] failed");
+
+        // Create a MultiProof and add a key that is not proven by its nodes.
+        let mut sut = imt.multi_proof([[1; 32]]).expect("multi proof failed");
+        sut.keys.push([4; 32]);
+        let res = sut.verify(Keccak::v256);
+        assert!(matches!(res, Err(ProofError::MissingKey(_))));
+    }
+
+    #[test]
+    fn test_verify_empty_keys() {
+        // Instanciate an imt with a few nodes.
+        let storage = BTreeImtStorage::default();
+        let mut imt = Imt::writer(Keccak::v256, storage).expect("imt writer failed");
+        imt.insert_node([1; 32], [42; 32])
+            .expect("insert [1] failed");
+
+        // Create a MultiProof without any key.
+        let sut = imt.multi_proof([]).expect("multi proof failed");
+        assert!(sut.nodes.is_empty());
+        let res = sut.verify(Keccak::v256);
+        assert!(matches!(res, Err(ProofError::EmptyKeys)));
+
+        // Create a MultiProof and drop its nodes.
+        let mut sut = imt.multi_proof([[1; 32]]).expect("multi proof failed");
+        sut.nodes.clear();
+        let res = sut.verify(Keccak::v256);
+        assert!(matches!(res, Err(ProofError::MissingKey(_))));
+    }
+
+    #[test]
+    fn test_verify() {
+        // Instanciate an imt with a few nodes.
+        let storage = BTreeImtStorage::default();
+        let mut imt = Imt::writer(Keccak::v256, storage).expect("imt writer failed");
+        for i in [1, 3, 5, 7, 9, 11, 13, 15, 17, 19] {
+            imt.insert_node([i; 32], [i; 32])
+                .unwrap_or_else(|_| panic!("insert [{}] failed", i));
+        }
+
+        let keys = [
+            [1; 32], [2; 32], [3; 32], [15; 32], [16; 32], [17; 32], [42; 32],
+        ];
+        let sut = imt.multi_proof(keys).expect("multi proof failed");
+
+        // Ensure the siblings are deduplicated.
+        let siblings_count = keys
+            .iter()
+            .map(
+                |key| match imt.node_proof(*key).expect("node proof failed") {
+                    NodeProof::Inclusion(inclusion) => inclusion.siblings.len(),
+                    NodeProof::Exclusion(exclusion) => exclusion.ln_siblings.len(),
+                },
+            )
+            .sum::<usize>();
+        assert!(sut.siblings.len() < siblings_count);
+
+        // Ensure the per-key results are correct.
+        let res = sut.verify(Keccak::v256).expect("verify failed");
+        assert_eq!(
+            res,
+            vec![
+                Some([1; 32]),
+                None,
+                Some([3; 32]),
+                Some([15; 32]),
+                None,
+                Some([17; 32]),
+                None
+            ]
+        );
+    }
+}
@@ -1,18 +1,19 @@
-use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use tiny_keccak::Hasher;
 
-use crate::{Hash256, NodeKey, NodeValue};
+use crate::{depth::ImtDepth, Hash256, NodeKey, NodeValue};
 
-use super::{insert::InsertProof, update::UpdateProof};
+use super::{delete::DeleteProof, insert::InsertProof, update::UpdateProof, ProofResult};
 
-/// A imt mutation that can either be an insert or an update.
+/// A imt mutation that can either be an insert, an update or a delete.
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
 pub enum MutateProof<K, V> {
     /// An [InsertProof].
     Insert(InsertProof<K, V>),
     /// An [UpdateProof].
     Update(UpdateProof<K, V>),
+    /// A [DeleteProof].
+    Delete(DeleteProof<K, V>),
 }
 
 impl<K, V> MutateProof<K, V>
@@ -23,15 +24,32 @@ where
     /// Verifies the imt mutate proof and return the new updated root.
     ///
     /// Before performing the mutation, the state is checked to make sure it is coherent.
-    /// In case of any inconsistency, an error is returned.
+    /// In case of any inconsistency, a [super::ProofError] is returned.
     pub fn verify<H: Hasher>(
         &self,
         hasher_factory: fn() -> H,
         old_root: Hash256,
-    ) -> Result<Hash256> {
+    ) -> ProofResult<Hash256> {
+        self.verify_with_depth(hasher_factory, old_root, ImtDepth::Dynamic)
+    }
+
+    /// Same as [MutateProof::verify] but for an imt with the given [ImtDepth].
+    pub fn verify_with_depth<H: Hasher>(
+        &self,
+        hasher_factory: fn() -> H,
+        old_root: Hash256,
+        depth: ImtDepth,
+    ) -> ProofResult<Hash256> {
         match &self {
-            MutateProof::Insert(insert) => insert.verify(hasher_factory, old_root),
-            MutateProof::Update(update) => update.verify(hasher_factory, old_root),
+            MutateProof::Insert(insert) => {
+                insert.verify_with_depth(hasher_factory, old_root, depth)
+            }
+            MutateProof::Update(update) => {
+                update.verify_with_depth(hasher_factory, old_root, depth)
+            }
+            MutateProof::Delete(delete) => {
+                delete.verify_with_depth(hasher_factory, old_root, depth)
+            }
         }
     }
 }
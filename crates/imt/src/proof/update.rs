@@ -1,9 +1,9 @@
-use anyhow::{ensure, Result};
+use alloc::vec::Vec;
 use serde::{Deserialize, Serialize};
 
-use crate::{node::ImtNode, Hash256, Hasher, NodeKey, NodeValue};
+use crate::{depth::ImtDepth, node::ImtNode, Hash256, Hasher, NodeKey, NodeValue};
 
-use super::{imt_root_from_node, node_exists};
+use super::{ensure_siblings_depth, imt_root_from_node, node_exists, ProofError, ProofResult};
 
 /// Update proof that can be verified for correctness.
 #[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
@@ -30,21 +30,35 @@ where
         &self,
         hasher_factory: fn() -> H,
         old_root: Hash256,
-    ) -> Result<Hash256> {
+    ) -> ProofResult<Hash256> {
+        self.verify_with_depth(hasher_factory, old_root, ImtDepth::Dynamic)
+    }
+
+    /// Same as [UpdateProof::verify] but for an imt with the given [ImtDepth].
+    pub fn verify_with_depth<H: Hasher>(
+        &self,
+        hasher_factory: fn() -> H,
+        old_root: Hash256,
+        depth: ImtDepth,
+    ) -> ProofResult<Hash256> {
         // Make sure the ImtMutate old_root matches the expected old_root.
-        ensure!(old_root == self.old_root, "ImtMutate.old_root is stale");
+        if old_root != self.old_root {
+            return Err(ProofError::StaleRoot);
+        }
+
+        // Make sure the siblings match the imt size.
+        ensure_siblings_depth(depth, self.size, &self.node_siblings)?;
 
         // Verify that the node to update is already in the imt.
-        ensure!(
-            node_exists(
-                &self.old_root,
-                hasher_factory,
-                self.size,
-                &self.node,
-                &self.node_siblings
-            ),
-            "ImtMutate.node is not in the imt"
-        );
+        if !node_exists(
+            &self.old_root,
+            hasher_factory,
+            self.size,
+            &self.node,
+            &self.node_siblings,
+        ) {
+            return Err(ProofError::NodeDoesNotExist);
+        }
 
         // Compute the new root from the updated node.
         let updated_node = ImtNode {
@@ -65,13 +79,13 @@ where
 mod tests {
     use tiny_keccak::Keccak;
 
-    use crate::{storage::btree_imt_storage::BTreeImtStorage, tree::Imt};
+    use crate::{proof::ProofError, storage::btree_imt_storage::BTreeImtStorage, tree::Imt};
 
     #[test]
     fn test_verify_invalid_old_root() {
         // Instanciate an imt with a few nodes.
         let storage = BTreeImtStorage::default();
-        let mut imt = Imt::writer(Keccak::v256, storage);
+        let mut imt = Imt::writer(Keccak::v256, storage).expect("imt writer failed");
         imt.insert_node([1; 32], [42; 32])
             .expect("insert [1] failed");
         imt.insert_node([2; 32], [42; 32])
@@ -84,14 +98,14 @@ mod tests {
             .update_node([2; 32], [43; 32])
             .expect("update [2] failed");
         let res = sut.verify(Keccak::v256, [0xff; 32]);
-        assert!(matches!(res, Err(e) if e.to_string() == "ImtMutate.old_root is stale"));
+        assert!(matches!(res, Err(ProofError::StaleRoot)));
     }
 
     #[test]
     fn test_verify_node_does_not_exist() {
         // Instanciate an imt with a few nodes.
         let storage = BTreeImtStorage::default();
-        let mut imt = Imt::writer(Keccak::v256, storage);
+        let mut imt = Imt::writer(Keccak::v256, storage).expect("imt writer failed");
         imt.insert_node([1; 32], [42; 32])
             .expect("insert [1] failed");
         imt.insert_node([2; 32], [42; 32])
@@ -105,14 +119,39 @@ mod tests {
             .expect("update [2] failed");
         sut.node.key = [4; 32];
         let res = sut.verify(Keccak::v256, sut.old_root);
-        assert!(matches!(res, Err(e) if e.to_string() == "ImtMutate.node is not in the imt"));
+        assert!(matches!(res, Err(ProofError::NodeDoesNotExist)));
+    }
+
+    #[test]
+    fn test_verify_size_mismatch() {
+        // Instanciate an imt with a few nodes.
+        let storage = BTreeImtStorage::default();
+        let mut imt = Imt::writer(Keccak::v256, storage).expect("imt writer failed");
+        imt.insert_node([1; 32], [42; 32])
+            .expect("insert [1] failed");
+        imt.insert_node([2; 32], [42; 32])
+            .expect("insert [2] failed");
+        imt.insert_node([3; 32], [42; 32])
+            .expect("insert [3] failed");
+
+        // Create an UpdateProof and use a `size` that does not match the siblings.
+        let mut sut = imt
+            .update_node([2; 32], [43; 32])
+            .expect("update [2] failed");
+        sut.size = 42;
+        let res = sut.verify(Keccak::v256, sut.old_root);
+        assert!(matches!(res, Err(ProofError::SizeMismatch)));
+
+        sut.size = 0;
+        let res = sut.verify(Keccak::v256, sut.old_root);
+        assert!(matches!(res, Err(ProofError::SizeMismatch)));
     }
 
     #[test]
     fn test_verify() {
         // Instanciate an imt with a few nodes.
         let storage = BTreeImtStorage::default();
-        let mut imt = Imt::writer(Keccak::v256, storage);
+        let mut imt = Imt::writer(Keccak::v256, storage).expect("imt writer failed");
         imt.insert_node([1; 32], [42; 32])
             .expect("insert [1] failed");
         imt.insert_node([2; 32], [42; 32])
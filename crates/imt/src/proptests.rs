@@ -0,0 +1,188 @@
+//! Property-based tests driving random sequences of mutations through an [Imt] backed by the
+//! [BTreeImtStorage] and differentially comparing it against a naive [ReferenceImt].
+
+use proptest::prelude::*;
+use std::collections::BTreeMap;
+use tiny_keccak::{Hasher, Keccak};
+
+use crate::{
+    depth::depth,
+    node::{ImtNode, DELETED_NODE_HASH},
+    proof::node::NodeProof,
+    storage::btree_imt_storage::BTreeImtStorage,
+    tree::{Imt, ImtError},
+    Hash256,
+};
+
+type Key = [u8; 32];
+type Value = [u8; 32];
+
+#[derive(Debug, Clone)]
+enum Op {
+    Insert(Key, Value),
+    Update(Key, Value),
+    Delete(Key),
+}
+
+/// Generates keys from a small domain so that the ops often target existing keys.
+fn key() -> impl Strategy<Value = Key> {
+    (1u8..=32).prop_map(|b| [b; 32])
+}
+
+fn value() -> impl Strategy<Value = Value> {
+    any::<u8>().prop_map(|b| [b; 32])
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        3 => (key(), value()).prop_map(|(key, value)| Op::Insert(key, value)),
+        2 => (key(), value()).prop_map(|(key, value)| Op::Update(key, value)),
+        1 => key().prop_map(Op::Delete),
+    ]
+}
+
+/// A naive indexed Merkle tree that recomputes everything from its leaves.
+#[derive(Debug)]
+struct ReferenceImt {
+    /// The (value; index) of each key in the tree, including the 0 node.
+    nodes: BTreeMap<Key, (Value, u64)>,
+    /// The number of leaves ever inserted (deleted leaves are never reused).
+    size: u64,
+}
+
+impl ReferenceImt {
+    fn new() -> Self {
+        Self {
+            nodes: BTreeMap::from([([0; 32], ([0; 32], 0))]),
+            size: 1,
+        }
+    }
+
+    fn insert(&mut self, key: Key, value: Value) {
+        self.nodes.insert(key, (value, self.size));
+        self.size += 1;
+    }
+
+    /// Returns the nodes ordered by index, with their `next_key` derived from the key order.
+    fn imt_nodes(&self) -> Vec<ImtNode<Key, Value>> {
+        let keys = self.nodes.keys().collect::<Vec<_>>();
+        let mut nodes = keys
+            .iter()
+            .enumerate()
+            .map(|(i, key)| {
+                let (value, index) = self.nodes[*key];
+                ImtNode {
+                    index,
+                    key: **key,
+                    value,
+                    next_key: keys.get(i + 1).map_or([0; 32], |key| **key),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        nodes.sort_by_key(|node| node.index);
+        nodes
+    }
+
+    fn root(&self) -> Hash256 {
+        let mut hashes = vec![DELETED_NODE_HASH; self.size as usize];
+        for node in self.imt_nodes() {
+            hashes[node.index as usize] = node.hash(Keccak::v256());
+        }
+
+        // Hash the levels pairwise, a node without sibling is hashed alone.
+        for _ in 0..depth(self.size) {
+            hashes = hashes.chunks(2).map(keccak).collect();
+        }
+
+        keccak(&[hashes[0].to_vec(), self.size.to_be_bytes().to_vec()])
+    }
+}
+
+fn keccak<T: AsRef<[u8]>>(data: &[T]) -> Hash256 {
+    let mut hasher = Keccak::v256();
+    data.iter().for_each(|data| hasher.update(data.as_ref()));
+
+    let mut hash = [0; 32];
+    hasher.finalize(&mut hash);
+    hash
+}
+
+proptest! {
+    #[test]
+    fn test_mutations_match_reference(ops in prop::collection::vec(op(), 1..64), probe in key()) {
+        let storage = BTreeImtStorage::default();
+        let mut sut = Imt::writer(Keccak::v256, storage).expect("imt writer failed");
+        let mut reference = ReferenceImt::new();
+
+        for op in ops {
+            let old_root = sut.root().expect("root failed");
+
+            // Apply the op to both trees and ensure the returned proof verifies from the old root
+            // to the new root.
+            let new_root = match op {
+                Op::Insert(key, value) => match sut.insert_node(key, value) {
+                    Ok(proof) => {
+                        prop_assert!(!reference.nodes.contains_key(&key));
+                        reference.insert(key, value);
+                        proof.verify(Keccak::v256, old_root).expect("insert proof verify failed")
+                    }
+                    Err(err) => {
+                        prop_assert!(matches!(err, ImtError::NodeAlreadyExist(_)));
+                        prop_assert!(reference.nodes.contains_key(&key));
+                        old_root
+                    }
+                },
+                Op::Update(key, value) => match sut.update_node(key, value) {
+                    Ok(proof) => {
+                        let node = reference.nodes.get_mut(&key).expect("missing reference node");
+                        node.0 = value;
+                        proof.verify(Keccak::v256, old_root).expect("update proof verify failed")
+                    }
+                    Err(err) => {
+                        prop_assert!(matches!(err, ImtError::NodeDoesNotExist(_)));
+                        prop_assert!(!reference.nodes.contains_key(&key));
+                        old_root
+                    }
+                },
+                Op::Delete(key) => match sut.delete_node(key) {
+                    Ok(proof) => {
+                        prop_assert!(reference.nodes.remove(&key).is_some());
+                        proof.verify(Keccak::v256, old_root).expect("delete proof verify failed")
+                    }
+                    Err(err) => {
+                        prop_assert!(matches!(err, ImtError::NodeDoesNotExist(_)));
+                        prop_assert!(!reference.nodes.contains_key(&key));
+                        old_root
+                    }
+                },
+            };
+
+            // Ensure both trees agree on the root and on the stored nodes.
+            prop_assert_eq!(new_root, sut.root().expect("root failed"));
+            prop_assert_eq!(new_root, reference.root());
+            prop_assert_eq!(sut.size().expect("size failed"), reference.size);
+
+            let nodes = sut
+                .nodes_by_index(..)
+                .collect::<Result<Vec<_>, _>>()
+                .expect("nodes_by_index failed");
+            prop_assert_eq!(nodes, reference.imt_nodes());
+
+            // Ensure the probed key is proven as expected.
+            match sut.node_proof(probe).expect("node proof failed") {
+                NodeProof::Inclusion(proof) => {
+                    let value = reference.nodes.get(&probe).map(|node| node.0);
+                    prop_assert_eq!(Some(proof.node.value), value);
+                    proof.verify(Keccak::v256).expect("inclusion proof verify failed");
+                }
+                NodeProof::Exclusion(proof) => {
+                    prop_assert!(!reference.nodes.contains_key(&probe));
+                    proof.verify(Keccak::v256).expect("exclusion proof verify failed");
+                }
+            }
+        }
+
+        sut.verify_integrity().expect("verify integrity failed");
+    }
+}
@@ -1,15 +1,42 @@
-use std::collections::{BTreeMap, HashMap};
+use std::{
+    collections::{BTreeMap, HashMap},
+    convert::Infallible,
+    ops::Bound,
+};
 
 use crate::{node::ImtNode, Hash256, NodeKey, NodeValue};
 
-use super::{ImtStorageReader, ImtStorageWriter};
+use super::{
+    versioned::{VersionedImtStorageReader, VersionedImtStorageWriter},
+    ImtStorageReader, ImtStorageWriter, StoredNodes,
+};
 
+/// An in-memory imt storage that snapshots itself on each new version.
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct BTreeImtStorage<NodeK, NodeV> {
     root: Option<Hash256>,
     size: Option<u64>,
     nodes: BTreeMap<NodeK, ImtNode<NodeK, NodeV>>,
+    node_keys: BTreeMap<u64, NodeK>,
     hashes: HashMap<u8, HashMap<u64, Hash256>>,
+
+    version: Option<u64>,
+    /// Snapshots of the storage at the end of each past version.
+    snapshots: BTreeMap<u64, BTreeImtStorage<NodeK, NodeV>>,
+}
+
+impl<NodeK, NodeV> BTreeImtStorage<NodeK, NodeV> {
+    /// Returns the storage (or its snapshot) as it was at the end of the given `version`.
+    fn at(&self, version: u64) -> Option<&Self> {
+        if version >= self.version.unwrap_or_default() {
+            Some(self)
+        } else {
+            self.snapshots
+                .range(..=version)
+                .next_back()
+                .map(|(_, snapshot)| snapshot)
+        }
+    }
 }
 
 impl<NodeK, NodeV> ImtStorageReader for BTreeImtStorage<NodeK, NodeV>
@@ -19,29 +46,60 @@ where
 {
     type NodeK = NodeK;
     type NodeV = NodeV;
+    type Error = Infallible;
 
-    fn get_node(&self, key: &NodeK) -> Option<ImtNode<NodeK, NodeV>> {
-        self.nodes.get(key).cloned()
+    fn get_node(&self, key: &NodeK) -> Result<Option<ImtNode<NodeK, NodeV>>, Infallible> {
+        Ok(self.nodes.get(key).cloned())
     }
 
-    fn get_ln_node(&self, key: &NodeK) -> Option<ImtNode<NodeK, NodeV>> {
-        self.nodes
+    fn get_ln_node(&self, key: &NodeK) -> Result<Option<ImtNode<NodeK, NodeV>>, Infallible> {
+        Ok(self
+            .nodes
             .range(..key)
             .next_back()
             .map(|(_, ln)| ln)
-            .cloned()
+            .cloned())
+    }
+
+    fn get_node_key(&self, index: u64) -> Result<Option<NodeK>, Infallible> {
+        Ok(self.node_keys.get(&index).cloned())
     }
 
-    fn get_hash(&self, level: u8, index: u64) -> Option<Hash256> {
-        self.hashes.get(&level)?.get(&index).cloned()
+    fn get_nodes_by_key<'a>(
+        &'a self,
+        start: Bound<&NodeK>,
+        end: Bound<&NodeK>,
+    ) -> StoredNodes<'a, Self> {
+        Box::new(
+            self.nodes
+                .range((start, end))
+                .map(|(_, node)| Ok(node.clone())),
+        )
     }
 
-    fn get_size(&self) -> Option<u64> {
-        self.size
+    fn get_nodes_by_index(&self, start: Bound<u64>, end: Bound<u64>) -> StoredNodes<'_, Self> {
+        Box::new(
+            self.node_keys
+                .range((start, end))
+                .filter_map(|(index, key)| self.nodes.get(key).filter(|node| node.index == *index))
+                .map(|node| Ok(node.clone())),
+        )
     }
 
-    fn get_root(&self) -> Option<Hash256> {
-        self.root
+    fn get_hash(&self, level: u8, index: u64) -> Result<Option<Hash256>, Infallible> {
+        Ok(self
+            .hashes
+            .get(&level)
+            .and_then(|hashes| hashes.get(&index))
+            .cloned())
+    }
+
+    fn get_size(&self) -> Result<Option<u64>, Infallible> {
+        Ok(self.size)
+    }
+
+    fn get_root(&self) -> Result<Option<Hash256>, Infallible> {
+        Ok(self.root)
     }
 }
 
@@ -50,19 +108,138 @@ where
     NodeK: NodeKey,
     NodeV: NodeValue,
 {
-    fn set_node(&mut self, node: ImtNode<NodeK, NodeV>) {
+    fn set_node(&mut self, node: ImtNode<NodeK, NodeV>) -> Result<(), Infallible> {
+        self.node_keys.insert(node.index, node.key.clone());
         self.nodes.insert(node.key.clone(), node);
+        Ok(())
+    }
+
+    fn remove_node(&mut self, key: &NodeK) -> Result<(), Infallible> {
+        self.nodes.remove(key);
+        Ok(())
     }
 
-    fn set_hash(&mut self, level: u8, index: u64, hash: Hash256) {
+    fn set_hash(&mut self, level: u8, index: u64, hash: Hash256) -> Result<(), Infallible> {
         self.hashes.entry(level).or_default().insert(index, hash);
+        Ok(())
+    }
+
+    fn set_size(&mut self, size: u64) -> Result<(), Infallible> {
+        self.size = Some(size);
+        Ok(())
+    }
+
+    fn set_root(&mut self, root: Hash256) -> Result<(), Infallible> {
+        self.root = Some(root);
+        Ok(())
+    }
+}
+
+impl<NodeK, NodeV> VersionedImtStorageReader for BTreeImtStorage<NodeK, NodeV>
+where
+    NodeK: NodeKey,
+    NodeV: NodeValue,
+{
+    fn get_version(&self) -> Result<Option<u64>, Infallible> {
+        Ok(self.version)
     }
 
-    fn set_size(&mut self, size: u64) {
-        self.size = Some(size)
+    fn get_root_version(&self, root: &Hash256) -> Result<Option<u64>, Infallible> {
+        if self.root == Some(*root) {
+            return Ok(Some(self.version.unwrap_or_default()));
+        }
+
+        Ok(self
+            .snapshots
+            .iter()
+            .find(|(_, snapshot)| snapshot.root == Some(*root))
+            .map(|(version, _)| *version))
+    }
+
+    fn get_node_at(
+        &self,
+        version: u64,
+        key: &NodeK,
+    ) -> Result<Option<ImtNode<NodeK, NodeV>>, Infallible> {
+        self.at(version)
+            .map_or(Ok(None), |storage| storage.get_node(key))
+    }
+
+    fn get_ln_node_at(
+        &self,
+        version: u64,
+        key: &NodeK,
+    ) -> Result<Option<ImtNode<NodeK, NodeV>>, Infallible> {
+        self.at(version)
+            .map_or(Ok(None), |storage| storage.get_ln_node(key))
+    }
+
+    fn get_hash_at(
+        &self,
+        version: u64,
+        level: u8,
+        index: u64,
+    ) -> Result<Option<Hash256>, Infallible> {
+        self.at(version)
+            .map_or(Ok(None), |storage| storage.get_hash(level, index))
+    }
+
+    fn get_size_at(&self, version: u64) -> Result<Option<u64>, Infallible> {
+        self.at(version)
+            .map_or(Ok(None), |storage| storage.get_size())
     }
 
-    fn set_root(&mut self, root: Hash256) {
-        self.root = Some(root)
+    fn get_root_at(&self, version: u64) -> Result<Option<Hash256>, Infallible> {
+        self.at(version)
+            .map_or(Ok(None), |storage| storage.get_root())
+    }
+}
+
+impl<NodeK, NodeV> VersionedImtStorageWriter for BTreeImtStorage<NodeK, NodeV>
+where
+    NodeK: NodeKey,
+    NodeV: NodeValue,
+{
+    fn set_version(&mut self, version: u64) -> Result<(), Infallible> {
+        // Snapshot the current version before moving to the next one.
+        if let Some(current_version) = self.version {
+            let snapshot = Self {
+                root: self.root,
+                size: self.size,
+                nodes: self.nodes.clone(),
+                node_keys: self.node_keys.clone(),
+                hashes: self.hashes.clone(),
+                version: Some(current_version),
+                snapshots: BTreeMap::new(),
+            };
+
+            self.snapshots.insert(current_version, snapshot);
+        }
+
+        self.version = Some(version);
+        Ok(())
+    }
+
+    fn rollback(&mut self, version: Option<u64>) -> Result<(), Infallible> {
+        let Some(version) = version else {
+            *self = Self::default();
+            return Ok(());
+        };
+
+        if version >= self.version.unwrap_or_default() {
+            return Ok(());
+        }
+
+        // Drop the snapshots of the discarded versions and restore the one of `version`.
+        self.snapshots.split_off(&(version + 1));
+        let mut restored = match self.snapshots.remove(&version) {
+            Some(snapshot) => snapshot,
+            None => self.at(version).cloned().unwrap_or_default(),
+        };
+        restored.version = Some(version);
+        restored.snapshots = std::mem::take(&mut self.snapshots);
+        *self = restored;
+
+        Ok(())
     }
 }
@@ -0,0 +1,202 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    ops::Bound,
+};
+
+use crate::{node::ImtNode, Hash256, NodeKey, NodeValue};
+
+use super::{
+    walk_nodes_by_index, walk_nodes_by_key, ImtStorageReader, ImtStorageWriter, StoredNode,
+    StoredNodes,
+};
+
+/// A buffered [ImtNode], `None` if it has been removed.
+type BufferedNode<S> = Option<StoredNode<S>>;
+
+/// An in-memory write buffer on top of an [ImtStorageWriter].
+///
+/// Every write is kept in memory (overwriting any previous write to the same entry) and reads
+/// fall back to the underlying storage. Removed nodes are buffered as tombstones (`None`).
+///
+/// Buffered writes are only persisted when calling [BufferedImtStorage::commit], meaning each
+/// entry is written at most once to the storage.
+pub(crate) struct BufferedImtStorage<'a, Storage>
+where
+    Storage: ImtStorageReader,
+{
+    storage: &'a mut Storage,
+
+    root: Option<Hash256>,
+    size: Option<u64>,
+    nodes: BTreeMap<Storage::NodeK, BufferedNode<Storage>>,
+    node_keys: HashMap<u64, Storage::NodeK>,
+    hashes: HashMap<(u8, u64), Hash256>,
+}
+
+impl<'a, Storage> BufferedImtStorage<'a, Storage>
+where
+    Storage: ImtStorageReader,
+{
+    /// Creates a new [BufferedImtStorage].
+    pub(crate) fn new(storage: &'a mut Storage) -> Self {
+        Self {
+            storage,
+            root: None,
+            size: None,
+            nodes: BTreeMap::new(),
+            node_keys: HashMap::new(),
+            hashes: HashMap::new(),
+        }
+    }
+}
+
+impl<'a, Storage> BufferedImtStorage<'a, Storage>
+where
+    Storage: ImtStorageWriter,
+{
+    /// Consumes the [BufferedImtStorage] and writes the buffered entries to the underlying storage.
+    pub(crate) fn commit(self) -> Result<(), Storage::Error> {
+        for (key, node) in self.nodes {
+            match node {
+                Some(node) => self.storage.set_node(node)?,
+                None => self.storage.remove_node(&key)?,
+            }
+        }
+
+        for ((level, index), hash) in self.hashes {
+            self.storage.set_hash(level, index, hash)?;
+        }
+
+        if let Some(size) = self.size {
+            self.storage.set_size(size)?;
+        }
+
+        if let Some(root) = self.root {
+            self.storage.set_root(root)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a, Storage> ImtStorageReader for BufferedImtStorage<'a, Storage>
+where
+    Storage: ImtStorageReader,
+    Storage::NodeK: NodeKey,
+    Storage::NodeV: NodeValue,
+{
+    type NodeK = Storage::NodeK;
+    type NodeV = Storage::NodeV;
+    type Error = Storage::Error;
+
+    fn get_node(&self, key: &Self::NodeK) -> Result<BufferedNode<Storage>, Self::Error> {
+        match self.nodes.get(key) {
+            Some(node) => Ok(node.clone()),
+            None => self.storage.get_node(key),
+        }
+    }
+
+    fn get_ln_node(&self, key: &Self::NodeK) -> Result<BufferedNode<Storage>, Self::Error> {
+        let buffered_ln_node = self
+            .nodes
+            .range(..key)
+            .rev()
+            .find_map(|(_, ln)| ln.as_ref());
+
+        // Skip the stored ln nodes that have been removed in the buffer.
+        let mut stored_ln_node = self.storage.get_ln_node(key)?;
+        while let Some(ln) = &stored_ln_node {
+            if !matches!(self.nodes.get(&ln.key), Some(None)) {
+                break;
+            }
+
+            stored_ln_node = self.storage.get_ln_node(&ln.key)?;
+        }
+
+        Ok(match (buffered_ln_node, stored_ln_node) {
+            (None, stored_ln_node) => stored_ln_node,
+            (Some(buffered_ln_node), None) => Some(buffered_ln_node.clone()),
+            (Some(buffered_ln_node), Some(stored_ln_node)) => {
+                // NOTE: On equal keys the buffered node is the most recent version.
+                if buffered_ln_node.key >= stored_ln_node.key {
+                    Some(buffered_ln_node.clone())
+                } else {
+                    Some(stored_ln_node)
+                }
+            }
+        })
+    }
+
+    fn get_node_key(&self, index: u64) -> Result<Option<Self::NodeK>, Self::Error> {
+        match self.node_keys.get(&index) {
+            Some(key) => Ok(Some(key.clone())),
+            None => self.storage.get_node_key(index),
+        }
+    }
+
+    fn get_nodes_by_key<'b>(
+        &'b self,
+        start: Bound<&Self::NodeK>,
+        end: Bound<&Self::NodeK>,
+    ) -> StoredNodes<'b, Self> {
+        walk_nodes_by_key(self, start, end)
+    }
+
+    fn get_nodes_by_index(&self, start: Bound<u64>, end: Bound<u64>) -> StoredNodes<'_, Self> {
+        walk_nodes_by_index(self, start, end)
+    }
+
+    fn get_hash(&self, level: u8, index: u64) -> Result<Option<Hash256>, Self::Error> {
+        match self.hashes.get(&(level, index)) {
+            Some(hash) => Ok(Some(*hash)),
+            None => self.storage.get_hash(level, index),
+        }
+    }
+
+    fn get_size(&self) -> Result<Option<u64>, Self::Error> {
+        match self.size {
+            Some(size) => Ok(Some(size)),
+            None => self.storage.get_size(),
+        }
+    }
+
+    fn get_root(&self) -> Result<Option<Hash256>, Self::Error> {
+        match self.root {
+            Some(root) => Ok(Some(root)),
+            None => self.storage.get_root(),
+        }
+    }
+}
+
+impl<'a, Storage> ImtStorageWriter for BufferedImtStorage<'a, Storage>
+where
+    Storage: ImtStorageReader,
+    Storage::NodeK: NodeKey,
+    Storage::NodeV: NodeValue,
+{
+    fn set_node(&mut self, node: ImtNode<Self::NodeK, Self::NodeV>) -> Result<(), Self::Error> {
+        self.node_keys.insert(node.index, node.key.clone());
+        self.nodes.insert(node.key.clone(), Some(node));
+        Ok(())
+    }
+
+    fn remove_node(&mut self, key: &Self::NodeK) -> Result<(), Self::Error> {
+        self.nodes.insert(key.clone(), None);
+        Ok(())
+    }
+
+    fn set_hash(&mut self, level: u8, index: u64, hash: Hash256) -> Result<(), Self::Error> {
+        self.hashes.insert((level, index), hash);
+        Ok(())
+    }
+
+    fn set_size(&mut self, size: u64) -> Result<(), Self::Error> {
+        self.size = Some(size);
+        Ok(())
+    }
+
+    fn set_root(&mut self, root: Hash256) -> Result<(), Self::Error> {
+        self.root = Some(root);
+        Ok(())
+    }
+}
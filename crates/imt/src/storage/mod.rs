@@ -1,27 +1,58 @@
-use crate::{node::ImtNode, Hash256};
+use std::ops::{Bound, RangeBounds};
 
-#[cfg(test)]
-pub(crate) mod btree_imt_storage;
+use crate::{node::ImtNode, Hash256, NodeKey};
+
+#[cfg(any(test, feature = "test-utils"))]
+pub mod btree_imt_storage;
+pub(crate) mod buffered;
+pub mod versioned;
+
+/// The [ImtNode] type stored by the given [ImtStorageReader].
+pub type StoredNode<S> = ImtNode<<S as ImtStorageReader>::NodeK, <S as ImtStorageReader>::NodeV>;
+
+/// An ordered iterator over the [StoredNode]s of the given [ImtStorageReader].
+pub type StoredNodes<'a, S> =
+    Box<dyn Iterator<Item = Result<StoredNode<S>, <S as ImtStorageReader>::Error>> + 'a>;
 
 /// Trait for reading and parsing an imt from storage.
 pub trait ImtStorageReader {
     type NodeK;
     type NodeV;
+    /// The error returned when failing to read from or write to the storage.
+    type Error: std::error::Error + Send + Sync + 'static;
 
     /// Returns the [ImtNode] from the imt form the given `key`.
-    fn get_node(&self, key: &Self::NodeK) -> Option<ImtNode<Self::NodeK, Self::NodeV>>;
+    fn get_node(&self, key: &Self::NodeK) -> Result<Option<StoredNode<Self>>, Self::Error>;
 
     /// Returns the low nullifier [ImtNode] from the imt for the given `key`.
-    fn get_ln_node(&self, key: &Self::NodeK) -> Option<ImtNode<Self::NodeK, Self::NodeV>>;
+    fn get_ln_node(&self, key: &Self::NodeK) -> Result<Option<StoredNode<Self>>, Self::Error>;
+
+    /// Returns the key of the last [ImtNode] registered at the given `index`.
+    ///
+    /// As indices are never reused, the key is kept even once its node has been removed: the
+    /// caller must check that the node still exists at this `index`.
+    fn get_node_key(&self, index: u64) -> Result<Option<Self::NodeK>, Self::Error>;
+
+    /// Returns the [ImtNode]s with keys within the (`start`; `end`) bounds, in key order.
+    fn get_nodes_by_key<'a>(
+        &'a self,
+        start: Bound<&Self::NodeK>,
+        end: Bound<&Self::NodeK>,
+    ) -> StoredNodes<'a, Self>;
+
+    /// Returns the [ImtNode]s with indices within the (`start`; `end`) bounds, in index order.
+    ///
+    /// The indices of the removed nodes are skipped.
+    fn get_nodes_by_index(&self, start: Bound<u64>, end: Bound<u64>) -> StoredNodes<'_, Self>;
 
     /// Returns the [Hash256] cached for the given (`level`; `index`) pair.
-    fn get_hash(&self, level: u8, index: u64) -> Option<Hash256>;
+    fn get_hash(&self, level: u8, index: u64) -> Result<Option<Hash256>, Self::Error>;
 
     /// Returns the imt size (including the 0 node).
-    fn get_size(&self) -> Option<u64>;
+    fn get_size(&self) -> Result<Option<u64>, Self::Error>;
 
     /// Returns the imt root (including the size).
-    fn get_root(&self) -> Option<Hash256>;
+    fn get_root(&self) -> Result<Option<Hash256>, Self::Error>;
 }
 
 impl<T> ImtStorageReader for &T
@@ -30,24 +61,47 @@ where
 {
     type NodeK = T::NodeK;
     type NodeV = T::NodeV;
+    type Error = T::Error;
 
-    fn get_node(&self, key: &Self::NodeK) -> Option<ImtNode<Self::NodeK, Self::NodeV>> {
+    fn get_node(
+        &self,
+        key: &Self::NodeK,
+    ) -> Result<Option<ImtNode<Self::NodeK, Self::NodeV>>, Self::Error> {
         T::get_node(*self, key)
     }
 
-    fn get_ln_node(&self, key: &Self::NodeK) -> Option<ImtNode<Self::NodeK, Self::NodeV>> {
+    fn get_ln_node(
+        &self,
+        key: &Self::NodeK,
+    ) -> Result<Option<ImtNode<Self::NodeK, Self::NodeV>>, Self::Error> {
         T::get_ln_node(*self, key)
     }
 
-    fn get_hash(&self, level: u8, index: u64) -> Option<Hash256> {
+    fn get_node_key(&self, index: u64) -> Result<Option<Self::NodeK>, Self::Error> {
+        T::get_node_key(*self, index)
+    }
+
+    fn get_nodes_by_key<'a>(
+        &'a self,
+        start: Bound<&Self::NodeK>,
+        end: Bound<&Self::NodeK>,
+    ) -> StoredNodes<'a, Self> {
+        T::get_nodes_by_key(*self, start, end)
+    }
+
+    fn get_nodes_by_index(&self, start: Bound<u64>, end: Bound<u64>) -> StoredNodes<'_, Self> {
+        T::get_nodes_by_index(*self, start, end)
+    }
+
+    fn get_hash(&self, level: u8, index: u64) -> Result<Option<Hash256>, Self::Error> {
         T::get_hash(*self, level, index)
     }
 
-    fn get_size(&self) -> Option<u64> {
+    fn get_size(&self) -> Result<Option<u64>, Self::Error> {
         T::get_size(*self)
     }
 
-    fn get_root(&self) -> Option<Hash256> {
+    fn get_root(&self) -> Result<Option<Hash256>, Self::Error> {
         T::get_root(*self)
     }
 }
@@ -58,60 +112,204 @@ where
 {
     type NodeK = T::NodeK;
     type NodeV = T::NodeV;
+    type Error = T::Error;
 
-    fn get_node(&self, key: &Self::NodeK) -> Option<ImtNode<Self::NodeK, Self::NodeV>> {
+    fn get_node(
+        &self,
+        key: &Self::NodeK,
+    ) -> Result<Option<ImtNode<Self::NodeK, Self::NodeV>>, Self::Error> {
         T::get_node(*self, key)
     }
 
-    fn get_ln_node(&self, key: &Self::NodeK) -> Option<ImtNode<Self::NodeK, Self::NodeV>> {
+    fn get_ln_node(
+        &self,
+        key: &Self::NodeK,
+    ) -> Result<Option<ImtNode<Self::NodeK, Self::NodeV>>, Self::Error> {
         T::get_ln_node(*self, key)
     }
 
-    fn get_hash(&self, level: u8, index: u64) -> Option<Hash256> {
+    fn get_node_key(&self, index: u64) -> Result<Option<Self::NodeK>, Self::Error> {
+        T::get_node_key(*self, index)
+    }
+
+    fn get_nodes_by_key<'a>(
+        &'a self,
+        start: Bound<&Self::NodeK>,
+        end: Bound<&Self::NodeK>,
+    ) -> StoredNodes<'a, Self> {
+        T::get_nodes_by_key(*self, start, end)
+    }
+
+    fn get_nodes_by_index(&self, start: Bound<u64>, end: Bound<u64>) -> StoredNodes<'_, Self> {
+        T::get_nodes_by_index(*self, start, end)
+    }
+
+    fn get_hash(&self, level: u8, index: u64) -> Result<Option<Hash256>, Self::Error> {
         T::get_hash(*self, level, index)
     }
 
-    fn get_size(&self) -> Option<u64> {
+    fn get_size(&self) -> Result<Option<u64>, Self::Error> {
         T::get_size(*self)
     }
 
-    fn get_root(&self) -> Option<Hash256> {
+    fn get_root(&self) -> Result<Option<Hash256>, Self::Error> {
         T::get_root(*self)
     }
 }
 
+/// Returns the [ImtNode]s with keys within the (`start`; `end`) bounds by walking the low nullifier
+/// linked list, for the storages that can not iterate over their nodes.
+///
+/// The walk stops on the first missing node: the caller is in charge of checking the list
+/// consistency.
+pub(crate) fn walk_nodes_by_key<'a, S>(
+    storage: &'a S,
+    start: Bound<&S::NodeK>,
+    end: Bound<&S::NodeK>,
+) -> StoredNodes<'a, S>
+where
+    S: ImtStorageReader,
+    S::NodeK: NodeKey,
+{
+    let mut cursor = Some(start.cloned());
+    let end = end.cloned();
+
+    Box::new(std::iter::from_fn(move || {
+        let node = match walk_first_node(storage, cursor.take()?.as_ref()) {
+            Ok(node) => node?,
+            Err(e) => return Some(Err(e)),
+        };
+
+        if !(Bound::Unbounded, end.as_ref()).contains(&node.key) {
+            return None;
+        }
+
+        cursor = Some(Bound::Excluded(node.key.clone()));
+        Some(Ok(node))
+    }))
+}
+
+/// Returns the first [ImtNode] (in key order) whose key is after the given `start` bound.
+fn walk_first_node<S>(
+    storage: &S,
+    start: Bound<&S::NodeK>,
+) -> Result<Option<StoredNode<S>>, S::Error>
+where
+    S: ImtStorageReader,
+    S::NodeK: NodeKey,
+{
+    let key = match start {
+        Bound::Unbounded => return storage.get_node(&S::NodeK::default()),
+        Bound::Included(key) | Bound::Excluded(key) => key,
+    };
+
+    let prev_node = match storage.get_node(key)? {
+        Some(node) if matches!(start, Bound::Included(_)) => return Ok(Some(node)),
+        Some(node) => node,
+        None => match storage.get_ln_node(key)? {
+            Some(ln_node) => ln_node,
+            None => return Ok(None),
+        },
+    };
+
+    // The list is closed by pointing back to the 0 node.
+    if prev_node.next_key == S::NodeK::default() {
+        return Ok(None);
+    }
+
+    storage.get_node(&prev_node.next_key)
+}
+
+/// Returns the [ImtNode]s with indices within the (`start`; `end`) bounds by looking up the node
+/// key of each index, for the storages that can not iterate over their nodes.
+pub(crate) fn walk_nodes_by_index<S>(
+    storage: &S,
+    start: Bound<u64>,
+    end: Bound<u64>,
+) -> StoredNodes<'_, S>
+where
+    S: ImtStorageReader,
+{
+    let start = match start {
+        Bound::Included(index) => index,
+        Bound::Excluded(index) => index.saturating_add(1),
+        Bound::Unbounded => 0,
+    };
+
+    let end = match end {
+        Bound::Included(index) => index.saturating_add(1),
+        Bound::Excluded(index) => index,
+        Bound::Unbounded => u64::MAX,
+    };
+
+    // NOTE: The indices are bounded by the imt size, which is only read on the first call.
+    let mut indices = None;
+
+    Box::new(std::iter::from_fn(move || {
+        if indices.is_none() {
+            match storage.get_size() {
+                Ok(size) => indices = Some(start..end.min(size.unwrap_or_default())),
+                Err(e) => {
+                    indices = Some(0..0);
+                    return Some(Err(e));
+                }
+            }
+        }
+
+        for index in indices.as_mut()?.by_ref() {
+            let node = storage.get_node_key(index).and_then(|key| match key {
+                Some(key) => Ok(storage.get_node(&key)?.filter(|node| node.index == index)),
+                None => Ok(None),
+            });
+
+            if let Some(node) = node.transpose() {
+                return Some(node);
+            }
+        }
+
+        None
+    }))
+}
+
 /// Trait for writing an imt to storage.
 pub trait ImtStorageWriter: ImtStorageReader {
-    /// Registers the given [ImtNode].
-    fn set_node(&mut self, node: ImtNode<Self::NodeK, Self::NodeV>);
+    /// Registers the given [ImtNode] (and its index).
+    fn set_node(&mut self, node: ImtNode<Self::NodeK, Self::NodeV>) -> Result<(), Self::Error>;
+
+    /// Unregisters the [ImtNode] for the given `key`.
+    fn remove_node(&mut self, key: &Self::NodeK) -> Result<(), Self::Error>;
 
     /// Registers the given [Hash256].
-    fn set_hash(&mut self, level: u8, index: u64, hash: Hash256);
+    fn set_hash(&mut self, level: u8, index: u64, hash: Hash256) -> Result<(), Self::Error>;
 
     /// Registers the given imt size.
-    fn set_size(&mut self, size: u64);
+    fn set_size(&mut self, size: u64) -> Result<(), Self::Error>;
 
     /// Registers the given imt root.
-    fn set_root(&mut self, root: Hash256);
+    fn set_root(&mut self, root: Hash256) -> Result<(), Self::Error>;
 }
 
 impl<T> ImtStorageWriter for &mut T
 where
     T: ImtStorageWriter,
 {
-    fn set_node(&mut self, node: ImtNode<Self::NodeK, Self::NodeV>) {
-        T::set_node(*self, node);
+    fn set_node(&mut self, node: ImtNode<Self::NodeK, Self::NodeV>) -> Result<(), Self::Error> {
+        T::set_node(*self, node)
+    }
+
+    fn remove_node(&mut self, key: &Self::NodeK) -> Result<(), Self::Error> {
+        T::remove_node(*self, key)
     }
 
-    fn set_hash(&mut self, level: u8, index: u64, hash: Hash256) {
-        T::set_hash(*self, level, index, hash);
+    fn set_hash(&mut self, level: u8, index: u64, hash: Hash256) -> Result<(), Self::Error> {
+        T::set_hash(*self, level, index, hash)
     }
 
-    fn set_size(&mut self, size: u64) {
-        T::set_size(*self, size);
+    fn set_size(&mut self, size: u64) -> Result<(), Self::Error> {
+        T::set_size(*self, size)
     }
 
-    fn set_root(&mut self, root: Hash256) {
-        T::set_root(*self, root);
+    fn set_root(&mut self, root: Hash256) -> Result<(), Self::Error> {
+        T::set_root(*self, root)
     }
 }
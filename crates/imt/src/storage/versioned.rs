@@ -0,0 +1,244 @@
+use std::ops::Bound;
+
+use crate::{node::ImtNode, Hash256, NodeKey};
+
+use super::{
+    walk_nodes_by_index, walk_nodes_by_key, ImtStorageReader, ImtStorageWriter, StoredNode,
+    StoredNodes,
+};
+
+/// Trait for reading past versions of an imt from storage.
+///
+/// A version groups all the writes performed after a call to [VersionedImtStorageWriter::set_version]
+/// (e.g. all the writes of a batch). Reading at a given `version` returns the state of the imt as it was
+/// at the end of this version.
+pub trait VersionedImtStorageReader: ImtStorageReader {
+    /// Returns the current version (if any).
+    fn get_version(&self) -> Result<Option<u64>, Self::Error>;
+
+    /// Returns the version at which the given imt `root` (including the size) was registered.
+    fn get_root_version(&self, root: &Hash256) -> Result<Option<u64>, Self::Error>;
+
+    /// Returns the [ImtNode] from the imt for the given `key` at the given `version`.
+    fn get_node_at(
+        &self,
+        version: u64,
+        key: &Self::NodeK,
+    ) -> Result<Option<StoredNode<Self>>, Self::Error>;
+
+    /// Returns the low nullifier [ImtNode] from the imt for the given `key` at the given `version`.
+    fn get_ln_node_at(
+        &self,
+        version: u64,
+        key: &Self::NodeK,
+    ) -> Result<Option<StoredNode<Self>>, Self::Error>;
+
+    /// Returns the [Hash256] cached for the given (`level`; `index`) pair at the given `version`.
+    fn get_hash_at(
+        &self,
+        version: u64,
+        level: u8,
+        index: u64,
+    ) -> Result<Option<Hash256>, Self::Error>;
+
+    /// Returns the imt size (including the 0 node) at the given `version`.
+    fn get_size_at(&self, version: u64) -> Result<Option<u64>, Self::Error>;
+
+    /// Returns the imt root (including the size) at the given `version`.
+    fn get_root_at(&self, version: u64) -> Result<Option<Hash256>, Self::Error>;
+}
+
+impl<T> VersionedImtStorageReader for &T
+where
+    T: VersionedImtStorageReader,
+{
+    fn get_version(&self) -> Result<Option<u64>, Self::Error> {
+        T::get_version(*self)
+    }
+
+    fn get_root_version(&self, root: &Hash256) -> Result<Option<u64>, Self::Error> {
+        T::get_root_version(*self, root)
+    }
+
+    fn get_node_at(
+        &self,
+        version: u64,
+        key: &Self::NodeK,
+    ) -> Result<Option<ImtNode<Self::NodeK, Self::NodeV>>, Self::Error> {
+        T::get_node_at(*self, version, key)
+    }
+
+    fn get_ln_node_at(
+        &self,
+        version: u64,
+        key: &Self::NodeK,
+    ) -> Result<Option<ImtNode<Self::NodeK, Self::NodeV>>, Self::Error> {
+        T::get_ln_node_at(*self, version, key)
+    }
+
+    fn get_hash_at(
+        &self,
+        version: u64,
+        level: u8,
+        index: u64,
+    ) -> Result<Option<Hash256>, Self::Error> {
+        T::get_hash_at(*self, version, level, index)
+    }
+
+    fn get_size_at(&self, version: u64) -> Result<Option<u64>, Self::Error> {
+        T::get_size_at(*self, version)
+    }
+
+    fn get_root_at(&self, version: u64) -> Result<Option<Hash256>, Self::Error> {
+        T::get_root_at(*self, version)
+    }
+}
+
+impl<T> VersionedImtStorageReader for &mut T
+where
+    T: VersionedImtStorageReader,
+{
+    fn get_version(&self) -> Result<Option<u64>, Self::Error> {
+        T::get_version(*self)
+    }
+
+    fn get_root_version(&self, root: &Hash256) -> Result<Option<u64>, Self::Error> {
+        T::get_root_version(*self, root)
+    }
+
+    fn get_node_at(
+        &self,
+        version: u64,
+        key: &Self::NodeK,
+    ) -> Result<Option<ImtNode<Self::NodeK, Self::NodeV>>, Self::Error> {
+        T::get_node_at(*self, version, key)
+    }
+
+    fn get_ln_node_at(
+        &self,
+        version: u64,
+        key: &Self::NodeK,
+    ) -> Result<Option<ImtNode<Self::NodeK, Self::NodeV>>, Self::Error> {
+        T::get_ln_node_at(*self, version, key)
+    }
+
+    fn get_hash_at(
+        &self,
+        version: u64,
+        level: u8,
+        index: u64,
+    ) -> Result<Option<Hash256>, Self::Error> {
+        T::get_hash_at(*self, version, level, index)
+    }
+
+    fn get_size_at(&self, version: u64) -> Result<Option<u64>, Self::Error> {
+        T::get_size_at(*self, version)
+    }
+
+    fn get_root_at(&self, version: u64) -> Result<Option<Hash256>, Self::Error> {
+        T::get_root_at(*self, version)
+    }
+}
+
+/// Trait for writing a versioned imt to storage.
+pub trait VersionedImtStorageWriter: ImtStorageWriter + VersionedImtStorageReader {
+    /// Registers the version under which all the subsequent writes are performed.
+    ///
+    /// The `version` MUST be greater than the current one.
+    fn set_version(&mut self, version: u64) -> Result<(), Self::Error>;
+
+    /// Reverts the storage to its state at the end of the given `version`, discarding all the writes
+    /// performed in the later versions. Reverting to `None` discards all the versions.
+    ///
+    /// The `version` (if any) MUST be lower or equal to the current one.
+    fn rollback(&mut self, version: Option<u64>) -> Result<(), Self::Error>;
+}
+
+impl<T> VersionedImtStorageWriter for &mut T
+where
+    T: VersionedImtStorageWriter,
+{
+    fn set_version(&mut self, version: u64) -> Result<(), Self::Error> {
+        T::set_version(*self, version)
+    }
+
+    fn rollback(&mut self, version: Option<u64>) -> Result<(), Self::Error> {
+        T::rollback(*self, version)
+    }
+}
+
+/// A read-only view of a [VersionedImtStorageReader] at a fixed version.
+///
+/// It implements [ImtStorageReader] so that an [crate::tree::Imt] can be built on top of it
+/// to generate proofs against a past root.
+#[derive(Debug, Clone)]
+pub struct ImtStorageAtVersion<Storage> {
+    storage: Storage,
+    version: u64,
+}
+
+impl<Storage> ImtStorageAtVersion<Storage> {
+    /// Creates a new [ImtStorageAtVersion].
+    pub fn new(storage: Storage, version: u64) -> Self {
+        Self { storage, version }
+    }
+
+    /// Returns the version of the [ImtStorageAtVersion].
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+}
+
+impl<Storage> ImtStorageReader for ImtStorageAtVersion<Storage>
+where
+    Storage: VersionedImtStorageReader,
+    Storage::NodeK: NodeKey,
+{
+    type NodeK = Storage::NodeK;
+    type NodeV = Storage::NodeV;
+    type Error = Storage::Error;
+
+    fn get_node(
+        &self,
+        key: &Self::NodeK,
+    ) -> Result<Option<ImtNode<Self::NodeK, Self::NodeV>>, Self::Error> {
+        self.storage.get_node_at(self.version, key)
+    }
+
+    fn get_ln_node(
+        &self,
+        key: &Self::NodeK,
+    ) -> Result<Option<ImtNode<Self::NodeK, Self::NodeV>>, Self::Error> {
+        self.storage.get_ln_node_at(self.version, key)
+    }
+
+    fn get_node_key(&self, index: u64) -> Result<Option<Self::NodeK>, Self::Error> {
+        // NOTE: Indices are never reused so the latest key registered at `index` is also
+        //       the one registered at `self.version` (if any).
+        self.storage.get_node_key(index)
+    }
+
+    fn get_nodes_by_key<'a>(
+        &'a self,
+        start: Bound<&Self::NodeK>,
+        end: Bound<&Self::NodeK>,
+    ) -> StoredNodes<'a, Self> {
+        walk_nodes_by_key(self, start, end)
+    }
+
+    fn get_nodes_by_index(&self, start: Bound<u64>, end: Bound<u64>) -> StoredNodes<'_, Self> {
+        walk_nodes_by_index(self, start, end)
+    }
+
+    fn get_hash(&self, level: u8, index: u64) -> Result<Option<Hash256>, Self::Error> {
+        self.storage.get_hash_at(self.version, level, index)
+    }
+
+    fn get_size(&self) -> Result<Option<u64>, Self::Error> {
+        self.storage.get_size_at(self.version)
+    }
+
+    fn get_root(&self) -> Result<Option<Hash256>, Self::Error> {
+        self.storage.get_root_at(self.version)
+    }
+}
@@ -1,14 +1,22 @@
+use std::{collections::BTreeMap, ops::RangeBounds};
+
 use thiserror::Error;
-use tiny_keccak::{Hasher, Keccak};
 use tracing::debug;
 
+pub use crate::depth::ImtDepth;
+
 use crate::{
-    node::ImtNode,
+    node::{ImtNode, DELETED_NODE_HASH},
     proof::{
-        exclusion::ExclusionProof, inclusion::InclusionProof, insert::InsertProof,
-        mutate::MutateProof, node::NodeProof, update::UpdateProof,
+        delete::DeleteProof, exclusion::ExclusionProof, hash_children, inclusion::InclusionProof,
+        insert::InsertProof, multi::MultiProof, mutate::MutateProof, node::NodeProof,
+        root_with_size, update::UpdateProof,
+    },
+    storage::{
+        buffered::BufferedImtStorage,
+        versioned::{ImtStorageAtVersion, VersionedImtStorageReader},
+        ImtStorageReader, ImtStorageWriter, StoredNodes,
     },
-    storage::{ImtStorageReader, ImtStorageWriter},
     Hash256, NodeKey, NodeValue,
 };
 
@@ -20,6 +28,28 @@ pub enum ImtError {
     NodeDoesNotExist(String),
     #[error("low nullifier not found for `{0}`")]
     LowNullifierNotFound(String),
+    #[error("node `{0}` can not be deleted")]
+    NodeNotDeletable(String),
+    #[error("root `{0}` not found")]
+    RootNotFound(String),
+    #[error("imt storage is not empty")]
+    StorageNotEmpty,
+    #[error("imt is full")]
+    Full,
+    #[error("imt is corrupted: {0}")]
+    Corrupted(String),
+    #[error("storage error: {0}")]
+    Storage(#[source] Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl ImtError {
+    /// Wraps the given storage `error` into an [ImtError::Storage].
+    fn storage<E>(error: E) -> Self
+    where
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        Self::Storage(Box::new(error))
+    }
 }
 
 pub type ImtResult<T> = Result<T, ImtError>;
@@ -29,6 +59,9 @@ pub type ImtResult<T> = Result<T, ImtError>;
 #[derive(Debug, Clone)]
 pub struct Imt<Hasher, NodeK, NodeV, Storage> {
     hasher_factory: fn() -> Hasher,
+    depth: ImtDepth,
+    /// The hashes of the empty subtrees of each level (only used by fixed depth imts).
+    zero_hashes: Vec<Hash256>,
     storage: Storage,
 
     _phantom_data_k: std::marker::PhantomData<NodeK>,
@@ -37,77 +70,286 @@ pub struct Imt<Hasher, NodeK, NodeV, Storage> {
 
 impl<Hasher, NodeK, NodeV, Storage> Imt<Hasher, NodeK, NodeV, Storage>
 where
+    Hasher: tiny_keccak::Hasher,
+    NodeK: NodeKey,
+    NodeV: NodeValue,
     Storage: ImtStorageReader<NodeK = NodeK, NodeV = NodeV>,
 {
     /// Creates a new imt that only provides read access.
     ///
-    /// Panics if the [Imt::storage] is empty.
-    pub fn reader(hasher_factory: fn() -> Hasher, storage: Storage) -> Self {
-        let size = storage.get_size();
+    /// If the [Imt::storage] is empty, the imt is read as the tree only containing the 0 node,
+    /// which is the tree [Imt::writer] initializes on an empty storage.
+    pub fn reader(hasher_factory: fn() -> Hasher, storage: Storage) -> ImtResult<Self> {
+        Self::reader_with_depth(hasher_factory, storage, ImtDepth::Dynamic)
+    }
 
-        let imt = Self {
+    /// Same as [Imt::reader] but for an imt with the given [ImtDepth].
+    ///
+    /// The `depth` MUST be the one the imt has been written with.
+    pub fn reader_with_depth(
+        hasher_factory: fn() -> Hasher,
+        storage: Storage,
+        depth: ImtDepth,
+    ) -> ImtResult<Self> {
+        // Make sure the storage is readable.
+        storage.get_size().map_err(ImtError::storage)?;
+
+        Ok(Self::new(hasher_factory, storage, depth))
+    }
+
+    /// Creates a new imt on top of the given `storage`.
+    fn new(hasher_factory: fn() -> Hasher, storage: Storage, depth: ImtDepth) -> Self {
+        let zero_hashes = match depth {
+            ImtDepth::Dynamic => vec![],
+            ImtDepth::Fixed(depth) => zero_hashes(hasher_factory, depth),
+        };
+
+        Self {
             hasher_factory,
+            depth,
+            zero_hashes,
             storage,
 
             _phantom_data_k: std::marker::PhantomData,
             _phantom_data_v: std::marker::PhantomData,
-        };
-
-        size.and(Some(imt)).expect("imt is empty")
+        }
     }
 
     /// Returns the imt root (including the size).
-    pub fn root(&self) -> Hash256 {
-        self.storage.get_root().unwrap_or_default()
+    pub fn root(&self) -> ImtResult<Hash256> {
+        match self.storage.get_root().map_err(ImtError::storage)? {
+            Some(root) => Ok(root),
+            None => Ok(root_with_size(
+                self.hasher_factory,
+                &self.empty_hash(self.depth.at(1)),
+                1,
+            )),
+        }
     }
 
     /// Returns the imt size (including the 0 node).
-    pub fn size(&self) -> u64 {
-        self.storage.get_size().unwrap_or(0)
+    pub fn size(&self) -> ImtResult<u64> {
+        let size = self.storage.get_size().map_err(ImtError::storage)?;
+        Ok(size.unwrap_or(1))
     }
 
     /// Returns the imt depth.
-    pub fn depth(&self) -> u8 {
-        let size = self.size();
-        depth(size)
+    pub fn depth(&self) -> ImtResult<u8> {
+        let size = self.size()?;
+        Ok(self.depth.at(size))
+    }
+
+    /// Returns true if nothing has been written to the [Imt::storage] yet.
+    fn is_empty(&self) -> ImtResult<bool> {
+        let size = self.storage.get_size().map_err(ImtError::storage)?;
+        Ok(size.is_none())
+    }
+
+    /// Returns the 0 node, which is the only node of an empty imt.
+    fn zero_node() -> ImtNode<NodeK, NodeV> {
+        ImtNode {
+            index: Default::default(),
+            key: Default::default(),
+            value: Default::default(),
+            next_key: Default::default(),
+        }
+    }
+
+    /// Returns the hash at the given `level` of the leftmost subtree of an empty imt.
+    fn empty_hash(&self, level: u8) -> Hash256 {
+        let mut hash = Self::zero_node().hash((self.hasher_factory)());
+        for zero_hash in &self.zero_hashes[..level as usize] {
+            hash = hash_children(self.hasher_factory, &Some(hash), &Some(*zero_hash));
+        }
+
+        hash
+    }
+
+    /// Returns the [ImtNode] for the given `key` (if any).
+    fn node(&self, key: &NodeK) -> ImtResult<Option<ImtNode<NodeK, NodeV>>> {
+        match self.storage.get_node(key).map_err(ImtError::storage)? {
+            Some(node) => Ok(Some(node)),
+            None if *key == NodeK::default() && self.is_empty()? => Ok(Some(Self::zero_node())),
+            None => Ok(None),
+        }
     }
 
     /// Returns the Low Nullifier node for the given `key`.
-    fn low_nullifier(&self, key: &NodeK) -> Option<ImtNode<NodeK, NodeV>> {
+    fn low_nullifier(&self, key: &NodeK) -> ImtResult<Option<ImtNode<NodeK, NodeV>>> {
         // TODO: This should really return an error instead.
-        if self.storage.get_node(key).is_some() {
-            return None;
+        if self.node(key)?.is_some() {
+            return Ok(None);
         }
 
-        self.storage.get_ln_node(key)
+        match self.storage.get_ln_node(key).map_err(ImtError::storage)? {
+            Some(ln_node) => Ok(Some(ln_node)),
+            None if self.is_empty()? => Ok(Some(Self::zero_node())),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the [Hash256] cached for the given (`level`; `index`) pair (if any).
+    fn hash(&self, level: u8, index: u64) -> ImtResult<Option<Hash256>> {
+        match self
+            .storage
+            .get_hash(level, index)
+            .map_err(ImtError::storage)?
+        {
+            Some(hash) => Ok(Some(hash)),
+            None if index == 0 && level <= self.depth.at(1) && self.is_empty()? => {
+                Ok(Some(self.empty_hash(level)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the [Hash256] of the sibling at the given (`level`; `index`) pair.
+    ///
+    /// In a fixed depth imt, missing siblings are empty subtrees so they are always known.
+    fn sibling(&self, level: u8, index: u64) -> ImtResult<Option<Hash256>> {
+        let hash = self.hash(level, index)?;
+        Ok(hash.or_else(|| self.zero_hashes.get(level as usize).copied()))
+    }
+
+    /// Returns an iterator over the [ImtNode]s with keys within the given `range`, in key order.
+    ///
+    /// The nodes are lazily fetched with an ordered range scan of the storage. To paginate, `take` a
+    /// page of nodes and resume from `(Bound::Excluded(last_key), Bound::Unbounded)`.
+    pub fn nodes_by_key<R>(
+        &self,
+        range: R,
+    ) -> impl Iterator<Item = ImtResult<ImtNode<NodeK, NodeV>>> + '_
+    where
+        R: RangeBounds<NodeK>,
+    {
+        let start = range.start_bound();
+        let end = range.end_bound();
+
+        let nodes = self.nodes_or_zero_node(range.contains(&NodeK::default()), || {
+            self.storage.get_nodes_by_key(start, end)
+        });
+
+        // Ensure the scanned nodes match the low nullifier linked list.
+        let mut prev_node: Option<ImtNode<NodeK, NodeV>> = None;
+        nodes.map(move |node| {
+            let node = node?;
+            if let Some(prev_node) = prev_node.replace(node.clone()) {
+                if prev_node.next_key != node.key {
+                    return Err(ImtError::Corrupted(format!(
+                        "node `{:?}` next node is missing",
+                        prev_node.key.as_ref()
+                    )));
+                }
+            }
+
+            Ok(node)
+        })
+    }
+
+    /// Returns an iterator over the [ImtNode]s with indices within the given `range`, in index order.
+    ///
+    /// The indices of the deleted nodes are skipped. To paginate, `take` a page of nodes and resume
+    /// from `last_index + 1..`.
+    pub fn nodes_by_index<R>(
+        &self,
+        range: R,
+    ) -> impl Iterator<Item = ImtResult<ImtNode<NodeK, NodeV>>> + '_
+    where
+        R: RangeBounds<u64>,
+    {
+        let start = range.start_bound().cloned();
+        let end = range.end_bound().cloned();
+
+        self.nodes_or_zero_node(range.contains(&0), || {
+            self.storage.get_nodes_by_index(start, end)
+        })
+    }
+
+    /// Returns the given storage `nodes`, or the 0 node (if `with_zero_node`) when the imt is empty.
+    fn nodes_or_zero_node<'a>(
+        &'a self,
+        with_zero_node: bool,
+        nodes: impl FnOnce() -> StoredNodes<'a, Storage>,
+    ) -> Box<dyn Iterator<Item = ImtResult<ImtNode<NodeK, NodeV>>> + 'a> {
+        match self.is_empty() {
+            Ok(true) => Box::new(with_zero_node.then(|| Ok(Self::zero_node())).into_iter()),
+            Ok(false) => Box::new(nodes().map(|node| node.map_err(ImtError::storage))),
+            Err(e) => Box::new(std::iter::once(Err(e))),
+        }
     }
 
     /// Returns the list of siblings for the given `node`.
-    fn siblings(&self, depth: u8, node: &ImtNode<NodeK, NodeV>) -> Vec<Option<Hash256>> {
+    fn siblings(&self, depth: u8, node: &ImtNode<NodeK, NodeV>) -> ImtResult<Vec<Option<Hash256>>> {
         let mut siblings = Vec::with_capacity(depth as _);
         let mut index = node.index;
 
         for level in 0..depth {
             let sibling_index = if index % 2 == 0 { index + 1 } else { index - 1 };
-            let sibling_hash = self.storage.get_hash(level, sibling_index);
+            let sibling_hash = self.sibling(level, sibling_index)?;
 
             siblings.push(sibling_hash);
             index /= 2;
         }
 
-        siblings
+        Ok(siblings)
+    }
+}
+
+impl<Hasher, NodeK, NodeV, Storage> Imt<Hasher, NodeK, NodeV, Storage>
+where
+    Storage: VersionedImtStorageReader<NodeK = NodeK, NodeV = NodeV>,
+{
+    /// Returns a read-only imt as it was at the end of the given `version`.
+    pub fn at_version(
+        &self,
+        version: u64,
+    ) -> Imt<Hasher, NodeK, NodeV, ImtStorageAtVersion<&Storage>> {
+        Imt {
+            hasher_factory: self.hasher_factory,
+            depth: self.depth,
+            zero_hashes: self.zero_hashes.clone(),
+            storage: ImtStorageAtVersion::new(&self.storage, version),
+
+            _phantom_data_k: std::marker::PhantomData,
+            _phantom_data_v: std::marker::PhantomData,
+        }
+    }
+
+    /// Returns a read-only imt as it was when its root (including the size) was `root`.
+    pub fn at_root(
+        &self,
+        root: &Hash256,
+    ) -> ImtResult<Imt<Hasher, NodeK, NodeV, ImtStorageAtVersion<&Storage>>> {
+        let version = self
+            .storage
+            .get_root_version(root)
+            .map_err(ImtError::storage)?
+            .ok_or_else(|| ImtError::RootNotFound(format!("{:?}", root)))?;
+
+        // NOTE: Make sure `root` is the root obtained at the end of the version and not
+        //       an intermediate one registered while the version was being written.
+        let version_root = self
+            .storage
+            .get_root_at(version)
+            .map_err(ImtError::storage)?;
+        if version_root != Some(*root) {
+            return Err(ImtError::RootNotFound(format!("{:?}", root)));
+        }
+
+        Ok(self.at_version(version))
     }
 }
 
 impl<Hasher, NodeK, NodeV, Storage> Imt<Hasher, NodeK, NodeV, Storage>
 where
+    Hasher: tiny_keccak::Hasher,
     NodeK: NodeKey,
     NodeV: NodeValue,
     Storage: ImtStorageReader<NodeK = NodeK, NodeV = NodeV>,
 {
     /// Generates an [NodeProof].
     pub fn node_proof(&self, key: NodeK) -> ImtResult<NodeProof<NodeK, NodeV>> {
-        Ok(match self.storage.get_node(&key) {
+        Ok(match self.node(&key)? {
             Some(_) => NodeProof::Inclusion(self.inclusion_proof(key)?),
             None => NodeProof::Exclusion(self.exclusion_proof(key)?),
         })
@@ -116,13 +358,12 @@ where
     /// Generates an [InclusionProof].
     pub fn inclusion_proof(&self, key: NodeK) -> ImtResult<InclusionProof<NodeK, NodeV>> {
         let node = self
-            .storage
-            .get_node(&key)
+            .node(&key)?
             .ok_or_else(|| ImtError::NodeDoesNotExist(format!("{:?}", key.as_ref())))?;
 
-        let root = self.root();
-        let size = self.size();
-        let siblings = self.siblings(self.depth(), &node);
+        let root = self.root()?;
+        let size = self.size()?;
+        let siblings = self.siblings(self.depth.at(size), &node)?;
 
         Ok(InclusionProof {
             root,
@@ -135,12 +376,12 @@ where
     /// Generates an [ExclusionProof].
     pub fn exclusion_proof(&self, key: NodeK) -> ImtResult<ExclusionProof<NodeK, NodeV>> {
         let ln_node = self
-            .low_nullifier(&key)
+            .low_nullifier(&key)?
             .ok_or_else(|| ImtError::LowNullifierNotFound(format!("{:?}", key.as_ref())))?;
 
-        let root = self.root();
-        let size = self.size();
-        let ln_siblings = self.siblings(self.depth(), &ln_node);
+        let root = self.root()?;
+        let size = self.size()?;
+        let ln_siblings = self.siblings(self.depth.at(size), &ln_node)?;
 
         Ok(ExclusionProof {
             root,
@@ -150,6 +391,176 @@ where
             node_key: key,
         })
     }
+
+    /// Generates a [MultiProof] for all the given `keys`.
+    pub fn multi_proof<I>(&self, keys: I) -> ImtResult<MultiProof<NodeK, NodeV>>
+    where
+        I: IntoIterator<Item = NodeK>,
+    {
+        let keys = keys.into_iter().collect::<Vec<_>>();
+
+        // Collect the nodes (or their ln nodes) proving the keys, sorted by index.
+        let mut nodes = BTreeMap::new();
+        for key in &keys {
+            let node = match self.node(key)? {
+                Some(node) => node,
+                None => self
+                    .low_nullifier(key)?
+                    .ok_or_else(|| ImtError::LowNullifierNotFound(format!("{:?}", key.as_ref())))?,
+            };
+
+            nodes.insert(node.index, node);
+        }
+
+        let size = self.size()?;
+        let indices = nodes.keys().copied().collect::<Vec<_>>();
+        let siblings = multi_proof_siblings_positions(self.depth.at(size), &indices)
+            .into_iter()
+            .map(|(level, index)| self.sibling(level, index))
+            .collect::<ImtResult<_>>()?;
+
+        Ok(MultiProof {
+            root: self.root()?,
+            size,
+            keys,
+            nodes: nodes.into_values().collect(),
+            siblings,
+        })
+    }
+}
+
+impl<Hasher, NodeK, NodeV, Storage> Imt<Hasher, NodeK, NodeV, Storage>
+where
+    Hasher: tiny_keccak::Hasher,
+    NodeK: NodeKey,
+    NodeV: NodeValue,
+    Storage: ImtStorageReader<NodeK = NodeK, NodeV = NodeV>,
+{
+    /// Verifies the integrity of the imt persisted in storage.
+    ///
+    /// All the nodes are walked in key order by following the low nullifier linked list from the 0 node,
+    /// making sure the list is sorted and closed and that each node is indexed under its index. The nodes
+    /// iterated by index are then checked to be exactly the ones reached by the list (i.e. no node is
+    /// unreachable). Every level hash is finally recomputed from the leaves (the indices that are not
+    /// reached are expected to be deleted nodes) and checked against the cached hashes, and the resulting
+    /// root is checked against the stored root and size.
+    pub fn verify_integrity(&self) -> ImtResult<()> {
+        let size = self.size()?;
+        if size == 0 {
+            return Err(ImtError::Corrupted("size is zero".to_string()));
+        }
+
+        // NOTE: An empty storage is read as the imt only containing the (unpersisted) 0 node.
+        let is_empty = self.is_empty()?;
+
+        // Walk the linked list and collect the leaves keys and hashes.
+        let mut leaves = vec![None; size as usize];
+        let mut node = self
+            .node(&NodeK::default())?
+            .ok_or_else(|| ImtError::Corrupted("0 node is missing".to_string()))?;
+
+        loop {
+            let key = format!("{:?}", node.key.as_ref());
+
+            let leaf = leaves.get_mut(node.index as usize).ok_or_else(|| {
+                ImtError::Corrupted(format!("node `{key}` index is out of bounds"))
+            })?;
+
+            if leaf.is_some() {
+                return Err(ImtError::Corrupted(format!(
+                    "node `{key}` index is already used"
+                )));
+            }
+
+            let node_key = self
+                .storage
+                .get_node_key(node.index)
+                .map_err(ImtError::storage)?;
+            if !is_empty && node_key.as_ref() != Some(&node.key) {
+                return Err(ImtError::Corrupted(format!(
+                    "node `{key}` is not indexed at its index"
+                )));
+            }
+
+            *leaf = Some((node.key.clone(), node.hash((self.hasher_factory)())));
+
+            // The list is closed by pointing back to the 0 node.
+            if node.next_key == NodeK::default() {
+                break;
+            }
+
+            if node.next_key <= node.key {
+                return Err(ImtError::Corrupted(format!(
+                    "node `{key}` next key is not sorted"
+                )));
+            }
+
+            node = self
+                .node(&node.next_key)?
+                .ok_or_else(|| ImtError::Corrupted(format!("node `{key}` next node is missing")))?;
+        }
+
+        // Ensure the nodes iterated by index are exactly the ones reached by the linked list.
+        let mut indexed_count = 0;
+        for node in self.nodes_by_index(..) {
+            let node = node?;
+            let is_reached = leaves
+                .get(node.index as usize)
+                .and_then(Option::as_ref)
+                .is_some_and(|(key, _)| *key == node.key);
+
+            if !is_reached {
+                return Err(ImtError::Corrupted(format!(
+                    "node `{:?}` is not reachable",
+                    node.key.as_ref()
+                )));
+            }
+
+            indexed_count += 1;
+        }
+
+        let reached_count = leaves.iter().flatten().count();
+        if indexed_count != reached_count {
+            return Err(ImtError::Corrupted(format!(
+                "{reached_count} nodes are reachable but {indexed_count} are indexed"
+            )));
+        }
+
+        // Recompute every level hash from the leaves and compare them with the cached ones.
+        let depth = self.depth.at(size);
+        let mut hashes = leaves
+            .into_iter()
+            .map(|leaf| leaf.map_or(DELETED_NODE_HASH, |(_, hash)| hash))
+            .collect::<Vec<_>>();
+
+        for level in 0..=depth {
+            for (index, hash) in hashes.iter().enumerate() {
+                if self.hash(level, index as u64)? != Some(*hash) {
+                    return Err(ImtError::Corrupted(format!(
+                        "hash ({level}; {index}) does not match"
+                    )));
+                }
+            }
+
+            if level < depth {
+                let zero_hash = self.zero_hashes.get(level as usize);
+                hashes = parent_hashes(self.hasher_factory, &hashes, zero_hash);
+            }
+        }
+
+        // NOTE: The root of an empty storage is not persisted but computed on the fly.
+        let root = root_with_size(self.hasher_factory, &hashes[0], size);
+        let stored_root = if is_empty {
+            Some(self.root()?)
+        } else {
+            self.storage.get_root().map_err(ImtError::storage)?
+        };
+        if stored_root != Some(root) {
+            return Err(ImtError::Corrupted("root does not match".to_string()));
+        }
+
+        Ok(())
+    }
 }
 
 impl<Hasher, NodeK, NodeV, Storage> Imt<Hasher, NodeK, NodeV, Storage>
@@ -160,51 +571,292 @@ where
     Storage: ImtStorageWriter<NodeK = NodeK, NodeV = NodeV>,
 {
     /// Creates a new imt that provides read and write accesses.
-    pub fn writer(hasher_factory: fn() -> Hasher, storage: Storage) -> Self {
-        let size = storage.get_size();
+    pub fn writer(hasher_factory: fn() -> Hasher, storage: Storage) -> ImtResult<Self> {
+        Self::writer_with_depth(hasher_factory, storage, ImtDepth::Dynamic)
+    }
 
-        let mut imt = Self {
-            hasher_factory,
-            storage,
+    /// Same as [Imt::writer] but for an imt with the given [ImtDepth].
+    ///
+    /// The `depth` MUST be the one the imt has been written with (if any).
+    pub fn writer_with_depth(
+        hasher_factory: fn() -> Hasher,
+        storage: Storage,
+        depth: ImtDepth,
+    ) -> ImtResult<Self> {
+        let size = storage.get_size().map_err(ImtError::storage)?;
 
-            _phantom_data_k: std::marker::PhantomData,
-            _phantom_data_v: std::marker::PhantomData,
-        };
+        let mut imt = Self::new(hasher_factory, storage, depth);
 
         // If the tree was empty in storage, insert the 0 node.
         if size.is_none() {
             debug!("Inserting the 0 node");
-            let init_node = ImtNode {
-                index: Default::default(),
-                key: Default::default(),
-                value: Default::default(),
-                next_key: Default::default(),
-            };
+            let init_node = Self::zero_node();
 
             // Save the size (1) in storage and set the node.
-            imt.storage.set_size(1);
-            imt.patch_tree_with_node(0, init_node);
+            imt.storage.set_size(1).map_err(ImtError::storage)?;
+            imt.patch_tree_with_node(depth.at(1), init_node)?;
+            imt.refresh_root(depth.at(1))?;
         }
 
         debug!(
-            size = imt.size(),
-            depth = imt.depth(),
-            root = format!("{:?}", imt.root()),
+            size = imt.size()?,
+            depth = imt.depth()?,
+            root = format!("{:?}", imt.root()?),
             "Imt writer initialized"
         );
 
-        imt
+        Ok(imt)
+    }
+
+    /// Creates a new imt from the given (key; value) `leaves` and writes it to the (empty) `storage`.
+    ///
+    /// The leaves are sorted by key and indexed in that order right after the 0 node (a leaf with the
+    /// default key sets the 0 node value), then the linked list and all the level hashes are built
+    /// bottom-up in a single pass, without generating any proof. Building from leaves already sorted by
+    /// key yields the same imt as inserting them one by one in that order.
+    pub fn from_leaves<I>(
+        hasher_factory: fn() -> Hasher,
+        storage: Storage,
+        leaves: I,
+    ) -> ImtResult<Self>
+    where
+        I: IntoIterator<Item = (NodeK, NodeV)>,
+    {
+        Self::from_leaves_with_depth(hasher_factory, storage, leaves, ImtDepth::Dynamic)
+    }
+
+    /// Same as [Imt::from_leaves] but for an imt with the given [ImtDepth].
+    pub fn from_leaves_with_depth<I>(
+        hasher_factory: fn() -> Hasher,
+        storage: Storage,
+        leaves: I,
+        depth: ImtDepth,
+    ) -> ImtResult<Self>
+    where
+        I: IntoIterator<Item = (NodeK, NodeV)>,
+    {
+        if storage.get_size().map_err(ImtError::storage)?.is_some() {
+            return Err(ImtError::StorageNotEmpty);
+        }
+
+        let mut imt = Self::new(hasher_factory, storage, depth);
+
+        let mut leaves = leaves.into_iter().collect::<Vec<_>>();
+        leaves.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+
+        if let Some(pair) = leaves.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+            return Err(ImtError::NodeAlreadyExist(format!(
+                "{:?}",
+                pair[0].0.as_ref()
+            )));
+        }
+
+        // Prepend the 0 node unless its value is provided.
+        if leaves.first().map(|(key, _)| key) != Some(&NodeK::default()) {
+            leaves.insert(0, Default::default());
+        }
+
+        if leaves.len() as u64 > depth.capacity() {
+            return Err(ImtError::Full);
+        }
+
+        debug!(size = leaves.len(), "Building the imt from leaves");
+
+        // Link the nodes in key order (the last one points back to the 0 node) and cache their hashes.
+        let size = leaves.len() as u64;
+        let mut hashes = Vec::with_capacity(leaves.len());
+        let mut leaves = leaves.into_iter().enumerate().peekable();
+        while let Some((index, (key, value))) = leaves.next() {
+            let next_key = leaves
+                .peek()
+                .map(|(_, (next_key, _))| next_key.clone())
+                .unwrap_or_default();
+
+            let node = ImtNode {
+                index: index as u64,
+                key,
+                value,
+                next_key,
+            };
+
+            let hash = node.hash(hasher_factory());
+            imt.storage.set_node(node).map_err(ImtError::storage)?;
+            imt.storage
+                .set_hash(0, index as u64, hash)
+                .map_err(ImtError::storage)?;
+            hashes.push(hash);
+        }
+
+        // Build the upper levels bottom-up.
+        for level in 0..depth.at(size) {
+            let zero_hash = imt.zero_hashes.get(level as usize);
+            hashes = parent_hashes(hasher_factory, &hashes, zero_hash);
+            for (index, hash) in hashes.iter().enumerate() {
+                imt.storage
+                    .set_hash(level + 1, index as u64, *hash)
+                    .map_err(ImtError::storage)?;
+            }
+        }
+
+        imt.storage.set_size(size).map_err(ImtError::storage)?;
+        imt.refresh_root(depth.at(size))?;
+
+        debug!(
+            size,
+            root = format!("{:?}", imt.root()?),
+            "Imt built from leaves"
+        );
+
+        Ok(imt)
     }
 
     /// Sets a (key; value) pair in the imt and returns the corresponding [MutateProof] proof.
     pub fn set_node(&mut self, key: NodeK, value: NodeV) -> ImtResult<MutateProof<NodeK, NodeV>> {
-        if self.storage.get_node(&key).is_some() {
+        if self.node(&key)?.is_some() {
             Ok(MutateProof::Update(self.update_node(key, value)?))
         } else {
             Ok(MutateProof::Insert(self.insert_node(key, value)?))
         }
     }
 
+    /// Sets all the given (key; value) pairs in the imt in a single pass.
+    ///
+    /// All the leaves are mutated first (in order, so a key set several times ends up with its last
+    /// value), then every internal hash above the touched leaves is recomputed once, bottom-up, and the
+    /// root is refreshed once. No proof is generated, see [Imt::set_nodes_with_proofs] to prove the
+    /// mutations.
+    ///
+    /// If any of the mutations fails, none of them are persisted.
+    pub fn set_nodes<I>(&mut self, nodes: I) -> ImtResult<()>
+    where
+        I: IntoIterator<Item = (NodeK, NodeV)>,
+    {
+        let hasher_factory = self.hasher_factory;
+        let mut imt = self.buffered();
+
+        // Mutate the leaves and keep the latest version of each touched node, by index.
+        let old_size = imt.size()?;
+        let mut size = old_size;
+        let mut dirty_nodes = BTreeMap::new();
+        for (key, value) in nodes {
+            let node = match imt.node(&key)? {
+                Some(node) => ImtNode { value, ..node },
+                None => {
+                    if size >= imt.depth.capacity() {
+                        return Err(ImtError::Full);
+                    }
+
+                    let mut ln_node = imt.low_nullifier(&key)?.ok_or_else(|| {
+                        ImtError::LowNullifierNotFound(format!("{:?}", key.as_ref()))
+                    })?;
+
+                    let node = ImtNode {
+                        index: size,
+                        key: key.clone(),
+                        value,
+                        next_key: ln_node.next_key,
+                    };
+
+                    ln_node.next_key = key;
+                    imt.storage
+                        .set_node(ln_node.clone())
+                        .map_err(ImtError::storage)?;
+                    dirty_nodes.insert(ln_node.index, ln_node);
+
+                    size += 1;
+                    node
+                }
+            };
+
+            imt.storage
+                .set_node(node.clone())
+                .map_err(ImtError::storage)?;
+            dirty_nodes.insert(node.index, node);
+        }
+
+        if dirty_nodes.is_empty() {
+            return Ok(());
+        }
+
+        if size != old_size {
+            imt.storage.set_size(size).map_err(ImtError::storage)?;
+        }
+
+        // Refresh the hashes level by level, from the touched leaves up to the root.
+        let depth = imt.depth.at(size);
+        let mut hashes = dirty_nodes
+            .into_values()
+            .map(|node| (node.index, node.hash(hasher_factory())))
+            .collect::<BTreeMap<_, _>>();
+
+        for level in 0..=depth {
+            for (index, hash) in &hashes {
+                imt.storage
+                    .set_hash(level, *index, *hash)
+                    .map_err(ImtError::storage)?;
+            }
+
+            if level == depth {
+                break;
+            }
+
+            let mut parent_hashes = BTreeMap::new();
+            for parent_index in hashes.keys().map(|index| index / 2) {
+                if parent_hashes.contains_key(&parent_index) {
+                    continue;
+                }
+
+                let left = imt.sibling(level, 2 * parent_index)?;
+                let right = imt.sibling(level, 2 * parent_index + 1)?;
+                parent_hashes.insert(parent_index, hash_children(hasher_factory, &left, &right));
+            }
+
+            hashes = parent_hashes;
+        }
+
+        imt.refresh_root(depth)?;
+        imt.storage.commit().map_err(ImtError::storage)
+    }
+
+    /// Same as [Imt::set_nodes] but also returns the [MutateProof] proof of each mutation.
+    ///
+    /// The mutations are applied one by one on top of an in-memory buffer, so that each node and hash
+    /// is persisted only once no matter how many mutations touched it. The returned proofs must be
+    /// verified sequentially, each one starting from the root returned by the previous one.
+    ///
+    /// If any of the mutations fails, none of them are persisted.
+    pub fn set_nodes_with_proofs<I>(
+        &mut self,
+        nodes: I,
+    ) -> ImtResult<Vec<MutateProof<NodeK, NodeV>>>
+    where
+        I: IntoIterator<Item = (NodeK, NodeV)>,
+    {
+        let mut imt = self.buffered();
+
+        let proofs = nodes
+            .into_iter()
+            .map(|(key, value)| imt.set_node(key, value))
+            .collect::<ImtResult<Vec<_>>>()?;
+
+        imt.storage.commit().map_err(ImtError::storage)?;
+
+        Ok(proofs)
+    }
+
+    /// Returns an imt writing to an in-memory buffer on top of [Imt::storage].
+    fn buffered(&mut self) -> Imt<Hasher, NodeK, NodeV, BufferedImtStorage<'_, Storage>> {
+        Imt {
+            hasher_factory: self.hasher_factory,
+            depth: self.depth,
+            zero_hashes: self.zero_hashes.clone(),
+            storage: BufferedImtStorage::new(&mut self.storage),
+
+            _phantom_data_k: std::marker::PhantomData,
+            _phantom_data_v: std::marker::PhantomData,
+        }
+    }
+
     /// Inserts a new (key; value) in the imt and returns the corresponding [InsertProof] proof.
     pub fn insert_node(
         &mut self,
@@ -212,20 +864,25 @@ where
         value: NodeV,
     ) -> ImtResult<InsertProof<NodeK, NodeV>> {
         // Ensure key does not already exist in the tree.
-        if self.storage.get_node(&key).is_some() {
+        if self.node(&key)?.is_some() {
             return Err(ImtError::NodeAlreadyExist(format!("{:?}", key.as_ref())));
         }
 
-        let old_size = self.size();
-        let old_root = self.root();
-        let old_depth = depth(old_size);
+        let old_size = self.size()?;
+        let old_root = self.root()?;
+        let old_depth = self.depth.at(old_size);
+
+        // Ensure the imt is not full.
+        if old_size >= self.depth.capacity() {
+            return Err(ImtError::Full);
+        }
 
         // Get the ln node.
         let mut ln_node = self
-            .low_nullifier(&key)
+            .low_nullifier(&key)?
             .ok_or_else(|| ImtError::LowNullifierNotFound(format!("{:?}", key.as_ref())))?;
 
-        let ln_siblings = self.siblings(old_depth, &ln_node);
+        let ln_siblings = self.siblings(old_depth, &ln_node)?;
 
         // Create the new node.
         let node = ImtNode {
@@ -237,17 +894,18 @@ where
 
         // Update the ln node and refresh the tree.
         ln_node.next_key = key;
-        self.patch_tree_with_node(old_depth, ln_node.clone());
+        self.patch_tree_with_node(old_depth, ln_node.clone())?;
 
         // Increment the imt size.
         // NOTE: Must be done prior to inserting the new node.
         let new_size = old_size + 1;
-        self.storage.set_size(new_size);
+        self.storage.set_size(new_size).map_err(ImtError::storage)?;
 
         // Insert the new node and refresh the tree.
-        let new_depth = depth(new_size);
-        let node_siblings = self.patch_tree_with_node(new_depth, node.clone());
-        let updated_ln_siblings = self.siblings(new_depth, &ln_node);
+        let new_depth = self.depth.at(new_size);
+        let node_siblings = self.patch_tree_with_node(new_depth, node.clone())?;
+        let updated_ln_siblings = self.siblings(new_depth, &ln_node)?;
+        self.refresh_root(new_depth)?;
 
         // NOTE: Reset the `ln_node.next_key` value before using it in ImtMutate::insert.
         // TODO: Improve this to avoid doing this hacky reset.
@@ -271,18 +929,18 @@ where
         key: NodeK,
         value: NodeV,
     ) -> ImtResult<UpdateProof<NodeK, NodeV>> {
-        let old_root = self.root();
-        let size = self.size();
+        let old_root = self.root()?;
+        let size = self.size()?;
 
         let mut node = self
-            .storage
-            .get_node(&key)
+            .node(&key)?
             .ok_or_else(|| ImtError::NodeDoesNotExist(format!("{:?}", key.as_ref())))?;
 
         let old_node = node.clone();
         node.value = value.clone();
 
-        let node_siblings = self.patch_tree_with_node(depth(size), node);
+        let node_siblings = self.patch_tree_with_node(self.depth.at(size), node)?;
+        self.refresh_root(self.depth.at(size))?;
 
         Ok(UpdateProof {
             old_root,
@@ -293,28 +951,90 @@ where
         })
     }
 
+    /// Deletes the given `key` from the imt and returns the corresponding [DeleteProof] proof.
+    ///
+    /// The deleted node index is left empty (its leaf hash is set to [DELETED_NODE_HASH]) and the
+    /// imt size is NOT decremented.
+    pub fn delete_node(&mut self, key: NodeK) -> ImtResult<DeleteProof<NodeK, NodeV>> {
+        // Ensure the 0 node is never deleted.
+        if key == NodeK::default() {
+            return Err(ImtError::NodeNotDeletable(format!("{:?}", key.as_ref())));
+        }
+
+        let old_root = self.root()?;
+        let size = self.size()?;
+        let depth = self.depth.at(size);
+
+        let node = self
+            .node(&key)?
+            .ok_or_else(|| ImtError::NodeDoesNotExist(format!("{:?}", key.as_ref())))?;
+
+        let ln_node = self
+            .storage
+            .get_ln_node(&key)
+            .map_err(ImtError::storage)?
+            .ok_or_else(|| ImtError::LowNullifierNotFound(format!("{:?}", key.as_ref())))?;
+
+        // Remove the node and clear its leaf.
+        self.storage.remove_node(&key).map_err(ImtError::storage)?;
+        let node_siblings = self.patch_tree_with_hash(depth, node.index, DELETED_NODE_HASH)?;
+
+        // Update the ln node to skip the deleted node and refresh the tree.
+        let updated_ln_node = ImtNode {
+            next_key: node.next_key.clone(),
+            ..ln_node.clone()
+        };
+        let ln_siblings = self.patch_tree_with_node(depth, updated_ln_node)?;
+        self.refresh_root(depth)?;
+
+        Ok(DeleteProof {
+            old_root,
+            size,
+            node,
+            node_siblings,
+            ln_node,
+            ln_siblings,
+        })
+    }
+
     /// Sets the given [ImtNode] in the imt and returns the updated list of siblings for the given `node`.
     ///
-    /// This refreshes the list of hashes based on the provided `node` and as well as the imt root.
+    /// This refreshes the list of hashes based on the provided `node`, but NOT the imt root which must
+    /// be refreshed by the caller once all the nodes have been patched.
     fn patch_tree_with_node(
         &mut self,
         depth: u8,
         node: ImtNode<NodeK, NodeV>,
-    ) -> Vec<Option<Hash256>> {
-        let mut index = node.index;
-        let hasher_factory = self.hasher_factory;
-        let mut hash = node.hash(hasher_factory());
+    ) -> ImtResult<Vec<Option<Hash256>>> {
+        let index = node.index;
+        let hash = node.hash((self.hasher_factory)());
+
+        self.storage.set_node(node).map_err(ImtError::storage)?;
+
+        self.patch_tree_with_hash(depth, index, hash)
+    }
 
-        self.storage.set_node(node);
+    /// Sets the given leaf `hash` at `index` in the imt and returns the updated list of siblings for this leaf.
+    ///
+    /// Same as [Imt::patch_tree_with_node] but without registering any [ImtNode].
+    fn patch_tree_with_hash(
+        &mut self,
+        depth: u8,
+        mut index: u64,
+        mut hash: Hash256,
+    ) -> ImtResult<Vec<Option<Hash256>>> {
+        let hasher_factory = self.hasher_factory;
 
         // Cache the node hash.
-        self.storage.set_hash(0, index, hash);
+        self.storage
+            .set_hash(0, index, hash)
+            .map_err(ImtError::storage)?;
 
         // Climb up the tree and refresh the hashes.
         let mut siblings = Vec::with_capacity(depth as _);
         for level in 0..depth {
             let sibling_index = if index % 2 == 0 { index + 1 } else { index - 1 };
-            let sibling_hash = self.storage.get_hash(level, sibling_index);
+            let sibling_hash = self.sibling(level, sibling_index)?;
             siblings.push(sibling_hash);
 
             let (left, right) = if index % 2 == 0 {
@@ -323,53 +1043,665 @@ where
                 (sibling_hash, Some(hash))
             };
 
-            let mut hasher = hasher_factory();
-            match (left, right) {
-                (None, None) => unreachable!(),
-                (None, Some(right)) => hasher.update(&right),
-                (Some(left), None) => hasher.update(&left),
-                (Some(left), Some(right)) => {
-                    hasher.update(&left);
-                    hasher.update(&right);
-                }
-            };
-
-            hasher.finalize(&mut hash);
+            hash = hash_children(hasher_factory, &left, &right);
 
             index /= 2;
 
-            self.storage.set_hash(level + 1, index, hash);
+            self.storage
+                .set_hash(level + 1, index, hash)
+                .map_err(ImtError::storage)?;
         }
 
-        // Refreshes the imt root.
-        self.refresh_root(depth);
-
-        siblings
+        Ok(siblings)
     }
 
     /// Refreshes the imt root.
-    fn refresh_root(&mut self, depth: u8) {
-        let size = self.size();
+    fn refresh_root(&mut self, depth: u8) -> ImtResult<()> {
+        let size = self.size()?;
+
+        // NOTE: The top hash is always set once a node has been patched at this depth.
+        let root = self
+            .hash(depth, 0)?
+            .ok_or_else(|| ImtError::Corrupted(format!("hash ({depth}; 0) is missing")))?;
+
+        let root_with_size = root_with_size(self.hasher_factory, &root, size);
+        self.storage
+            .set_root(root_with_size)
+            .map_err(ImtError::storage)
+    }
+}
+
+/// Returns the hashes of the empty subtrees of each level of an imt of the given `depth`.
+///
+/// The empty leaves are hashed as [DELETED_NODE_HASH].
+fn zero_hashes<H: tiny_keccak::Hasher>(hasher_factory: fn() -> H, depth: u8) -> Vec<Hash256> {
+    let mut hashes = Vec::with_capacity(depth as _);
+    let mut hash = DELETED_NODE_HASH;
+    for _ in 0..depth {
+        hashes.push(hash);
+        hash = hash_children(hasher_factory, &Some(hash), &Some(hash));
+    }
+
+    hashes
+}
 
-        // TODO: Is it always safe to unwrap_or_default here?
-        let root = self.storage.get_hash(depth, 0).unwrap_or_default();
+/// Returns the (level; index) positions of the siblings needed to compute the root of an imt
+/// of the given `depth` from the leaves at `indices`.
+///
+/// The `indices` MUST be sorted and deduplicated. The returned positions are sorted by level
+/// and then by index.
+fn multi_proof_siblings_positions(depth: u8, indices: &[u64]) -> Vec<(u8, u64)> {
+    let mut positions = vec![];
+    let mut indices = indices.to_vec();
+
+    for level in 0..depth {
+        let mut parent_indices = Vec::with_capacity(indices.len());
+
+        let mut i = 0;
+        while i < indices.len() {
+            let index = indices[i];
+
+            if index % 2 == 0 && indices.get(i + 1) == Some(&(index + 1)) {
+                i += 1;
+            } else {
+                let sibling_index = if index % 2 == 0 { index + 1 } else { index - 1 };
+                positions.push((level, sibling_index));
+            }
 
-        let mut root_with_size = [0; 32];
-        let mut k = Keccak::v256();
-        k.update(&root);
-        k.update(&size.to_be_bytes());
-        k.finalize(&mut root_with_size);
+            parent_indices.push(index / 2);
+            i += 1;
+        }
 
-        self.storage.set_root(root_with_size);
+        indices = parent_indices;
     }
+
+    positions
+}
+
+/// Computes the hashes of the level above the given level `hashes`.
+///
+/// If the level has an odd number of hashes, the last one is hashed with the given `zero_hash`
+/// (or alone if there is none).
+fn parent_hashes<H: tiny_keccak::Hasher>(
+    hasher_factory: fn() -> H,
+    hashes: &[Hash256],
+    zero_hash: Option<&Hash256>,
+) -> Vec<Hash256> {
+    hashes
+        .chunks(2)
+        .map(|children| {
+            hash_children(
+                hasher_factory,
+                &Some(children[0]),
+                &children.get(1).or(zero_hash).copied(),
+            )
+        })
+        .collect()
 }
 
-/// Computes the depth of the tree based on its provided `size`.
-fn depth(size: u64) -> u8 {
-    let depth = (u64::BITS - size.leading_zeros() - 1) as u8;
-    if size == (1_u64 << depth) {
-        depth
-    } else {
-        depth + 1
+#[cfg(test)]
+mod tests {
+    use std::ops::Bound;
+    use tiny_keccak::Keccak;
+
+    use crate::{
+        proof::ProofError,
+        storage::{btree_imt_storage::BTreeImtStorage, versioned::VersionedImtStorageWriter},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_set_nodes() {
+        let nodes = vec![
+            ([1; 32], [42; 32]),
+            ([5; 32], [42; 32]),
+            ([3; 32], [42; 32]),
+            ([1; 32], [43; 32]),
+            ([2; 32], [42; 32]),
+            ([4; 32], [42; 32]),
+            ([3; 32], [44; 32]),
+        ];
+
+        // Apply the mutations one by one.
+        let mut expected_imt =
+            Imt::writer(Keccak::v256, BTreeImtStorage::default()).expect("imt writer failed");
+        for (key, value) in nodes.clone() {
+            expected_imt.set_node(key, value).expect("set_node failed");
+        }
+
+        // Apply the mutations in a single batch.
+        let mut sut =
+            Imt::writer(Keccak::v256, BTreeImtStorage::default()).expect("imt writer failed");
+        sut.set_nodes(nodes.clone()).expect("set_nodes failed");
+
+        // Ensure the final states match.
+        assert_eq!(
+            sut.root().expect("root failed"),
+            expected_imt.root().expect("root failed")
+        );
+        assert_eq!(sut.storage, expected_imt.storage);
+
+        // Ensure a batch on top of an existing imt (growing its depth) matches too.
+        let more_nodes = [
+            ([6; 32], [42; 32]),
+            ([0; 32], [45; 32]),
+            ([8; 32], [42; 32]),
+            ([2; 32], [46; 32]),
+            ([7; 32], [42; 32]),
+        ];
+        for (key, value) in more_nodes {
+            expected_imt.set_node(key, value).expect("set_node failed");
+        }
+        sut.set_nodes(more_nodes).expect("set_nodes failed");
+        assert_eq!(sut.storage, expected_imt.storage);
+        assert!(sut.verify_integrity().is_ok());
+
+        // Ensure a failing batch is not persisted.
+        let mut sut =
+            Imt::writer_with_depth(Keccak::v256, BTreeImtStorage::default(), ImtDepth::Fixed(1))
+                .expect("imt writer failed");
+        let res = sut.set_nodes([([1; 32], [42; 32]), ([2; 32], [42; 32])]);
+        assert!(matches!(res, Err(ImtError::Full)));
+        assert_eq!(sut.size().expect("size failed"), 1);
+    }
+
+    #[test]
+    fn test_set_nodes_with_proofs() {
+        let nodes = vec![
+            ([1; 32], [42; 32]),
+            ([5; 32], [42; 32]),
+            ([3; 32], [42; 32]),
+            ([1; 32], [43; 32]),
+            ([2; 32], [42; 32]),
+        ];
+
+        // Apply the mutations one by one.
+        let mut expected_imt =
+            Imt::writer(Keccak::v256, BTreeImtStorage::default()).expect("imt writer failed");
+        for (key, value) in nodes.clone() {
+            expected_imt.set_node(key, value).expect("set_node failed");
+        }
+
+        // Apply the mutations in a single batch.
+        let mut sut =
+            Imt::writer(Keccak::v256, BTreeImtStorage::default()).expect("imt writer failed");
+        let mut root = sut.root().expect("root failed");
+        let proofs = sut
+            .set_nodes_with_proofs(nodes)
+            .expect("set_nodes_with_proofs failed");
+
+        // Ensure the returned proofs verify sequentially.
+        assert_eq!(proofs.len(), 5);
+        for proof in proofs {
+            root = proof
+                .verify(Keccak::v256, root)
+                .expect("proof verification failed");
+        }
+
+        // Ensure the final states match.
+        assert_eq!(root, expected_imt.root().expect("root failed"));
+        assert_eq!(sut.storage, expected_imt.storage);
+    }
+
+    #[test]
+    fn test_empty_reader() {
+        let storage: BTreeImtStorage<[u8; 32], [u8; 32]> = Default::default();
+        let sut = Imt::reader(Keccak::v256, &storage).expect("imt reader failed");
+
+        // Ensure the empty imt is read as the imt initialized by the writer.
+        let imt = Imt::writer(Keccak::v256, storage.clone()).expect("imt writer failed");
+        assert_eq!(sut.size().expect("size failed"), 1);
+        assert_eq!(
+            sut.root().expect("root failed"),
+            imt.root().expect("root failed")
+        );
+        assert!(sut.verify_integrity().is_ok());
+
+        // Ensure proofs can be generated against the empty imt.
+        let proof = sut
+            .inclusion_proof([0; 32])
+            .expect("inclusion proof failed");
+        assert!(proof.verify(Keccak::v256).is_ok());
+
+        let proof = sut
+            .exclusion_proof([1; 32])
+            .expect("exclusion proof failed");
+        assert_eq!(proof.ln_node.key, [0; 32]);
+        assert!(proof.verify(Keccak::v256).is_ok());
+        assert_eq!(proof.root, imt.root().expect("root failed"));
+        assert_eq!(proof.node_key, [1; 32]);
+    }
+
+    #[test]
+    fn test_nodes_by_key() {
+        // Instanciate an imt with a few nodes.
+        let mut sut =
+            Imt::writer(Keccak::v256, BTreeImtStorage::default()).expect("imt writer failed");
+        sut.set_nodes([
+            ([3; 32], [42; 32]),
+            ([1; 32], [42; 32]),
+            ([5; 32], [42; 32]),
+            ([2; 32], [42; 32]),
+            ([4; 32], [42; 32]),
+        ])
+        .expect("set_nodes failed");
+        sut.delete_node([2; 32]).expect("delete failed");
+
+        let keys = |range: (Bound<[u8; 32]>, Bound<[u8; 32]>)| {
+            sut.nodes_by_key(range)
+                .map(|node| node.map(|node| node.key[0]))
+                .collect::<ImtResult<Vec<_>>>()
+                .expect("nodes_by_key failed")
+        };
+
+        assert_eq!(keys((Bound::Unbounded, Bound::Unbounded)), [0, 1, 3, 4, 5]);
+        assert_eq!(
+            keys((Bound::Included([1; 32]), Bound::Included([4; 32]))),
+            [1, 3, 4]
+        );
+        assert_eq!(
+            keys((Bound::Excluded([1; 32]), Bound::Excluded([4; 32]))),
+            [3]
+        );
+        assert_eq!(
+            keys((Bound::Included([2; 32]), Bound::Unbounded)),
+            [3, 4, 5]
+        );
+        assert_eq!(keys((Bound::Excluded([5; 32]), Bound::Unbounded)), []);
+
+        // Ensure the nodes can be paginated.
+        let page = sut
+            .nodes_by_key(..)
+            .take(2)
+            .collect::<ImtResult<Vec<_>>>()
+            .expect("nodes_by_key failed");
+        let last_key = page.last().expect("page is empty").key;
+        assert_eq!(
+            keys((Bound::Excluded(last_key), Bound::Unbounded)),
+            [3, 4, 5]
+        );
+
+        // Ensure a scanned node that is not linked by its predecessor is reported.
+        let mut broken_node = sut
+            .node(&[3; 32])
+            .expect("node failed")
+            .expect("missing node");
+        broken_node.next_key = [5; 32];
+        sut.storage.set_node(broken_node).expect("set_node failed");
+        let res = sut.nodes_by_key(..).collect::<ImtResult<Vec<_>>>();
+        assert!(matches!(res, Err(ImtError::Corrupted(e)) if e.ends_with("next node is missing")));
+    }
+
+    #[test]
+    fn test_nodes_by_index() {
+        // Instanciate an imt with a few nodes.
+        let mut sut =
+            Imt::writer(Keccak::v256, BTreeImtStorage::default()).expect("imt writer failed");
+        for key in [[3; 32], [1; 32], [5; 32], [2; 32]] {
+            sut.insert_node(key, [42; 32]).expect("insert failed");
+        }
+        sut.delete_node([1; 32]).expect("delete failed");
+        sut.insert_node([1; 32], [43; 32]).expect("insert failed");
+
+        let keys = |nodes: Vec<ImtResult<ImtNode<[u8; 32], [u8; 32]>>>| {
+            nodes
+                .into_iter()
+                .map(|node| node.map(|node| node.key[0]))
+                .collect::<ImtResult<Vec<_>>>()
+                .expect("nodes_by_index failed")
+        };
+
+        // Ensure the deleted index is skipped.
+        assert_eq!(keys(sut.nodes_by_index(..).collect()), [0, 3, 5, 2, 1]);
+        assert_eq!(keys(sut.nodes_by_index(1..=3).collect()), [3, 5]);
+        assert_eq!(keys(sut.nodes_by_index(4..).collect()), [2, 1]);
+        assert_eq!(keys(sut.nodes_by_index(6..).collect()), []);
+        assert_eq!(
+            keys(sut.nodes_by_index(..).skip(1).take(2).collect()),
+            [3, 5]
+        );
+    }
+
+    #[test]
+    fn test_fixed_depth() {
+        let depth = ImtDepth::Fixed(4);
+
+        // Ensure the empty imt is read as the imt initialized by the writer.
+        let storage: BTreeImtStorage<[u8; 32], [u8; 32]> = Default::default();
+        let empty_root = Imt::reader_with_depth(Keccak::v256, &storage, depth)
+            .expect("imt reader failed")
+            .root()
+            .expect("root failed");
+
+        let mut sut =
+            Imt::writer_with_depth(Keccak::v256, storage, depth).expect("imt writer failed");
+        let mut root = sut.root().expect("root failed");
+        assert_eq!(root, empty_root);
+        assert_eq!(sut.depth().expect("depth failed"), 4);
+
+        // Ensure all the proofs have the same number of siblings and verify with the fixed depth.
+        let keys = [[3; 32], [1; 32], [5; 32], [2; 32], [4; 32]];
+        for key in keys {
+            let proof = sut.insert_node(key, [42; 32]).expect("insert failed");
+            assert!(proof
+                .ln_siblings
+                .iter()
+                .chain(&proof.node_siblings)
+                .chain(&proof.updated_ln_siblings)
+                .all(Option::is_some));
+            assert_eq!(proof.ln_siblings.len(), 4);
+            assert_eq!(proof.updated_ln_siblings.len(), 4);
+
+            assert!(matches!(
+                proof.verify(Keccak::v256, root),
+                Err(ProofError::SizeMismatch)
+            ));
+            root = proof
+                .verify_with_depth(Keccak::v256, root, depth)
+                .expect("proof verification failed");
+        }
+
+        let proof = sut.delete_node([2; 32]).expect("delete failed");
+        root = proof
+            .verify_with_depth(Keccak::v256, root, depth)
+            .expect("proof verification failed");
+        assert_eq!(root, sut.root().expect("root failed"));
+        assert!(sut.verify_integrity().is_ok());
+
+        let proof = sut
+            .exclusion_proof([6; 32])
+            .expect("exclusion proof failed");
+        assert!(proof.verify_with_depth(Keccak::v256, depth).is_ok());
+
+        let proof = sut
+            .multi_proof([[1; 32], [2; 32], [5; 32]])
+            .expect("multi proof failed");
+        let res = proof
+            .verify_with_depth(Keccak::v256, depth)
+            .expect("multi proof verification failed");
+        assert_eq!(res, vec![Some([42; 32]), None, Some([42; 32])]);
+
+        // Ensure building the imt from its leaves gives the same root.
+        let imt = Imt::from_leaves_with_depth(
+            Keccak::v256,
+            BTreeImtStorage::default(),
+            keys.into_iter()
+                .filter(|key| *key != [2; 32])
+                .map(|key| (key, [42; 32])),
+            depth,
+        )
+        .expect("from_leaves failed");
+        assert!(imt.verify_integrity().is_ok());
+        assert_eq!(
+            imt.inclusion_proof([5; 32])
+                .expect("inclusion proof failed")
+                .siblings
+                .len(),
+            4
+        );
+
+        // Ensure a full imt rejects insertions.
+        let mut sut =
+            Imt::writer_with_depth(Keccak::v256, BTreeImtStorage::default(), ImtDepth::Fixed(1))
+                .expect("imt writer failed");
+        sut.insert_node([1; 32], [42; 32]).expect("insert failed");
+        let res = sut.insert_node([2; 32], [42; 32]);
+        assert!(matches!(res, Err(ImtError::Full)));
+    }
+
+    #[test]
+    fn test_from_leaves() {
+        let leaves = vec![
+            ([3; 32], [42; 32]),
+            ([1; 32], [43; 32]),
+            ([5; 32], [44; 32]),
+            ([2; 32], [45; 32]),
+            ([4; 32], [46; 32]),
+        ];
+
+        // Insert the leaves one by one in key order.
+        let mut expected_imt =
+            Imt::writer(Keccak::v256, BTreeImtStorage::default()).expect("imt writer failed");
+        let mut sorted_leaves = leaves.clone();
+        sorted_leaves.sort();
+        for (key, value) in sorted_leaves {
+            expected_imt.insert_node(key, value).expect("insert failed");
+        }
+
+        let sut = Imt::from_leaves(Keccak::v256, BTreeImtStorage::default(), leaves)
+            .expect("from_leaves failed");
+        assert_eq!(
+            sut.root().expect("root failed"),
+            expected_imt.root().expect("root failed")
+        );
+        assert_eq!(sut.storage, expected_imt.storage);
+        assert!(sut.verify_integrity().is_ok());
+
+        // Ensure duplicated keys are rejected.
+        let res = Imt::from_leaves(
+            Keccak::v256,
+            BTreeImtStorage::default(),
+            [([1; 32], [42; 32]), ([1; 32], [43; 32])],
+        );
+        assert!(matches!(res, Err(ImtError::NodeAlreadyExist(_))));
+
+        // Ensure a non empty storage is rejected.
+        let res = Imt::from_leaves(Keccak::v256, sut.storage, []);
+        assert!(matches!(res, Err(ImtError::StorageNotEmpty)));
+    }
+
+    #[test]
+    fn test_delete_node() {
+        let mut sut =
+            Imt::writer(Keccak::v256, BTreeImtStorage::default()).expect("imt writer failed");
+        for key in [[1; 32], [2; 32], [3; 32]] {
+            sut.insert_node(key, [42; 32]).expect("insert failed");
+        }
+
+        // Ensure the 0 node can not be deleted.
+        let res = sut.delete_node([0; 32]);
+        assert!(matches!(res, Err(ImtError::NodeNotDeletable(_))));
+
+        // Delete a node and ensure it is now excluded from the imt.
+        sut.delete_node([2; 32]).expect("delete [2] failed");
+        assert_eq!(sut.size().expect("size failed"), 4);
+
+        let proof = sut
+            .exclusion_proof([2; 32])
+            .expect("exclusion proof failed");
+        assert_eq!(proof.ln_node.key, [1; 32]);
+        assert_eq!(proof.ln_node.next_key, [3; 32]);
+        assert!(proof.verify(Keccak::v256).is_ok());
+
+        let res = sut.delete_node([2; 32]);
+        assert!(matches!(res, Err(ImtError::NodeDoesNotExist(_))));
+
+        // Ensure the deleted node can be inserted again.
+        let proof = sut
+            .insert_node([2; 32], [43; 32])
+            .expect("insert [2] failed");
+        assert_eq!(proof.node.index, 4);
+        assert!(proof.verify(Keccak::v256, proof.old_root).is_ok());
+    }
+
+    #[test]
+    fn test_verify_integrity() {
+        // Instanciate an imt with a few nodes.
+        let mut sut =
+            Imt::writer(Keccak::v256, BTreeImtStorage::default()).expect("imt writer failed");
+        assert!(sut.verify_integrity().is_ok());
+
+        for key in [[1; 32], [5; 32], [3; 32], [4; 32], [2; 32]] {
+            sut.insert_node(key, [42; 32]).expect("insert failed");
+        }
+        sut.update_node([3; 32], [43; 32]).expect("update failed");
+        sut.delete_node([4; 32]).expect("delete failed");
+        assert!(sut.verify_integrity().is_ok());
+
+        // Ensure a corrupted cached hash is detected.
+        let hash = sut.storage.get_hash(1, 1).expect("get_hash failed");
+        sut.storage
+            .set_hash(1, 1, [0xff; 32])
+            .expect("set_hash failed");
+        let res = sut.verify_integrity();
+        assert!(matches!(res, Err(ImtError::Corrupted(e)) if e == "hash (1; 1) does not match"));
+        sut.storage
+            .set_hash(1, 1, hash.expect("hash is missing"))
+            .expect("set_hash failed");
+
+        // Ensure a broken linked list is detected.
+        let node = sut.storage.get_node(&[2; 32]).expect("get_node failed");
+        let mut broken_node = node.clone().expect("node is missing");
+        broken_node.next_key = [1; 32];
+        sut.storage.set_node(broken_node).expect("set_node failed");
+        let res = sut.verify_integrity();
+        assert!(
+            matches!(res, Err(ImtError::Corrupted(e)) if e.ends_with("next key is not sorted"))
+        );
+
+        broken_node = node.clone().expect("node is missing");
+        broken_node.next_key = [6; 32];
+        sut.storage.set_node(broken_node).expect("set_node failed");
+        let res = sut.verify_integrity();
+        assert!(matches!(res, Err(ImtError::Corrupted(e)) if e.ends_with("next node is missing")));
+        sut.storage
+            .set_node(node.clone().expect("node is missing"))
+            .expect("set_node failed");
+        assert!(sut.verify_integrity().is_ok());
+
+        // Ensure a stale root is detected.
+        let root = sut.root().expect("root failed");
+        sut.storage.set_root([0xff; 32]).expect("set_root failed");
+        let res = sut.verify_integrity();
+        assert!(matches!(res, Err(ImtError::Corrupted(e)) if e == "root does not match"));
+        sut.storage.set_root(root).expect("set_root failed");
+
+        // Ensure a node left out of the linked list is detected.
+        let deleted_node = ImtNode {
+            index: 4,
+            key: [4; 32],
+            value: [42; 32],
+            next_key: [5; 32],
+        };
+        sut.storage.set_node(deleted_node).expect("set_node failed");
+        let res = sut.verify_integrity();
+        assert!(matches!(res, Err(ImtError::Corrupted(e)) if e.ends_with("is not reachable")));
+        sut.storage
+            .remove_node(&[4; 32])
+            .expect("remove_node failed");
+
+        // Ensure a node indexed under another key is detected.
+        let mut stale_node = node.expect("node is missing");
+        stale_node.key = [9; 32];
+        sut.storage.set_node(stale_node).expect("set_node failed");
+        sut.storage
+            .remove_node(&[9; 32])
+            .expect("remove_node failed");
+        let res = sut.verify_integrity();
+        assert!(
+            matches!(res, Err(ImtError::Corrupted(e)) if e.ends_with("is not indexed at its index"))
+        );
+    }
+
+    #[test]
+    fn test_at_root() {
+        let mut storage = BTreeImtStorage::default();
+
+        // Write a first version of the imt.
+        storage.set_version(0).expect("set_version failed");
+        let mut imt = Imt::writer(Keccak::v256, &mut storage).expect("imt writer failed");
+        imt.insert_node([1; 32], [42; 32])
+            .expect("insert [1] failed");
+        imt.insert_node([3; 32], [42; 32])
+            .expect("insert [3] failed");
+        let old_root = imt.root().expect("root failed");
+
+        // Write a second version of the imt.
+        storage.set_version(1).expect("set_version failed");
+        let mut imt = Imt::writer(Keccak::v256, &mut storage).expect("imt writer failed");
+        imt.update_node([1; 32], [43; 32])
+            .expect("update [1] failed");
+        imt.insert_node([2; 32], [42; 32])
+            .expect("insert [2] failed");
+        imt.delete_node([3; 32]).expect("delete [3] failed");
+        let new_root = imt.root().expect("root failed");
+
+        // Ensure proofs can be generated against the old root.
+        let imt = Imt::reader(Keccak::v256, &storage).expect("imt reader failed");
+        let sut = imt.at_root(&old_root).expect("old root not found");
+        assert_eq!(sut.root().expect("root failed"), old_root);
+        assert_eq!(sut.size().expect("size failed"), 3);
+
+        let keys = sut
+            .nodes_by_key(..)
+            .map(|node| node.map(|node| (node.key[0], node.value[0])))
+            .collect::<ImtResult<Vec<_>>>()
+            .expect("nodes_by_key failed");
+        assert_eq!(keys, [(0, 0), (1, 42), (3, 42)]);
+
+        let proof = sut
+            .inclusion_proof([1; 32])
+            .expect("inclusion proof failed");
+        assert_eq!(proof.node.value, [42; 32]);
+        assert!(proof.verify(Keccak::v256).is_ok());
+
+        let proof = sut
+            .inclusion_proof([3; 32])
+            .expect("inclusion proof failed");
+        assert!(proof.verify(Keccak::v256).is_ok());
+
+        let proof = sut
+            .exclusion_proof([2; 32])
+            .expect("exclusion proof failed");
+        assert_eq!(proof.ln_node.key, [1; 32]);
+        assert!(proof.verify(Keccak::v256).is_ok());
+
+        // Ensure proofs can be generated against the latest root.
+        let sut = imt.at_root(&new_root).expect("new root not found");
+        let proof = sut
+            .inclusion_proof([1; 32])
+            .expect("inclusion proof failed");
+        assert_eq!(proof.node.value, [43; 32]);
+        assert!(proof.verify(Keccak::v256).is_ok());
+
+        let proof = sut
+            .exclusion_proof([3; 32])
+            .expect("exclusion proof failed");
+        assert!(proof.verify(Keccak::v256).is_ok());
+
+        // Ensure unknown roots are rejected.
+        let res = imt.at_root(&[0xff; 32]);
+        assert!(matches!(res, Err(ImtError::RootNotFound(_))));
+    }
+
+    #[test]
+    fn test_rollback() {
+        let mut storage = BTreeImtStorage::default();
+
+        // Write a first version of the imt.
+        storage.set_version(0).expect("set_version failed");
+        let mut imt = Imt::writer(Keccak::v256, &mut storage).expect("imt writer failed");
+        imt.insert_node([1; 32], [42; 32])
+            .expect("insert [1] failed");
+        let old_storage = storage.clone();
+
+        // Write a second version of the imt.
+        storage.set_version(1).expect("set_version failed");
+        let mut imt = Imt::writer(Keccak::v256, &mut storage).expect("imt writer failed");
+        imt.insert_node([2; 32], [42; 32])
+            .expect("insert [2] failed");
+        let new_root = imt.root().expect("root failed");
+
+        // Ensure the rollback restores the first version and discards the second one.
+        storage.rollback(Some(0)).expect("rollback failed");
+        assert_eq!(storage, old_storage);
+
+        let imt = Imt::reader(Keccak::v256, &storage).expect("imt reader failed");
+        let res = imt.at_root(&new_root);
+        assert!(matches!(res, Err(ImtError::RootNotFound(_))));
+
+        storage.rollback(None).expect("rollback failed");
+        assert_eq!(storage, BTreeImtStorage::default());
     }
 }
@@ -1,17 +1,26 @@
-use std::{cmp::min, time::Duration};
+use std::{cmp::min, collections::VecDeque, time::Duration};
 
 use alloy::{
-    primitives::Address,
+    primitives::{Address, B256},
     providers::{Provider, ProviderBuilder, RootProvider},
     rpc::types::{Filter, Log},
     transports::http::{Client, Http},
 };
-use anyhow::Result;
+use anyhow::{anyhow, bail, Result};
 use keyspace_keystore_bindings::bindings::KeyStore::{BatchProved, ForcedTransactionSubmitted};
-use keyspace_state_manager::message::StateManagerMessage;
-use tokio::{sync::mpsc::Sender, time::sleep};
+use keyspace_state_manager::{checkpoint::LogPosition, message::StateManagerMessage};
+use tokio::{
+    sync::{mpsc::Sender, oneshot},
+    time::sleep,
+};
 use tracing::{debug, info, warn};
 
+/// The maximum number of processed blocks whose hash is tracked to detect L1 reorgs.
+///
+/// NOTE: Only the last block of each processed range is tracked, so the deepest detectable reorg
+///       spans this many ranges.
+const MAX_TRACKED_BLOCKS: usize = 128;
+
 /// The [Indexer] is monitoring the L1 KeyStore contract and forwarding [StateManagerMessage]s.
 #[derive(Debug)]
 pub struct Indexer {
@@ -26,6 +35,9 @@ pub struct Indexer {
 
 impl Indexer {
     /// Creates a new [Indexer].
+    ///
+    /// When resuming from a checkpoint, `start_block` MUST be the block of the last processed log so
+    /// that the logs that follow it in the same block are not missed.
     pub fn new(
         rpc_url: &str,
         start_block: u64,
@@ -46,9 +58,16 @@ impl Indexer {
     }
 
     /// Runs the [Indexer] to monitor the L1 KeyStore contract.
+    ///
+    /// The hash of the last block of each processed range is tracked to detect L1 reorgs. On a reorg,
+    /// the [Indexer] sends a [StateManagerMessage::Rollback] to the StateManager and resumes
+    /// from the last log it processed before the reorged blocks.
+    ///
+    /// NOTE: Reorgs happening while the node is stopped are not detected.
     pub async fn run(self) -> Result<()> {
         info!("Indexer started");
 
+        let mut tracked_blocks = VecDeque::with_capacity(MAX_TRACKED_BLOCKS);
         let mut from_block = self.start_block;
         loop {
             let latest_block = self.provider.get_block_number().await?;
@@ -60,17 +79,83 @@ impl Indexer {
                 continue;
             }
 
-            // Fetch and process events.
-            self.fetch_and_process_events(from_block, to_block).await?;
+            // NOTE: The range hash is fetched before its logs and the processed blocks are checked
+            //       after, so that a reorg happening meanwhile is detected now or on the next range.
+            let to_block_hash = self
+                .block_hash(to_block)
+                .await?
+                .ok_or_else(|| anyhow!("block {to_block} not found"))?;
+            let logs = self.fetch_events(from_block, to_block).await?;
+
+            if let Some(ancestor_block) = self.find_reorg_ancestor(&mut tracked_blocks).await? {
+                from_block = self.rollback(ancestor_block).await?;
+                tracked_blocks.retain(|(number, _)| *number < from_block);
+                continue;
+            }
+
+            // Process the events.
+            self.process_events(logs).await?;
+
+            if tracked_blocks.len() == MAX_TRACKED_BLOCKS {
+                tracked_blocks.pop_front();
+            }
+            tracked_blocks.push_back((to_block, to_block_hash));
 
             // Move to the next batch
             from_block = to_block + 1;
         }
     }
 
-    /// Fetches all events emitted by the L1 KeyStore contract in the range (`from_block`..=`to_block`)
-    /// and handles them appropriately.
-    async fn fetch_and_process_events(&self, from_block: u64, to_block: u64) -> Result<()> {
+    /// Returns the hash of the given canonical block, or `None` if the chain is not that long.
+    async fn block_hash(&self, number: u64) -> Result<Option<B256>> {
+        let block = self
+            .provider
+            .get_block_by_number(number.into(), false)
+            .await?;
+
+        Ok(block.map(|block| block.header.hash))
+    }
+
+    /// Checks that the last tracked block is still canonical. If not, untracks the reorged blocks and
+    /// returns the number of the last tracked block that is still canonical (the common ancestor).
+    async fn find_reorg_ancestor(
+        &self,
+        tracked_blocks: &mut VecDeque<(u64, B256)>,
+    ) -> Result<Option<u64>> {
+        let Some(&(number, hash)) = tracked_blocks.back() else {
+            return Ok(None);
+        };
+
+        if self.block_hash(number).await? == Some(hash) {
+            return Ok(None);
+        }
+
+        warn!(block = number, "L1 reorg detected");
+
+        while let Some((number, hash)) = tracked_blocks.pop_back() {
+            if self.block_hash(number).await? == Some(hash) {
+                tracked_blocks.push_back((number, hash));
+                return Ok(Some(number));
+            }
+        }
+
+        bail!("L1 reorg deeper than the {MAX_TRACKED_BLOCKS} tracked blocks")
+    }
+
+    /// Sends a [StateManagerMessage::Rollback] to the StateManager and returns the block to resume from.
+    async fn rollback(&self, ancestor_block: u64) -> Result<u64> {
+        info!(ancestor_block, "Rolling back the StateManager");
+
+        let (resume_sink, resume_stream) = oneshot::channel();
+        let msg = StateManagerMessage::Rollback(ancestor_block, resume_sink);
+        self.state_manager_sink.send(msg).await?;
+
+        let last_processed = resume_stream.await?;
+        Ok(last_processed.map_or(self.start_block, |position| position.block_number))
+    }
+
+    /// Fetches all events emitted by the L1 KeyStore contract in the range (`from_block`..=`to_block`).
+    async fn fetch_events(&self, from_block: u64, to_block: u64) -> Result<Vec<Log>> {
         // Create a filter for logs emitted by the Keystore contract in the block range.
         let filter = Filter::new()
             .address(self.keystore_address)
@@ -78,17 +163,21 @@ impl Indexer {
             .to_block(to_block);
 
         // Fetch all logs in the block range for the contract
-        let logs = self.provider.get_logs(&filter).await?;
+        Ok(self.provider.get_logs(&filter).await?)
+    }
 
+    /// Handles the given events emitted by the L1 KeyStore contract appropriately.
+    async fn process_events(&self, logs: Vec<Log>) -> Result<()> {
         // Process each log.
         for log in logs {
             debug!("New KeyStore contract event");
 
+            let position = log_position(&log)?;
             if let Ok(forced_tx_registered) = log.log_decode::<ForcedTransactionSubmitted>() {
-                self.handle_forced_tx_submitted(forced_tx_registered)
+                self.handle_forced_tx_submitted(forced_tx_registered, position)
                     .await?;
             } else if let Ok(batch_proved) = log.log_decode::<BatchProved>() {
-                self.handle_batch_proved(batch_proved).await?;
+                self.handle_batch_proved(batch_proved, position).await?;
             } else {
                 // Unknown event.
                 warn!("Unknown event: {:?}", log);
@@ -102,23 +191,40 @@ impl Indexer {
     async fn handle_forced_tx_submitted(
         &self,
         event: Log<ForcedTransactionSubmitted>,
+        position: LogPosition,
     ) -> Result<()> {
         debug!(
             even = "ForcedTransactionSubmitted",
             "Forwarding event to StateManager"
         );
 
-        let msg = StateManagerMessage::ForcedTransactionSubmitted(event.inner.data);
+        let msg = StateManagerMessage::ForcedTransactionSubmitted(event.inner.data, position);
         self.state_manager_sink.send(msg).await?;
         Ok(())
     }
 
     /// Wraps the [BatchProved] event in a [StateManagerMessage::BatchProved] and forwards it to the state manager.
-    async fn handle_batch_proved(&self, event: Log<BatchProved>) -> Result<()> {
+    async fn handle_batch_proved(
+        &self,
+        event: Log<BatchProved>,
+        position: LogPosition,
+    ) -> Result<()> {
         debug!(even = "BatchProved", "Forwarding event to StateManager");
 
-        let msg = StateManagerMessage::BatchProved(event.inner.data);
+        let msg = StateManagerMessage::BatchProved(event.inner.data, position);
         self.state_manager_sink.send(msg).await?;
         Ok(())
     }
 }
+
+/// Returns the [LogPosition] of the given mined `log`.
+fn log_position(log: &Log) -> Result<LogPosition> {
+    Ok(LogPosition {
+        block_number: log
+            .block_number
+            .ok_or_else(|| anyhow!("log without block number"))?,
+        log_index: log
+            .log_index
+            .ok_or_else(|| anyhow!("log without log index"))?,
+    })
+}
@@ -22,12 +22,14 @@ sol! {
         /// @param currentValue The KeySpace record current value.
         /// @param newValue The KeySpace record new value to set.
         /// @param zkVmVkHash The zkVM record program verifier key hash.
+        /// @param storageHash The KeySpace record storage hash, committed to by `currentValue`.
         /// @param proof The record program proof wrapped in a PLONK BN254.
         event ForcedTransactionSubmitted(
             bytes32 indexed keySpaceId,
             bytes32 indexed currentValue,
             bytes32 indexed newValue,
             bytes32 zkVmVkHash,
+            bytes32 storageHash,
             bytes proof
         );
 
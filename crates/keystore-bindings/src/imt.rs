@@ -0,0 +1,118 @@
+use alloy::{
+    primitives::{FixedBytes, U256},
+    sol,
+};
+use keyspace_imt::{
+    node,
+    proof::{exclusion::ExclusionProof, inclusion::InclusionProof},
+    Hash256,
+};
+
+sol! {
+    #[derive(Debug, Default, PartialEq, Eq)]
+    /// @notice A node of the KeySpace Indexed Merkle Tree (IMT).
+    struct ImtNode {
+        /// @dev The node index in the IMT.
+        uint64 index;
+        /// @dev The node key (the KeySpace id).
+        bytes32 key;
+        /// @dev The node value (the KeySpace record value).
+        bytes32 value;
+        /// @dev The key of the next node in key order (0 for the last node).
+        bytes32 nextKey;
+    }
+
+    #[derive(Debug, Default, PartialEq, Eq)]
+    /// @notice A proof that a node is in the IMT.
+    struct ImtInclusionProof {
+        /// @dev The IMT root (including the size).
+        bytes32 root;
+        /// @dev The IMT size (including the 0 node).
+        uint64 size;
+        /// @dev The node proven to be in the IMT.
+        ImtNode node;
+        /// @dev The node siblings from the leaves to the top of the IMT (absent siblings are set to 0).
+        bytes32[] siblings;
+        /// @dev The bitmap of the present `siblings` (bit `i` is set if `siblings[i]` is present).
+        uint256 siblingsBitmap;
+    }
+
+    #[derive(Debug, Default, PartialEq, Eq)]
+    /// @notice A proof that a key is not in the IMT.
+    struct ImtExclusionProof {
+        /// @dev The IMT root (including the size).
+        bytes32 root;
+        /// @dev The IMT size (including the 0 node).
+        uint64 size;
+        /// @dev The low nullifier node of `nodeKey`.
+        ImtNode lnNode;
+        /// @dev The low nullifier node siblings from the leaves to the top of the IMT (absent siblings are
+        /// set to 0).
+        bytes32[] lnSiblings;
+        /// @dev The bitmap of the present `lnSiblings` (bit `i` is set if `lnSiblings[i]` is present).
+        uint256 lnSiblingsBitmap;
+        /// @dev The key proven not to be in the IMT.
+        bytes32 nodeKey;
+    }
+}
+
+impl From<&node::ImtNode<[u8; 32], [u8; 32]>> for ImtNode {
+    fn from(node: &node::ImtNode<[u8; 32], [u8; 32]>) -> Self {
+        Self {
+            index: node.index,
+            key: node.key.into(),
+            value: node.value.into(),
+            nextKey: node.next_key.into(),
+        }
+    }
+}
+
+impl From<&InclusionProof<[u8; 32], [u8; 32]>> for ImtInclusionProof {
+    fn from(proof: &InclusionProof<[u8; 32], [u8; 32]>) -> Self {
+        let (siblings, siblings_bitmap) = abi_siblings(&proof.siblings);
+
+        Self {
+            root: proof.root.into(),
+            size: proof.size,
+            node: (&proof.node).into(),
+            siblings,
+            siblingsBitmap: siblings_bitmap,
+        }
+    }
+}
+
+impl From<&ExclusionProof<[u8; 32], [u8; 32]>> for ImtExclusionProof {
+    fn from(proof: &ExclusionProof<[u8; 32], [u8; 32]>) -> Self {
+        let (ln_siblings, ln_siblings_bitmap) = abi_siblings(&proof.ln_siblings);
+
+        Self {
+            root: proof.root.into(),
+            size: proof.size,
+            lnNode: (&proof.ln_node).into(),
+            lnSiblings: ln_siblings,
+            lnSiblingsBitmap: ln_siblings_bitmap,
+            nodeKey: proof.node_key.into(),
+        }
+    }
+}
+
+/// Converts the imt proof siblings into their ABI representation: the list of siblings (absent
+/// siblings are set to 0) and the bitmap of the present ones.
+///
+/// NOTE: An imt has at most 64 levels, so the bitmap always fits in a `uint256`.
+fn abi_siblings(siblings: &[Option<Hash256>]) -> (Vec<FixedBytes<32>>, U256) {
+    siblings.iter().enumerate().fold(
+        (Vec::with_capacity(siblings.len()), U256::ZERO),
+        |(mut abi_siblings, mut bitmap), (level, sibling)| {
+            match sibling {
+                Some(sibling) => {
+                    abi_siblings.push(sibling.into());
+                    bitmap.set_bit(level, true);
+                }
+                None => abi_siblings.push(FixedBytes::ZERO),
+            }
+
+            (abi_siblings, bitmap)
+        },
+    )
+}
@@ -1,14 +1,20 @@
-use alloy::primitives::address;
-use anyhow::{anyhow, Result};
+use alloy::primitives::{address, B256};
+use anyhow::{anyhow, Context, Result};
 use futures::TryFutureExt;
+use std::env;
 use tokio::sync::mpsc;
 use tracing_subscriber::EnvFilter;
 
 use keyspace_indexer::Indexer;
 use keyspace_sequencer::Sequencer;
-use keyspace_state_manager::{manager::StateManager, storage::btree::BTreeStorage};
+use keyspace_state_manager::{
+    forced_tx::SP1ForcedTxVerifier, manager::StateManager, storage::sled::SledStorage,
+};
 use keyspace_transaction_pool::TransactionPool;
 
+/// The path of the sled database persisting the rollup state, unless set with `KEYSPACE_DB_PATH`.
+const DEFAULT_DB_PATH: &str = "keyspace-db";
+
 #[tokio::main]
 async fn main() -> Result<()> {
     // Configure logging.
@@ -27,19 +33,32 @@ async fn main() -> Result<()> {
     let (rpc_to_tx_pool_sink, rpc_to_tx_pool_stream) = mpsc::channel(1000);
     let (sequencer_to_batcher_sink, sequencer_to_batcher_stream) = mpsc::channel(1000);
 
-    // Instanciate the indexer.
+    // Instanciate the StateManager.
+    let db_path = env::var("KEYSPACE_DB_PATH").unwrap_or_else(|_| DEFAULT_DB_PATH.to_string());
+    let storage = SledStorage::<Vec<u8>, Vec<u8>>::open(&db_path)?;
+
+    // NOTE: The forced vk hash is specific to the deployment, so it MUST be provided.
+    let forced_vk_hash: B256 = env::var("KEYSPACE_FORCED_VK_HASH")
+        .context("KEYSPACE_FORCED_VK_HASH must be set")?
+        .parse()
+        .context("KEYSPACE_FORCED_VK_HASH must be a 32 bytes hex string")?;
+    let forced_tx_verifier = SP1ForcedTxVerifier::new(forced_vk_hash.0);
+
+    let state_manager =
+        StateManager::new(storage, indexer_to_state_manager_stream, forced_tx_verifier)?;
+
+    // Instanciate the indexer, resuming from the StateManager checkpoint.
+    let start_block = state_manager
+        .last_processed()
+        .map_or(0, |position| position.block_number);
     let indexer = Indexer::new(
         "http://127.0.0.1:8545",
-        0,
+        start_block,
         10,
         address!("5FbDB2315678afecb367f032d93F642f64180aa3"),
         indexer_to_state_manager_sink,
     )?;
 
-    // Instanciate the StateManager.
-    let storage = BTreeStorage::default();
-    let state_manager = StateManager::new(storage, indexer_to_state_manager_stream);
-
     // Instanciate the Sequencer.
     let sequencer = Sequencer::new(sequencer_to_tx_pool_sink, sequencer_to_batcher_sink);
 
@@ -0,0 +1,136 @@
+//! Benchmarks of the imt operations over the state manager storage backends.
+//!
+//! The benchmarked tree sizes can be overridden with a comma separated list of sizes:
+//! `IMT_BENCH_SIZES=1000,1000000 cargo bench -p keyspace-state-manager`.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use std::env;
+use tiny_keccak::{Hasher, Keccak};
+
+use keyspace_imt::{storage::versioned::VersionedImtStorageWriter, tree::Imt, Hash256};
+use keyspace_state_manager::storage::{
+    btree::BTreeStorage, imt::ImtStorage, sled::SledStorage, StorageWriter, Transaction,
+    TransactionalStorage,
+};
+
+const DEFAULT_SIZES: [u64; 3] = [1_000, 100_000, 1_000_000];
+
+/// The number of mutations applied per batch, half of them being insertions.
+const BATCH_SIZE: u64 = 100;
+
+/// Returns the tree sizes to benchmark.
+fn sizes() -> Vec<u64> {
+    env::var("IMT_BENCH_SIZES").map_or(DEFAULT_SIZES.to_vec(), |sizes| {
+        sizes
+            .split(',')
+            .map(|size| size.trim().parse().expect("invalid IMT_BENCH_SIZES"))
+            .collect()
+    })
+}
+
+/// Returns the i-th benchmark key, uniformly distributed over the key space.
+fn key(i: u64) -> Hash256 {
+    let mut hasher = Keccak::v256();
+    hasher.update(&i.to_be_bytes());
+
+    let mut key = [0; 32];
+    hasher.finalize(&mut key);
+    key
+}
+
+/// Benchmarks the imt operations over the given `storage`, filled with `size` random leaves.
+fn bench_storage<S>(c: &mut Criterion, backend: &str, size: u64, mut storage: S)
+where
+    S: StorageWriter<StorageKey = Vec<u8>, StorageValue = Vec<u8>> + TransactionalStorage,
+    for<'a> S::T<'a>: StorageWriter<StorageKey = Vec<u8>, StorageValue = Vec<u8>>,
+{
+    let leaves = (1..=size).map(|i| (key(i), key(i)));
+    Imt::from_leaves(Keccak::v256, ImtStorage::new(&mut storage), leaves)
+        .expect("from_leaves failed");
+
+    let mut group = c.benchmark_group(backend);
+    let mut next_key = size;
+    let mut i = 0;
+
+    {
+        let mut imt =
+            Imt::writer(Keccak::v256, ImtStorage::new(&mut storage)).expect("imt writer failed");
+
+        // NOTE: Inserting grows the tree, which is negligible compared to its size.
+        group.bench_function(BenchmarkId::new("insert_node", size), |b| {
+            b.iter(|| {
+                next_key += 1;
+                imt.insert_node(key(next_key), [42; 32])
+                    .expect("insert failed")
+            })
+        });
+
+        group.bench_function(BenchmarkId::new("update_node", size), |b| {
+            b.iter(|| {
+                i = i % size + 1;
+                imt.update_node(key(i), [42; 32]).expect("update failed")
+            })
+        });
+
+        group.bench_function(BenchmarkId::new("inclusion_proof", size), |b| {
+            b.iter(|| {
+                i = i % size + 1;
+                imt.inclusion_proof(key(i)).expect("inclusion proof failed")
+            })
+        });
+
+        group.bench_function(BenchmarkId::new("exclusion_proof", size), |b| {
+            b.iter(|| {
+                i = i % size + 1;
+                imt.exclusion_proof(key(u64::MAX - i))
+                    .expect("exclusion proof failed")
+            })
+        });
+    }
+
+    // Apply the mutations the same way the state manager applies a proved batch.
+    let mut version = 0;
+    group.bench_function(BenchmarkId::new("apply_batch", size), |b| {
+        b.iter(|| {
+            let mut tx = storage.transaction();
+            let mut imt_tx = ImtStorage::new(&mut tx);
+            imt_tx.set_version(version).expect("set_version failed");
+            version += 1;
+
+            let mutations = (0..BATCH_SIZE).map(|j| {
+                if j % 2 == 0 {
+                    next_key += 1;
+                    (key(next_key), [42; 32])
+                } else {
+                    i = i % size + 1;
+                    (key(i), [43; 32])
+                }
+            });
+
+            let mut imt = Imt::writer(Keccak::v256, imt_tx).expect("imt writer failed");
+            imt.set_nodes(mutations).expect("set_nodes failed");
+            tx.commit().expect("commit failed");
+        })
+    });
+
+    group.finish();
+}
+
+fn bench_backends(c: &mut Criterion) {
+    for size in sizes() {
+        bench_storage(c, "btree", size, BTreeStorage::<Vec<u8>, Vec<u8>>::new());
+
+        let db = sled::Config::new()
+            .temporary(true)
+            .open()
+            .expect("sled open failed");
+        bench_storage(c, "sled", size, SledStorage::<Vec<u8>, Vec<u8>>::new(db));
+    }
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(20);
+    targets = bench_backends
+}
+criterion_main!(benches);
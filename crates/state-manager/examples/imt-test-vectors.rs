@@ -0,0 +1,115 @@
+//! Generates the ABI encoded imt proofs test vectors used by the `ImtVerifier` forge tests.
+//!
+//! Usage: `cargo run --example imt-test-vectors -- <OUTPUT_PATH>`
+//! (e.g. `contracts/test/vectors/imt.json`).
+
+use alloy::{hex, sol_types::SolValue};
+use anyhow::{anyhow, Context, Result};
+use std::{env, fs};
+use tiny_keccak::Keccak;
+
+use keyspace_imt::{proof::node::NodeProof, storage::ImtStorageReader, tree::Imt, Hash256};
+use keyspace_keystore_bindings::imt::{ImtExclusionProof, ImtInclusionProof};
+use keyspace_state_manager::storage::{btree::BTreeStorage, imt::ImtStorage};
+
+/// The keys inserted in the imt, one after the other.
+const INSERTED_KEYS: [u8; 6] = [0x10, 0x40, 0x20, 0x60, 0x50, 0x30];
+
+/// The key deleted from the imt once all the [INSERTED_KEYS] are inserted.
+const DELETED_KEY: u8 = 0x40;
+
+/// The keys that are never inserted in the imt.
+const MISSING_KEYS: [u8; 3] = [0x08, 0x38, 0xff];
+
+#[derive(Default)]
+struct Vectors {
+    inclusion_roots: Vec<Hash256>,
+    inclusion_proofs: Vec<Vec<u8>>,
+    exclusion_roots: Vec<Hash256>,
+    exclusion_proofs: Vec<Vec<u8>>,
+}
+
+impl Vectors {
+    /// Adds the proof of `key` against the current `imt` root.
+    fn push<S>(&mut self, imt: &Imt<Keccak, [u8; 32], [u8; 32], S>, key: Hash256) -> Result<()>
+    where
+        S: ImtStorageReader<NodeK = [u8; 32], NodeV = [u8; 32]>,
+    {
+        let root = imt.root()?;
+
+        match imt.node_proof(key)? {
+            NodeProof::Inclusion(proof) => {
+                self.inclusion_roots.push(root);
+                self.inclusion_proofs
+                    .push(ImtInclusionProof::from(&proof).abi_encode());
+            }
+            NodeProof::Exclusion(proof) => {
+                self.exclusion_roots.push(root);
+                self.exclusion_proofs
+                    .push(ImtExclusionProof::from(&proof).abi_encode());
+            }
+        }
+
+        Ok(())
+    }
+
+    fn to_json(&self) -> String {
+        format!(
+            "{{\n  \"inclusionRoots\": {},\n  \"inclusionProofs\": {},\n  \"exclusionRoots\": {},\n  \"exclusionProofs\": {}\n}}\n",
+            json_hex_array(&self.inclusion_roots),
+            json_hex_array(&self.inclusion_proofs),
+            json_hex_array(&self.exclusion_roots),
+            json_hex_array(&self.exclusion_proofs),
+        )
+    }
+}
+
+fn main() -> Result<()> {
+    let path = env::args()
+        .nth(1)
+        .ok_or_else(|| anyhow!("usage: cargo run --example imt-test-vectors -- <OUTPUT_PATH>"))?;
+
+    let mut storage = BTreeStorage::default();
+    let mut imt = Imt::writer(Keccak::v256, ImtStorage::new(&mut storage))?;
+    let mut vectors = Vectors::default();
+
+    // Prove the 0 node and the missing keys in the empty imt.
+    vectors.push(&imt, [0; 32])?;
+    for key in MISSING_KEYS {
+        vectors.push(&imt, [key; 32])?;
+    }
+
+    // Prove all the keys after each insertion to cover the different imt sizes.
+    for (i, key) in INSERTED_KEYS.into_iter().enumerate() {
+        imt.insert_node([key; 32], [key + 1; 32])?;
+
+        vectors.push(&imt, [0; 32])?;
+        for key in INSERTED_KEYS[..=i].iter().chain(&MISSING_KEYS) {
+            vectors.push(&imt, [*key; 32])?;
+        }
+    }
+
+    // Prove the deleted key is no longer in the imt.
+    imt.delete_node([DELETED_KEY; 32])?;
+    vectors.push(&imt, [DELETED_KEY; 32])?;
+
+    fs::write(&path, vectors.to_json()).with_context(|| format!("failed to write {path}"))?;
+
+    println!(
+        "wrote {} inclusion and {} exclusion proofs to {path}",
+        vectors.inclusion_proofs.len(),
+        vectors.exclusion_proofs.len(),
+    );
+
+    Ok(())
+}
+
+/// Formats the given `values` as a JSON array of 0x prefixed hex strings.
+fn json_hex_array<T: AsRef<[u8]>>(values: &[T]) -> String {
+    let values = values
+        .iter()
+        .map(|value| format!("    \"{}\"", hex::encode_prefixed(value)))
+        .collect::<Vec<_>>();
+
+    format!("[\n{}\n  ]", values.join(",\n"))
+}
@@ -0,0 +1,34 @@
+//! Verifies the integrity of the imt persisted in a [BTreeStorage] snapshot.
+//!
+//! Usage: `imt-integrity <SNAPSHOT_PATH>` where the snapshot is a bincode encoded [BTreeStorage].
+
+use anyhow::{anyhow, Context, Result};
+use std::{env, fs};
+use tiny_keccak::Keccak;
+
+use keyspace_imt::tree::Imt;
+use keyspace_state_manager::storage::{btree::BTreeStorage, imt::ImtStorage};
+
+fn main() -> Result<()> {
+    let path = env::args()
+        .nth(1)
+        .ok_or_else(|| anyhow!("usage: imt-integrity <SNAPSHOT_PATH>"))?;
+
+    let snapshot = fs::read(&path).with_context(|| format!("failed to read {path}"))?;
+    let storage: BTreeStorage<Vec<u8>, Vec<u8>> =
+        bincode::deserialize(&snapshot).context("failed to deserialize the storage snapshot")?;
+
+    let imt = Imt::reader(
+        Keccak::v256,
+        ImtStorage::<_, [u8; 32], [u8; 32]>::new(&storage),
+    )?;
+    imt.verify_integrity()?;
+
+    println!(
+        "imt is valid (size: {}, root: {:?})",
+        imt.size()?,
+        imt.root()?
+    );
+
+    Ok(())
+}
@@ -0,0 +1,256 @@
+use alloy::{primitives::FixedBytes, sol_types::SolEvent};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+use crate::storage::{StorageError, StorageReader, StorageResult, StorageWriter};
+use keyspace_keystore_bindings::bindings::KeyStore::ForcedTransactionSubmitted;
+
+/// The storage key of the [Checkpoint].
+///
+/// NOTE: It MUST not collide with the imt storage keys prefixes (see `storage/imt/keys.rs`).
+const CHECKPOINT_STORAGE_KEY: [u8; 1] = [0xff];
+
+/// The storage key prefix of the [Checkpoint]s persisted at each imt version.
+const VERSIONED_CHECKPOINT_STORAGE_PREFIX: u8 = 0xfe;
+
+/// The position of a log emitted by the L1 KeyStore contract.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct LogPosition {
+    /// The L1 block number the log was emitted in.
+    pub block_number: u64,
+    /// The index of the log in its L1 block.
+    pub log_index: u64,
+}
+
+/// The progress of the [crate::manager::StateManager] over the L1 KeyStore contract logs.
+///
+/// It is persisted in the same storage transaction as the imt updates, so that the node can resume
+/// from it after a restart without applying a batch twice nor forgetting the queued forced txs.
+#[derive(Debug, Default, Clone)]
+pub struct Checkpoint {
+    /// The position of the last processed log, if any.
+    pub last_processed: Option<LogPosition>,
+
+    /// The pending list of forced transactions waiting to be proved.
+    /// NOTE: Those transactions are not managed via some mempool mechanism as
+    ///       they are always sent to the L1 KeyStore contract directly and are
+    ///       only temporarly needed by the [crate::manager::StateManager] to rebuild the imt.
+    pub pending_forced_transactions: VecDeque<ForcedTransactionSubmitted>,
+}
+
+/// The persisted representation of a [Checkpoint].
+#[derive(Serialize, Deserialize)]
+struct StoredCheckpoint {
+    last_processed: Option<LogPosition>,
+    /// The ABI encoded (topics; data) of the pending forced transactions logs.
+    pending_forced_transactions: Vec<(Vec<[u8; 32]>, Vec<u8>)>,
+}
+
+impl Checkpoint {
+    /// Loads the persisted [Checkpoint] from the given `storage`, or the default one if none was
+    /// persisted yet.
+    pub fn load<S>(storage: &S) -> StorageResult<Self>
+    where
+        S: StorageReader<StorageKey = Vec<u8>, StorageValue = Vec<u8>>,
+    {
+        storage
+            .get(&CHECKPOINT_STORAGE_KEY.to_vec())?
+            .map_or(Ok(Self::default()), |bytes| Self::decode(&bytes))
+    }
+
+    /// Loads the [Checkpoint] persisted along the given imt `version` from the given `storage`.
+    pub fn load_at<S>(storage: &S, version: u64) -> StorageResult<Option<Self>>
+    where
+        S: StorageReader<StorageKey = Vec<u8>, StorageValue = Vec<u8>>,
+    {
+        storage
+            .get(&versioned_checkpoint_storage_key(version))?
+            .map(|bytes| Self::decode(&bytes))
+            .transpose()
+    }
+
+    /// Persists the [Checkpoint] in the given `storage`, along the given imt `version`.
+    ///
+    /// The [Checkpoint]s of the past versions are kept so that they can be restored on rollback.
+    pub fn store<S>(&self, storage: &mut S, version: u64) -> StorageResult<()>
+    where
+        S: StorageWriter<StorageKey = Vec<u8>, StorageValue = Vec<u8>>,
+    {
+        let bytes = self.encode()?;
+        storage.set(versioned_checkpoint_storage_key(version), bytes.clone())?;
+        storage.set(CHECKPOINT_STORAGE_KEY.to_vec(), bytes)
+    }
+
+    /// Reverts the persisted [Checkpoint] to the one of the given imt `version` (or to the default one
+    /// when `None`), discarding the ones of the later versions up to `current_version`.
+    pub fn rollback<S>(
+        storage: &mut S,
+        version: Option<u64>,
+        current_version: u64,
+    ) -> StorageResult<Self>
+    where
+        S: StorageWriter<StorageKey = Vec<u8>, StorageValue = Vec<u8>>,
+    {
+        let first_discarded = version.map_or(0, |version| version + 1);
+        for discarded in first_discarded..=current_version {
+            storage.remove(&versioned_checkpoint_storage_key(discarded))?;
+        }
+
+        let Some(version) = version else {
+            storage.remove(&CHECKPOINT_STORAGE_KEY.to_vec())?;
+            return Ok(Self::default());
+        };
+
+        let checkpoint = Self::load_at(storage, version)?.ok_or_else(|| {
+            StorageError::Deserialization("checkpoint", format!("missing at version {version}"))
+        })?;
+        storage.set(CHECKPOINT_STORAGE_KEY.to_vec(), checkpoint.encode()?)?;
+
+        Ok(checkpoint)
+    }
+
+    /// Removes the [Checkpoint]s persisted along the imt versions before the given `version`, which
+    /// can no longer be rolled back to.
+    pub fn prune<S>(storage: &mut S, version: u64) -> StorageResult<()>
+    where
+        S: StorageWriter<StorageKey = Vec<u8>, StorageValue = Vec<u8>>,
+    {
+        let mut cursor = versioned_checkpoint_storage_key(version);
+        while let Some((k, _)) = storage.get_lt(&cursor)? {
+            if k.first() != Some(&VERSIONED_CHECKPOINT_STORAGE_PREFIX) {
+                break;
+            }
+
+            storage.remove(&k)?;
+            cursor = k;
+        }
+
+        Ok(())
+    }
+
+    /// Serializes the [Checkpoint].
+    fn encode(&self) -> StorageResult<Vec<u8>> {
+        let pending_forced_transactions = self
+            .pending_forced_transactions
+            .iter()
+            .map(|forced_tx| {
+                let log = forced_tx.encode_log_data();
+                let topics = log.topics().iter().map(|topic| topic.0).collect();
+                (topics, log.data.to_vec())
+            })
+            .collect();
+
+        let stored = StoredCheckpoint {
+            last_processed: self.last_processed,
+            pending_forced_transactions,
+        };
+
+        bincode::serialize(&stored)
+            .map_err(|e| StorageError::Serialization("checkpoint", e.to_string()))
+    }
+
+    /// Deserializes a [Checkpoint] from the given `bytes`.
+    fn decode(bytes: &[u8]) -> StorageResult<Self> {
+        let stored: StoredCheckpoint = bincode::deserialize(bytes)
+            .map_err(|e| StorageError::Deserialization("checkpoint", e.to_string()))?;
+
+        let pending_forced_transactions = stored
+            .pending_forced_transactions
+            .into_iter()
+            .map(|(topics, data)| {
+                let topics = topics.into_iter().map(FixedBytes::from);
+                ForcedTransactionSubmitted::decode_raw_log(topics, &data, true).map_err(|e| {
+                    StorageError::Deserialization("checkpoint forced tx", e.to_string())
+                })
+            })
+            .collect::<StorageResult<_>>()?;
+
+        Ok(Self {
+            last_processed: stored.last_processed,
+            pending_forced_transactions,
+        })
+    }
+}
+
+/// Returns the storage key of the [Checkpoint] persisted along the given imt `version`.
+fn versioned_checkpoint_storage_key(version: u64) -> Vec<u8> {
+    let mut v = vec![VERSIONED_CHECKPOINT_STORAGE_PREFIX];
+    v.extend_from_slice(&version.to_be_bytes());
+
+    v
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{Bytes, B256};
+
+    use crate::storage::btree::BTreeStorage;
+
+    use super::*;
+
+    #[test]
+    fn test_checkpoint_roundtrip() {
+        let mut storage = BTreeStorage::new();
+        let sut = Checkpoint::load(&storage).expect("load failed");
+        assert_eq!(sut.last_processed, None);
+
+        let forced_tx = ForcedTransactionSubmitted {
+            keySpaceId: B256::repeat_byte(1),
+            currentValue: B256::repeat_byte(2),
+            newValue: B256::repeat_byte(3),
+            zkVmVkHash: B256::repeat_byte(4),
+            storageHash: B256::repeat_byte(5),
+            proof: Bytes::from_static(b"proof"),
+        };
+
+        let checkpoint = Checkpoint {
+            last_processed: Some(LogPosition {
+                block_number: 42,
+                log_index: 3,
+            }),
+            pending_forced_transactions: VecDeque::from([forced_tx]),
+        };
+        checkpoint.store(&mut storage, 0).expect("store failed");
+
+        let sut = Checkpoint::load(&storage).expect("load failed");
+
+        assert_eq!(sut.last_processed, checkpoint.last_processed);
+        assert_eq!(sut.pending_forced_transactions.len(), 1);
+        assert_eq!(
+            sut.pending_forced_transactions[0].encode_log_data(),
+            checkpoint.pending_forced_transactions[0].encode_log_data()
+        );
+
+        // Persist a newer checkpoint and rollback to the first one.
+        let newer = Checkpoint {
+            last_processed: Some(LogPosition {
+                block_number: 43,
+                log_index: 0,
+            }),
+            ..Default::default()
+        };
+        newer.store(&mut storage, 1).expect("store failed");
+
+        let sut = Checkpoint::rollback(&mut storage, Some(0), 1).expect("rollback failed");
+        assert_eq!(sut.last_processed, checkpoint.last_processed);
+        assert_eq!(
+            Checkpoint::load(&storage)
+                .expect("load failed")
+                .last_processed,
+            checkpoint.last_processed
+        );
+        assert!(Checkpoint::load_at(&storage, 1)
+            .expect("load_at failed")
+            .is_none());
+
+        // Ensure pruning only removes the checkpoints of the previous versions.
+        newer.store(&mut storage, 1).expect("store failed");
+        Checkpoint::prune(&mut storage, 1).expect("prune failed");
+        assert!(Checkpoint::load_at(&storage, 0)
+            .expect("load_at failed")
+            .is_none());
+        assert!(Checkpoint::load_at(&storage, 1)
+            .expect("load_at failed")
+            .is_some());
+    }
+}
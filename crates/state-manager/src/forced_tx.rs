@@ -0,0 +1,113 @@
+use anyhow::{anyhow, Result};
+use std::{fmt::Debug, panic};
+
+use keyspace_imt::Hash256;
+use keyspace_keystore_bindings::bindings::KeyStore::ForcedTransactionSubmitted;
+use keyspace_programs_lib::batcher::proof::sp1_forced::SP1ForcedProof;
+
+/// Verifies the proofs of the forced transactions before the [crate::manager::StateManager] applies
+/// them, so that it skips the same invalid forced transactions as the Batcher program.
+pub trait ForcedTxVerifier: Debug {
+    /// Verifies the proof of the given forced transaction.
+    fn verify(&self, forced_tx: &ForcedTransactionSubmitted) -> Result<()>;
+}
+
+/// The [ForcedTxVerifier] running the same [SP1ForcedProof] verification as the Batcher program.
+#[derive(Debug, Clone)]
+pub struct SP1ForcedTxVerifier {
+    /// The SP1 verifier key hash of the forced inclusion path, that the authorization keys of the
+    /// records commit to along their record program verifier key hash (`zkVmVkHash`).
+    forced_vk_hash: Hash256,
+}
+
+impl SP1ForcedTxVerifier {
+    /// Creates a new [SP1ForcedTxVerifier] for the given deployment `forced_vk_hash`.
+    pub fn new(forced_vk_hash: Hash256) -> Self {
+        Self { forced_vk_hash }
+    }
+
+    /// Returns the [SP1ForcedProof] of the given forced transaction.
+    ///
+    /// The `proof` of a [ForcedTransactionSubmitted] is the raw SP1 record proof wrapped in a PLONK,
+    /// generated for the record program whose verifier key hash is `zkVmVkHash`. The `storageHash`
+    /// of the record is emitted along, so that `currentValue` can be checked to authorize it.
+    pub fn forced_proof(&self, forced_tx: &ForcedTransactionSubmitted) -> SP1ForcedProof {
+        SP1ForcedProof::new(
+            forced_tx.proof.to_vec(),
+            forced_tx.zkVmVkHash.0,
+            self.forced_vk_hash,
+            forced_tx.storageHash.0,
+        )
+    }
+}
+
+impl ForcedTxVerifier for SP1ForcedTxVerifier {
+    fn verify(&self, forced_tx: &ForcedTransactionSubmitted) -> Result<()> {
+        let proof = self.forced_proof(forced_tx);
+
+        // NOTE: The PLONK verifier panics on malformed proofs (instead of failing), which anyone can
+        //       submit to the L1 KeyStore contract and thus MUST NOT crash the node.
+        panic::catch_unwind(|| {
+            proof.verify_record_update(
+                forced_tx.keySpaceId.0,
+                forced_tx.currentValue.0,
+                forced_tx.newValue.0,
+            )
+        })
+        .map_err(|_| anyhow!("malformed wrapped proof"))?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{Bytes, B256};
+    use keyspace_programs_lib::{authorization_key, keyspace_value};
+
+    use super::*;
+
+    #[test]
+    fn test_forced_proof() {
+        // Instanciate the verifier.
+        let sut = SP1ForcedTxVerifier::new([3; 32]);
+
+        let forced_tx = ForcedTransactionSubmitted {
+            zkVmVkHash: B256::repeat_byte(2),
+            storageHash: B256::repeat_byte(4),
+            proof: Bytes::from_static(b"wrapped proof"),
+            ..Default::default()
+        };
+
+        // Ensure the proof is built from the event fields.
+        assert_eq!(
+            sut.forced_proof(&forced_tx),
+            SP1ForcedProof::new(b"wrapped proof".to_vec(), [2; 32], [3; 32], [4; 32])
+        );
+    }
+
+    #[test]
+    fn test_verify() {
+        // Instanciate the verifier.
+        let sut = SP1ForcedTxVerifier::new([3; 32]);
+
+        let authorization_key = authorization_key(&[2; 32], Some(&[3; 32]));
+        let forced_tx = |storage_hash| ForcedTransactionSubmitted {
+            keySpaceId: B256::repeat_byte(1),
+            currentValue: keyspace_value(&authorization_key, &[4; 32]).into(),
+            newValue: B256::repeat_byte(5),
+            zkVmVkHash: B256::repeat_byte(2),
+            storageHash: B256::repeat_byte(storage_hash),
+            proof: Bytes::from_static(b"wrapped proof"),
+        };
+
+        // Ensure the emitted storage hash authorizes the record, so that only the wrapped proof fails.
+        let err = sut.verify(&forced_tx(4)).expect_err("verify succeeded");
+        assert_eq!(err.to_string(), "malformed wrapped proof");
+
+        // Ensure a storage hash not committed to by `currentValue` is rejected.
+        let err = sut.verify(&forced_tx(6)).expect_err("verify succeeded");
+        assert_eq!(
+            err.to_string(),
+            "authorization_key does not match with current_value"
+        );
+    }
+}
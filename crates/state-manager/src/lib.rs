@@ -1,3 +1,5 @@
+pub mod checkpoint;
+pub mod forced_tx;
 pub mod manager;
 pub mod message;
 pub mod storage;
@@ -1,59 +1,134 @@
-use anyhow::Result;
-use std::{collections::VecDeque, fmt::Debug};
+use alloy::primitives::B256;
+use anyhow::{anyhow, Result};
+use std::fmt::Debug;
+use thiserror::Error;
 use tiny_keccak::Keccak;
-use tokio::sync::mpsc::Receiver;
-use tracing::{debug, info};
+use tokio::sync::{mpsc::Receiver, watch};
+use tracing::{debug, error, info, warn};
 
 use crate::{
+    checkpoint::{Checkpoint, LogPosition},
+    forced_tx::{ForcedTxVerifier, SP1ForcedTxVerifier},
     message::StateManagerMessage,
-    storage::{Transaction, TransactionalStorage},
+    storage::{
+        imt::ImtStorage, SnapshotStorage, StorageReader, StorageWriter, Transaction,
+        TransactionalStorage,
+    },
+};
+use keyspace_imt::{
+    storage::versioned::{VersionedImtStorageReader, VersionedImtStorageWriter},
+    tree::Imt,
+    Hash256,
 };
-use keyspace_imt::{storage::ImtStorageWriter, tree::Imt};
 use keyspace_keystore_bindings::bindings::KeyStore::{BatchProved, ForcedTransactionSubmitted};
 
+/// The number of past imt versions (i.e. batches) whose history is retained, so that they can be
+/// rolled back to on a L1 reorg and still serve proofs against their roots.
+///
+/// NOTE: It MUST cover the L1 reorg window, the older versions being pruned as new batches are applied.
+const RETAINED_IMT_VERSIONS: u64 = 1024;
+
+/// The fatal errors of the [StateManager].
+#[derive(Error, Debug)]
+pub enum StateManagerError {
+    #[error("imt root diverged from L1 at {0:?}:\n{1}")]
+    RootDivergence(LogPosition, String),
+
+    #[error("batch at {0:?} includes {1} forced txs while only {2} are pending")]
+    InconsistentForcedTxs(LogPosition, usize, usize),
+}
+
 /// The state manager responsible for persiting the roolup state.
 #[derive(Debug)]
-pub struct StateManager<Storage> {
+pub struct StateManager<Storage: SnapshotStorage, Verifier = SP1ForcedTxVerifier> {
     /// The underlying storage layer that stores the rollup state.
     storage: Storage,
 
+    /// The snapshot of the last commited rollup state, shared with the readers (e.g. RPC handlers)
+    /// so that they can generate proofs while a batch is being applied.
+    snapshot: watch::Sender<Storage::Snapshot>,
+
     /// The stream of [StateManagerMessage], feeded by the indexer, to process.
     indexer_stream: Receiver<StateManagerMessage>,
 
-    /// The pending list of forced transactions waiting to be proved.
-    /// NOTE: Those transactions are not managed via some mempool mechanism as
-    ///       they are always sent to the L1 KeyStore contract directly and are
-    ///       only temporarly needed by the [StateManager] to rebuild the imt.
-    pending_forced_transactions: VecDeque<ForcedTransactionSubmitted>,
+    /// The progress over the L1 KeyStore contract logs, persisted along each imt update.
+    checkpoint: Checkpoint,
+
+    /// The verifier of the forced transactions proofs.
+    forced_tx_verifier: Verifier,
 }
 
-impl<S> StateManager<S> {
-    /// Creates a new [StateManager].
-    pub fn new(storage: S, indexer_stream: Receiver<StateManagerMessage>) -> Self {
-        Self {
+impl<S: SnapshotStorage, V> StateManager<S, V> {
+    /// Creates a new [StateManager], resuming from the [Checkpoint] persisted in the `storage` (if any).
+    pub fn new(
+        storage: S,
+        indexer_stream: Receiver<StateManagerMessage>,
+        forced_tx_verifier: V,
+    ) -> Result<Self>
+    where
+        S: StorageReader<StorageKey = Vec<u8>, StorageValue = Vec<u8>>,
+    {
+        let checkpoint = Checkpoint::load(&storage)?;
+        let (snapshot, _) = watch::channel(storage.snapshot());
+
+        Ok(Self {
             storage,
+            snapshot,
             indexer_stream,
-            pending_forced_transactions: VecDeque::new(),
-        }
+            checkpoint,
+            forced_tx_verifier,
+        })
+    }
+
+    /// Returns the position of the last processed log, from which the indexer should resume.
+    pub fn last_processed(&self) -> Option<LogPosition> {
+        self.checkpoint.last_processed
+    }
+
+    /// Returns a [watch::Receiver] that always holds the snapshot of the last commited rollup state.
+    ///
+    /// An imt reader built over the received snapshot (e.g. with `ImtStorage::new(&snapshot)`) serves
+    /// proofs against the last commited root, concurrently with the batch being applied.
+    pub fn snapshots(&self) -> watch::Receiver<S::Snapshot> {
+        self.snapshot.subscribe()
     }
 }
 
-impl<S> StateManager<S>
+impl<S, V> StateManager<S, V>
 where
-    S: TransactionalStorage,
-    for<'a> S::T<'a>: ImtStorageWriter<NodeK = [u8; 32], NodeV = [u8; 32]>,
+    S: TransactionalStorage + SnapshotStorage,
+    V: ForcedTxVerifier,
+    for<'a> S::T<'a>: StorageWriter<StorageKey = Vec<u8>, StorageValue = Vec<u8>>,
 {
     /// Runs the [StateManager] to listen for [StateManagerMessage] from the indexer and rebuild the imt state.
     pub async fn run(mut self) -> Result<()> {
         info!("StateManager started");
 
         while let Some(msg) = self.indexer_stream.recv().await {
+            // Skip the events already processed before a restart, as the indexer resumes from the
+            // beginning of the last processed block.
+            let position = msg.position();
+            if position.is_some_and(|position| {
+                self.checkpoint
+                    .last_processed
+                    .is_some_and(|last_processed| position <= last_processed)
+            }) {
+                debug!(?position, "Skipping already processed event");
+                continue;
+            }
+
             match msg {
-                StateManagerMessage::BatchProved(batch_proved) => {
-                    self.handle_batch_proved(batch_proved).await?;
+                StateManagerMessage::BatchProved(batch_proved, position) => {
+                    self.handle_batch_proved(batch_proved, position).await?;
                 }
-                StateManagerMessage::ForcedTransactionSubmitted(forced_tx_submitted) => {
-                    self.handle_forced_tx_submitted(forced_tx_submitted);
+                StateManagerMessage::ForcedTransactionSubmitted(forced_tx_submitted, position) => {
+                    self.handle_forced_tx_submitted(forced_tx_submitted, position);
+                }
+                StateManagerMessage::Rollback(ancestor_block, resume_sink) => {
+                    let last_processed = self.handle_rollback(ancestor_block)?;
+
+                    // NOTE: Sending only fails if the indexer stopped, in which case there is no one to resume.
+                    let _ = resume_sink.send(last_processed);
                 }
             }
         }
@@ -65,38 +140,79 @@ where
     /// included in the provided [BatchProved].
     ///
     /// For each forced transaction, its proof MUST be re-verified before updating the imt as they are allowed
-    /// to be invalid. The imt is only updated when the proof verification passes.
+    /// to be invalid. The imt is only updated when the proof verification passes (see [ForcedTxVerifier]).
     ///
     /// For sequenced transactions, there is no need to re-verify the proofs as the Batcher program already enforces
     /// the proof validity.
-    async fn handle_batch_proved(&mut self, batch_proved: BatchProved) -> Result<()> {
+    ///
+    /// The resulting imt root MUST match the `newRoot` proved on L1, otherwise the storage transaction is
+    /// discarded and a fatal [StateManagerError::RootDivergence] is returned.
+    ///
+    /// The [Checkpoint] is persisted in the same storage transaction as the imt updates.
+    async fn handle_batch_proved(
+        &mut self,
+        batch_proved: BatchProved,
+        position: LogPosition,
+    ) -> Result<()> {
         debug!(event = "BatchProved", "Processing event");
 
+        // NOTE: The forced transactions are only dequeued once the batch is commited.
+        let forced_tx_count: usize = batch_proved.forcedTxCount.to();
+        let pending_forced_tx_count = self.checkpoint.pending_forced_transactions.len();
+        if forced_tx_count > pending_forced_tx_count {
+            return Err(StateManagerError::InconsistentForcedTxs(
+                position,
+                forced_tx_count,
+                pending_forced_tx_count,
+            )
+            .into());
+        }
+
         let mut tx = self.storage.transaction();
-        let mut imt = Imt::writer(Keccak::v256, &mut tx);
+        let mut imt_tx = ImtStorage::<_, [u8; 32], [u8; 32]>::new(&mut tx);
 
-        let forced_tx_count: usize = batch_proved.forcedTxCount.to();
-        for _ in 0..forced_tx_count {
-            let forced_tx = self
-                .pending_forced_transactions
-                .pop_front()
-                .expect("inconsitent forced txs state");
+        // Register the batch updates under a new imt version so that the previous roots remain queryable.
+        let version = imt_tx.get_version()?.map_or(0, |version| version + 1);
+        imt_tx.set_version(version)?;
+
+        let mut imt = Imt::writer(Keccak::v256, imt_tx)?;
+        let previous_root = imt.root()?;
 
+        let mut mutations = Vec::with_capacity(forced_tx_count + batch_proved.sequencedTxs.len());
+        let mut forced_txs = Vec::with_capacity(forced_tx_count);
+        for forced_tx in self
+            .checkpoint
+            .pending_forced_transactions
+            .range(..forced_tx_count)
+        {
             debug!(
                 keyspace_id = forced_tx.keySpaceId.to_string(),
                 new_value = forced_tx.newValue.to_string(),
                 "Processing forced transaction"
             );
 
-            // TODO: Verify the forced tx proof. TBD if we should not verify the proof onchain directly.
-            let is_valid = true;
+            // NOTE: Forced transactions with an invalid proof are skipped, as the Batcher program does.
+            let is_valid = match self.forced_tx_verifier.verify(forced_tx) {
+                Ok(()) => true,
+                Err(e) => {
+                    warn!(
+                        keyspace_id = forced_tx.keySpaceId.to_string(),
+                        error = e.to_string(),
+                        "Skipping forced transaction with an invalid proof"
+                    );
+                    false
+                }
+            };
+
             if is_valid {
-                imt.set_node(forced_tx.keySpaceId.into(), forced_tx.newValue.into())?;
+                mutations.push((forced_tx.keySpaceId.into(), forced_tx.newValue.into()));
             }
+
+            forced_txs.push((forced_tx, is_valid));
         }
 
         // Process the sequenced transactions that were sent to the node mempool already.
-        for sequenced_tx in batch_proved.sequencedTxs {
+        for sequenced_tx in &batch_proved.sequencedTxs {
             debug!(
                 keyspace_id = sequenced_tx.keySpaceId.to_string(),
                 new_value = sequenced_tx.newValue.to_string(),
@@ -106,26 +222,320 @@ where
             // NOTE: For sequenced transactions there is no need to verify them again here before
             //       updating the imt state as sequenced transactions MUST be valid for the Batcher
             //       proof to verify correctly in the L1 KeyStore contract.
-            imt.set_node(sequenced_tx.keySpaceId.into(), sequenced_tx.newValue.into())?;
+            mutations.push((sequenced_tx.keySpaceId.into(), sequenced_tx.newValue.into()));
         }
 
+        // Apply all the mutations at once.
+        imt.set_nodes(mutations)?;
+
+        let root = imt.root()?;
         debug!(
-            size = imt.size(),
-            depth = imt.depth(),
-            root = format!("{:?}", imt.root()),
+            size = imt.size()?,
+            depth = imt.depth()?,
+            root = format!("{:?}", root),
             "Imt updated dimensions"
         );
 
-        tx.commit();
+        // Ensure the rebuilt imt matches the root proved on L1 before commiting it, as a divergence
+        // would otherwise corrupt the rollup state for good.
+        if B256::from(root) != batch_proved.newRoot {
+            tx.discard();
+
+            let dump = divergence_dump(&batch_proved, &forced_txs, version, previous_root, root);
+            error!(?position, "Imt root diverged from L1:\n{dump}");
+            return Err(StateManagerError::RootDivergence(position, dump).into());
+        }
+
+        // Persist the progress atomically with the imt updates, and only update the in-memory
+        // checkpoint once commited so that it never gets ahead of the storage.
+        let mut checkpoint = self.checkpoint.clone();
+        checkpoint
+            .pending_forced_transactions
+            .drain(..forced_tx_count);
+        checkpoint.last_processed = Some(position);
+        checkpoint.store(&mut tx, version)?;
+
+        // Discard the history of the versions that fell out of the retention window.
+        if let Some(pruned_version) = version.checked_sub(RETAINED_IMT_VERSIONS) {
+            ImtStorage::<_, [u8; 32], [u8; 32]>::new(&mut tx).prune(pruned_version)?;
+            Checkpoint::prune(&mut tx, pruned_version)?;
+        }
+
+        tx.commit()?;
+        self.checkpoint = checkpoint;
+
+        // Publish the new commited state to the readers.
+        self.snapshot.send_replace(self.storage.snapshot());
 
         Ok(())
     }
 
-    /// Push the received [ForcedTransactionSubmitted] to the [Self::pending_forced_transactions] queue.
-    fn handle_forced_tx_submitted(&mut self, forced_tx_submitted: ForcedTransactionSubmitted) {
+    /// Reverts the imt and the [Checkpoint] to the last [BatchProved] processed at or before the given
+    /// `ancestor_block`, i.e. the last L1 block that is still canonical after a reorg.
+    ///
+    /// The forced transactions processed after this batch are dropped along with the in-memory
+    /// [Checkpoint] and are indexed again, as the indexer resumes from the returned
+    /// [Checkpoint::last_processed].
+    fn handle_rollback(&mut self, ancestor_block: u64) -> Result<Option<LogPosition>> {
+        debug!(ancestor_block, "Processing rollback");
+
+        let mut tx = self.storage.transaction();
+        let Some(current_version) = ImtStorage::<_, [u8; 32], [u8; 32]>::new(&tx).get_version()?
+        else {
+            // No batch was applied yet, only the processed forced transactions are dropped.
+            self.checkpoint = Checkpoint::default();
+            return Ok(None);
+        };
+
+        // Find the last version whose batch was emitted at or before the ancestor block.
+        let mut version = Some(current_version);
+        while let Some(v) = version {
+            let checkpoint = Checkpoint::load_at(&tx, v)?
+                .ok_or_else(|| anyhow!("missing checkpoint at imt version {v}"))?;
+            if checkpoint
+                .last_processed
+                .is_some_and(|position| position.block_number <= ancestor_block)
+            {
+                break;
+            }
+
+            version = v.checked_sub(1);
+        }
+
+        ImtStorage::<_, [u8; 32], [u8; 32]>::new(&mut tx).rollback(version)?;
+        self.checkpoint = Checkpoint::rollback(&mut tx, version, current_version)?;
+
+        tx.commit()?;
+
+        // Publish the reverted state to the readers.
+        self.snapshot.send_replace(self.storage.snapshot());
+
+        info!(
+            ?version,
+            last_processed = ?self.checkpoint.last_processed,
+            "Rolled back the imt"
+        );
+
+        Ok(self.checkpoint.last_processed)
+    }
+
+    /// Push the received [ForcedTransactionSubmitted] to the [Checkpoint::pending_forced_transactions] queue.
+    ///
+    /// NOTE: The queue is only persisted with the next [BatchProved]. Until then the forced transaction is
+    ///       indexed again after a restart, as it is after the persisted [Checkpoint::last_processed].
+    fn handle_forced_tx_submitted(
+        &mut self,
+        forced_tx_submitted: ForcedTransactionSubmitted,
+        position: LogPosition,
+    ) {
         debug!(event = "ForcedTransactionSubmitted", "Processing event");
 
-        self.pending_forced_transactions
+        self.checkpoint
+            .pending_forced_transactions
             .push_back(forced_tx_submitted);
+        self.checkpoint.last_processed = Some(position);
+    }
+}
+
+/// Returns a human readable dump of the given [BatchProved] whose application diverged from L1.
+fn divergence_dump(
+    batch_proved: &BatchProved,
+    forced_txs: &[(&ForcedTransactionSubmitted, bool)],
+    version: u64,
+    previous_root: Hash256,
+    root: Hash256,
+) -> String {
+    let mut dump = format!(
+        "imt version: {version}\n\
+         previous root: {}\n\
+         computed root: {}\n\
+         expected root: {}\n\
+         forced tx commitment proved: {}\n",
+        B256::from(previous_root),
+        B256::from(root),
+        batch_proved.newRoot,
+        batch_proved.forcedTxCommitmentProved,
+    );
+
+    for (i, (forced_tx, is_valid)) in forced_txs.iter().enumerate() {
+        dump.push_str(&format!(
+            "forced tx #{i} (applied: {is_valid}): {forced_tx:?}\n"
+        ));
+    }
+
+    for (i, sequenced_tx) in batch_proved.sequencedTxs.iter().enumerate() {
+        dump.push_str(&format!("sequenced tx #{i}: {sequenced_tx:?}\n"));
+    }
+
+    dump
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{Bytes, U256};
+    use keyspace_keystore_bindings::bindings::Transaction;
+    use tokio::sync::mpsc;
+
+    use super::*;
+    use crate::storage::btree::BTreeStorage;
+
+    /// A [ForcedTxVerifier] only accepting the forced transactions whose proof is `valid proof`.
+    #[derive(Debug)]
+    struct MockForcedTxVerifier;
+
+    impl ForcedTxVerifier for MockForcedTxVerifier {
+        fn verify(&self, forced_tx: &ForcedTransactionSubmitted) -> Result<()> {
+            anyhow::ensure!(&forced_tx.proof[..] == b"valid proof", "invalid proof");
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_root_divergence() {
+        let (_sink, stream) = mpsc::channel(1);
+        let mut sut = StateManager::new(BTreeStorage::new(), stream, MockForcedTxVerifier)
+            .expect("new failed");
+
+        let sequenced_tx = Transaction {
+            keySpaceId: B256::repeat_byte(1),
+            newValue: B256::repeat_byte(2),
+            ..Default::default()
+        };
+        let batch_proved = |new_root| BatchProved {
+            newRoot: new_root,
+            sequencedTxs: vec![sequenced_tx.clone()],
+            ..Default::default()
+        };
+        let position = LogPosition {
+            block_number: 1,
+            log_index: 0,
+        };
+
+        // Ensure a batch diverging from L1 is rejected without commiting anything.
+        let res = sut
+            .handle_batch_proved(batch_proved(B256::repeat_byte(0xff)), position)
+            .await
+            .map_err(|e| e.downcast::<StateManagerError>());
+        assert!(matches!(res, Err(Ok(StateManagerError::RootDivergence(p, _))) if p == position));
+        assert_eq!(sut.last_processed(), None);
+        let imt_storage = ImtStorage::<_, [u8; 32], [u8; 32]>::new(&sut.storage);
+        assert_eq!(imt_storage.get_version().expect("get_version failed"), None);
+
+        // Ensure the batch is commited when matching L1.
+        let mut expected_imt = Imt::writer(Keccak::v256, ImtStorage::new(BTreeStorage::new()))
+            .expect("imt writer failed");
+        expected_imt
+            .insert_node([1; 32], [2; 32])
+            .expect("insert failed");
+        let new_root = expected_imt.root().expect("root failed");
+
+        sut.handle_batch_proved(batch_proved(new_root.into()), position)
+            .await
+            .expect("handle_batch_proved failed");
+        assert_eq!(sut.last_processed(), Some(position));
+    }
+
+    #[tokio::test]
+    async fn test_inconsistent_forced_txs() {
+        let (_sink, stream) = mpsc::channel(1);
+        let mut sut = StateManager::new(BTreeStorage::new(), stream, MockForcedTxVerifier)
+            .expect("new failed");
+
+        let batch_proved = BatchProved {
+            forcedTxCount: U256::from(1),
+            ..Default::default()
+        };
+        let position = LogPosition {
+            block_number: 1,
+            log_index: 0,
+        };
+
+        // Ensure a batch including more forced txs than the pending ones is rejected.
+        let res = sut
+            .handle_batch_proved(batch_proved, position)
+            .await
+            .map_err(|e| e.downcast::<StateManagerError>());
+        assert!(matches!(
+            res,
+            Err(Ok(StateManagerError::InconsistentForcedTxs(p, 1, 0))) if p == position
+        ));
+        assert_eq!(sut.last_processed(), None);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_forced_tx_skipped() {
+        let (_sink, stream) = mpsc::channel(1);
+        let verifier = SP1ForcedTxVerifier::new([0; 32]);
+        let mut sut = StateManager::new(BTreeStorage::new(), stream, verifier).expect("new failed");
+
+        let forced_tx = ForcedTransactionSubmitted {
+            keySpaceId: B256::repeat_byte(1),
+            currentValue: B256::repeat_byte(1),
+            newValue: B256::repeat_byte(2),
+            proof: Bytes::from_static(b"invalid proof"),
+            ..Default::default()
+        };
+        let position = |block_number| LogPosition {
+            block_number,
+            log_index: 0,
+        };
+        sut.handle_forced_tx_submitted(forced_tx, position(1));
+
+        // Ensure the batch is commited without applying the forced tx.
+        let expected_imt = Imt::writer(
+            Keccak::v256,
+            ImtStorage::<_, [u8; 32], [u8; 32]>::new(BTreeStorage::new()),
+        )
+        .expect("imt writer failed");
+        let batch_proved = BatchProved {
+            newRoot: expected_imt.root().expect("root failed").into(),
+            forcedTxCount: U256::from(1),
+            ..Default::default()
+        };
+
+        sut.handle_batch_proved(batch_proved, position(2))
+            .await
+            .expect("handle_batch_proved failed");
+        assert!(sut.checkpoint.pending_forced_transactions.is_empty());
+        assert_eq!(sut.last_processed(), Some(position(2)));
+    }
+
+    #[tokio::test]
+    async fn test_valid_forced_tx_applied() {
+        let (_sink, stream) = mpsc::channel(1);
+        let mut sut = StateManager::new(BTreeStorage::new(), stream, MockForcedTxVerifier)
+            .expect("new failed");
+
+        let forced_tx = |keyspace_id, proof| ForcedTransactionSubmitted {
+            keySpaceId: B256::repeat_byte(keyspace_id),
+            currentValue: B256::repeat_byte(keyspace_id),
+            newValue: B256::repeat_byte(2),
+            proof: Bytes::from_static(proof),
+            ..Default::default()
+        };
+        let position = |block_number| LogPosition {
+            block_number,
+            log_index: 0,
+        };
+        sut.handle_forced_tx_submitted(forced_tx(1, b"valid proof"), position(1));
+        sut.handle_forced_tx_submitted(forced_tx(3, b"invalid proof"), position(2));
+
+        // Ensure the batch is commited with only the valid forced tx applied.
+        let mut expected_imt = Imt::writer(Keccak::v256, ImtStorage::new(BTreeStorage::new()))
+            .expect("imt writer failed");
+        expected_imt
+            .insert_node([1; 32], [2; 32])
+            .expect("insert failed");
+        let batch_proved = BatchProved {
+            newRoot: expected_imt.root().expect("root failed").into(),
+            forcedTxCount: U256::from(2),
+            ..Default::default()
+        };
+
+        sut.handle_batch_proved(batch_proved, position(3))
+            .await
+            .expect("handle_batch_proved failed");
+        assert!(sut.checkpoint.pending_forced_transactions.is_empty());
+        assert_eq!(sut.last_processed(), Some(position(3)));
     }
 }
@@ -1,9 +1,30 @@
 use keyspace_keystore_bindings::bindings::KeyStore::{BatchProved, ForcedTransactionSubmitted};
+use tokio::sync::oneshot;
+
+use crate::checkpoint::LogPosition;
 
 /// This enum defines the different messages that the [crate::manager::StateManager] listen for.
+///
+/// Each event comes with the [LogPosition] of the log it was decoded from.
 pub enum StateManagerMessage {
     /// Wrapper around the [BatchProved] emitted by the L1 KeyStore contract.
-    BatchProved(BatchProved),
+    BatchProved(BatchProved, LogPosition),
     /// Wrapper around the [ForcedTransactionSubmitted] emitted by the L1 KeyStore contract.
-    ForcedTransactionSubmitted(ForcedTransactionSubmitted),
+    ForcedTransactionSubmitted(ForcedTransactionSubmitted, LogPosition),
+    /// Sent on an L1 reorg with the number of the last L1 block that is still canonical.
+    ///
+    /// The rollup state is reverted to the last [BatchProved] emitted at or before this block and the
+    /// position of the last processed log, from which the indexer MUST resume, is sent back.
+    Rollback(u64, oneshot::Sender<Option<LogPosition>>),
+}
+
+impl StateManagerMessage {
+    /// Returns the [LogPosition] of the event, if the message is an event.
+    pub fn position(&self) -> Option<LogPosition> {
+        match self {
+            StateManagerMessage::BatchProved(_, position) => Some(*position),
+            StateManagerMessage::ForcedTransactionSubmitted(_, position) => Some(*position),
+            StateManagerMessage::Rollback(..) => None,
+        }
+    }
 }
@@ -1,22 +1,54 @@
-use std::collections::BTreeMap;
+use im::OrdMap;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Debug},
+    ops::Bound,
+};
 
-use super::{StorageReader, StorageWriter, Transaction, TransactionalStorage};
+use super::{
+    merge_buffered, SnapshotStorage, StorageIter, StorageReader, StorageResult, StorageWriter,
+    Transaction, TransactionalStorage,
+};
 
-/// A storage implementation over a [BTreeMap].
-#[derive(Debug, Default)]
+/// A storage implementation over a persistent B-tree ([OrdMap]).
+///
+/// It can be (de)serialized to be snapshotted to disk.
+///
+/// The [OrdMap] shares its structure with the [BTreeStorage] snapshots: writing to it while a
+/// snapshot is alive only copies the B-tree nodes on the path to the written key.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(bound(
+    serialize = "K: Ord + Clone + Serialize, V: Clone + Serialize",
+    deserialize = "K: Ord + Clone + Deserialize<'de>, V: Clone + Deserialize<'de>"
+))]
 pub struct BTreeStorage<K, V> {
-    data: BTreeMap<K, V>,
+    data: OrdMap<K, V>,
 }
 
 impl<K, V> BTreeStorage<K, V> {
     /// Creates a new [BTreeStorage].
     pub fn new() -> Self {
         BTreeStorage {
-            data: BTreeMap::new(),
+            data: OrdMap::new(),
         }
     }
 }
 
+impl<K: Ord + Debug, V: Debug> Debug for BTreeStorage<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BTreeStorage")
+            .field("data", &self.data)
+            .finish()
+    }
+}
+
+impl<K, V> Default for BTreeStorage<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<K, Vaue> StorageReader for BTreeStorage<K, Vaue>
 where
     K: Clone + Ord,
@@ -25,15 +57,31 @@ where
     type StorageKey = K;
     type StorageValue = Vaue;
 
-    fn get(&self, key: &Self::StorageKey) -> Option<Self::StorageValue> {
-        self.data.get(key).cloned()
+    fn get(&self, key: &Self::StorageKey) -> StorageResult<Option<Self::StorageValue>> {
+        Ok(self.data.get(key).cloned())
     }
 
-    fn get_lt(&self, key: &Self::StorageKey) -> Option<(Self::StorageKey, Self::StorageValue)> {
-        self.data
+    fn get_lt(
+        &self,
+        key: &Self::StorageKey,
+    ) -> StorageResult<Option<(Self::StorageKey, Self::StorageValue)>> {
+        Ok(self
+            .data
             .range(..key)
             .next_back()
-            .map(|(k, v)| (k.clone(), v.clone()))
+            .map(|(k, v)| (k.clone(), v.clone())))
+    }
+
+    fn range<'a>(
+        &'a self,
+        start: Bound<&Self::StorageKey>,
+        end: Bound<&Self::StorageKey>,
+    ) -> StorageIter<'a, Self::StorageKey, Self::StorageValue> {
+        Box::new(
+            self.data
+                .range((start, end))
+                .map(|(k, v)| Ok((k.clone(), v.clone()))),
+        )
     }
 }
 
@@ -42,8 +90,14 @@ where
     K: Clone + Ord,
     V: Clone,
 {
-    fn set(&mut self, key: Self::StorageKey, value: Self::StorageValue) {
+    fn set(&mut self, key: Self::StorageKey, value: Self::StorageValue) -> StorageResult<()> {
         self.data.insert(key, value);
+        Ok(())
+    }
+
+    fn remove(&mut self, key: &Self::StorageKey) -> StorageResult<()> {
+        self.data.remove(key);
+        Ok(())
     }
 }
 
@@ -52,17 +106,35 @@ where
     K: Clone + Ord,
     V: Clone,
 {
-    type T<'a> = BTreeTransaction<'a, K, V> where K: 'a, V: 'a;
+    type T<'a>
+        = BTreeTransaction<'a, K, V>
+    where
+        K: 'a,
+        V: 'a;
 
     fn transaction(&mut self) -> Self::T<'_> {
         BTreeTransaction::new(self)
     }
 }
 
+impl<K, V> SnapshotStorage for BTreeStorage<K, V>
+where
+    K: Clone + Ord,
+    V: Clone,
+{
+    type Snapshot = BTreeStorage<K, V>;
+
+    fn snapshot(&self) -> Self::Snapshot {
+        self.clone()
+    }
+}
+
 /// A storage transaction that can be created from a [BTreeStorage].
+///
+/// Removed keys are buffered as tombstones (`None`) until the transaction is commited.
 pub struct BTreeTransaction<'a, K, V> {
     storage: &'a mut BTreeStorage<K, V>,
-    buffer: BTreeMap<K, V>,
+    buffer: BTreeMap<K, Option<V>>,
 }
 
 impl<'a, K, V> BTreeTransaction<'a, K, V> {
@@ -83,30 +155,58 @@ where
     type StorageKey = K;
     type StorageValue = V;
 
-    fn get(&self, key: &Self::StorageKey) -> Option<Self::StorageValue> {
-        self.buffer
-            .get(key)
-            .cloned()
-            .or_else(|| self.storage.get(key))
-    }
-
-    fn get_lt(&self, key: &Self::StorageKey) -> Option<(Self::StorageKey, Self::StorageValue)> {
-        let closest_buffer_entry = self.buffer.range(..key).next_back();
-        let closest_storage_entry = self.storage.data.range(..key).next_back();
-
-        match (closest_buffer_entry, closest_storage_entry) {
-            (None, None) => None,
-            (None, Some(storage_entry)) => Some((storage_entry.0.clone(), storage_entry.1.clone())),
-            (Some(buffer_entry), None) => Some((buffer_entry.0.clone(), buffer_entry.1.clone())),
-            (Some(buffer_entry), Some(storage_entry)) => {
-                if buffer_entry.0 > storage_entry.0 {
-                    Some((buffer_entry.0.clone(), buffer_entry.1.clone()))
-                } else {
-                    Some((storage_entry.0.clone(), storage_entry.1.clone()))
+    fn get(&self, key: &Self::StorageKey) -> StorageResult<Option<Self::StorageValue>> {
+        match self.buffer.get(key) {
+            Some(value) => Ok(value.clone()),
+            None => self.storage.get(key),
+        }
+    }
+
+    fn get_lt(
+        &self,
+        key: &Self::StorageKey,
+    ) -> StorageResult<Option<(Self::StorageKey, Self::StorageValue)>> {
+        let mut buffer_entries = self.buffer.range(..key).rev().peekable();
+        let mut storage_entries = self.storage.data.range(..key).rev().peekable();
+
+        loop {
+            match (buffer_entries.peek(), storage_entries.peek()) {
+                (None, None) => return Ok(None),
+                (None, Some(storage_entry)) => {
+                    return Ok(Some((storage_entry.0.clone(), storage_entry.1.clone())))
+                }
+                (Some(buffer_entry), Some(storage_entry)) if storage_entry.0 > buffer_entry.0 => {
+                    return Ok(Some((storage_entry.0.clone(), storage_entry.1.clone())))
+                }
+                (Some(buffer_entry), storage_entry) => {
+                    // The buffer entry shadows the storage entry with the same key (if any).
+                    if storage_entry.is_some_and(|storage_entry| storage_entry.0 == buffer_entry.0)
+                    {
+                        storage_entries.next();
+                    }
+
+                    match buffer_entry.1 {
+                        Some(value) => return Ok(Some((buffer_entry.0.clone(), value.clone()))),
+                        // Skip the removed entries.
+                        None => {
+                            buffer_entries.next();
+                        }
+                    }
                 }
             }
         }
     }
+
+    fn range<'b>(
+        &'b self,
+        start: Bound<&Self::StorageKey>,
+        end: Bound<&Self::StorageKey>,
+    ) -> StorageIter<'b, Self::StorageKey, Self::StorageValue> {
+        merge_buffered(
+            self.buffer.range((start, end)),
+            self.storage.range(start, end),
+        )
+    }
 }
 
 impl<'a, K, V> StorageWriter for BTreeTransaction<'a, K, V>
@@ -114,8 +214,14 @@ where
     K: Clone + Ord,
     V: Clone,
 {
-    fn set(&mut self, key: Self::StorageKey, value: Self::StorageValue) {
-        self.buffer.insert(key, value);
+    fn set(&mut self, key: Self::StorageKey, value: Self::StorageValue) -> StorageResult<()> {
+        self.buffer.insert(key, Some(value));
+        Ok(())
+    }
+
+    fn remove(&mut self, key: &Self::StorageKey) -> StorageResult<()> {
+        self.buffer.insert(key.clone(), None);
+        Ok(())
     }
 }
 
@@ -124,11 +230,93 @@ where
     K: Clone + Ord,
     V: Clone,
 {
-    fn commit(self) {
+    fn commit(self) -> StorageResult<()> {
         for (k, v) in self.buffer {
-            self.storage.set(k, v);
+            match v {
+                Some(v) => self.storage.set(k, v)?,
+                None => self.storage.remove(&k)?,
+            }
         }
+
+        Ok(())
     }
 
     fn discard(self) {}
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transaction_get_lt() {
+        let mut storage = BTreeStorage::new();
+        for (k, v) in [(1, "a"), (3, "c"), (5, "e")] {
+            storage.set(k, v).expect("set failed");
+        }
+
+        let mut tx = storage.transaction();
+        tx.set(4, "d").expect("set failed");
+        tx.remove(&3).expect("remove failed");
+
+        // Buffered entries shadow the stored ones and removed entries are skipped.
+        assert_eq!(tx.get_lt(&6).expect("get_lt failed"), Some((5, "e")));
+        assert_eq!(tx.get_lt(&5).expect("get_lt failed"), Some((4, "d")));
+        assert_eq!(tx.get_lt(&4).expect("get_lt failed"), Some((1, "a")));
+        assert_eq!(tx.get_lt(&1).expect("get_lt failed"), None);
+        assert_eq!(tx.get(&3).expect("get failed"), None);
+
+        tx.commit().expect("commit failed");
+
+        assert_eq!(storage.get(&3).expect("get failed"), None);
+        assert_eq!(storage.get_lt(&5).expect("get_lt failed"), Some((4, "d")));
+    }
+
+    #[test]
+    fn test_transaction_range() {
+        let mut storage = BTreeStorage::new();
+        for (k, v) in [(1, "a"), (3, "c"), (5, "e"), (7, "g")] {
+            storage.set(k, v).expect("set failed");
+        }
+
+        let mut tx = storage.transaction();
+        tx.set(4, "d").expect("set failed");
+        tx.set(5, "f").expect("set failed");
+        tx.remove(&3).expect("remove failed");
+
+        // Buffered entries shadow the stored ones and removed entries are skipped.
+        let range = |start, end| {
+            tx.range(start, end)
+                .collect::<StorageResult<Vec<_>>>()
+                .expect("range failed")
+        };
+        assert_eq!(
+            range(Bound::Unbounded, Bound::Unbounded),
+            [(1, "a"), (4, "d"), (5, "f"), (7, "g")]
+        );
+        assert_eq!(
+            range(Bound::Excluded(&1), Bound::Included(&5)),
+            [(4, "d"), (5, "f")]
+        );
+        assert_eq!(range(Bound::Included(&2), Bound::Excluded(&4)), []);
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let mut storage = BTreeStorage::new();
+        storage.set(1, "a").expect("set failed");
+
+        let sut = storage.snapshot();
+
+        // Ensure the snapshot is not affected by the writes commited after it was taken.
+        let mut tx = storage.transaction();
+        tx.set(1, "b").expect("set failed");
+        tx.set(2, "c").expect("set failed");
+        tx.commit().expect("commit failed");
+
+        assert_eq!(sut.get(&1).expect("get failed"), Some("a"));
+        assert_eq!(sut.get(&2).expect("get failed"), None);
+        assert_eq!(storage.get(&1).expect("get failed"), Some("b"));
+        assert_eq!(storage.snapshot().get(&2).expect("get failed"), Some("c"));
+    }
+}
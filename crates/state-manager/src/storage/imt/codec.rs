@@ -0,0 +1,44 @@
+use keyspace_imt::Hash256;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::storage::{StorageError, StorageResult};
+
+// Helpers (de)serializing the imt elements persisted as raw bytes.
+
+/// Serializes the given imt `node` (or node history entry).
+pub(super) fn encode_node<T: Serialize>(node: &T) -> StorageResult<Vec<u8>> {
+    bincode::serialize(node).map_err(|e| StorageError::Serialization("imt node", e.to_string()))
+}
+
+/// Deserializes an imt node (or node history entry) from the given `bytes`.
+pub(super) fn decode_node<T: DeserializeOwned>(bytes: &[u8]) -> StorageResult<T> {
+    bincode::deserialize(bytes)
+        .map_err(|e| StorageError::Deserialization("imt node", e.to_string()))
+}
+
+/// Deserializes an imt node key from its raw `bytes`.
+pub(super) fn decode_node_key<K>(bytes: &[u8]) -> StorageResult<K>
+where
+    K: for<'a> TryFrom<&'a [u8]>,
+{
+    K::try_from(bytes).map_err(|_| {
+        StorageError::Deserialization("imt node key", format!("invalid {} bytes key", bytes.len()))
+    })
+}
+
+/// Deserializes a [Hash256] (named `what`) from the given `bytes`.
+pub(super) fn decode_hash(what: &'static str, bytes: Vec<u8>) -> StorageResult<Hash256> {
+    bytes.try_into().map_err(|bytes: Vec<u8>| {
+        StorageError::Deserialization(what, format!("expected 32 bytes, got {}", bytes.len()))
+    })
+}
+
+/// Deserializes a little endian `u64` (named `what`) from the given `bytes`.
+pub(super) fn decode_u64(what: &'static str, bytes: Vec<u8>) -> StorageResult<u64> {
+    bytes
+        .try_into()
+        .map(u64::from_le_bytes)
+        .map_err(|bytes: Vec<u8>| {
+            StorageError::Deserialization(what, format!("expected 8 bytes, got {}", bytes.len()))
+        })
+}
@@ -5,23 +5,202 @@
 // necessary as nodes MIGTH be fetched from the storage using `get_lt` that returns the
 // key immediately preceding the given one. For this `NODE_STORAGE_PREFIX` MUST be the
 // lowest key prefix.
+//
+// The node keys are variable width: they are escaped and terminated (see `push_node_key`) so that
+// no encoded node key is the prefix of another one while keeping their lexicographical order.
+//
+// Storages with column families (e.g. RocksDB) can persist each kind of storage keys in its own
+// [StorageColumn] instead of prefixing them (see `StorageColumn::split`). The single entries
+// (size, root, version and the state manager checkpoints) are persisted whole in the metadata
+// column, as their key is then only their name.
+//
+// The versioned storage keys are suffixed with the big endian encoded version so that all the
+// versions of the same element are contiguous and ordered. The value of an element at a given
+// version is thus the one stored under the closest key lower or equal to it (see `get_lt`).
+//
+// Each versioned node, hash and root registration write is also registered under a version change
+// storage key (the big endian encoded version followed by the storage key written) so that the writes
+// of a version can be listed to roll it back.
+
+use std::ops::Bound;
 
 const NODE_STORAGE_PREFIX: u8 = 0;
 const HASH_STORAGE_PREFIX: u8 = 1;
 const SIZE_STORAGE_PREFIX: u8 = 2;
 const ROOT_STORAGE_PREFIX: u8 = 3;
+const VERSION_STORAGE_PREFIX: u8 = 4;
+const VERSIONED_NODE_STORAGE_PREFIX: u8 = 5;
+const VERSIONED_HASH_STORAGE_PREFIX: u8 = 6;
+const VERSIONED_SIZE_STORAGE_PREFIX: u8 = 7;
+const VERSIONED_ROOT_STORAGE_PREFIX: u8 = 8;
+const ROOT_VERSION_STORAGE_PREFIX: u8 = 9;
+const NODE_KEY_STORAGE_PREFIX: u8 = 10;
+const VERSION_CHANGE_STORAGE_PREFIX: u8 = 11;
+
+/// The length of the version suffix of the versioned storage keys.
+pub const VERSION_SUFFIX_LEN: usize = 8;
+
+/// The byte escaping the `0x00` bytes of the encoded node keys.
+const NODE_KEY_ESCAPE: u8 = 0xff;
+
+/// The bytes terminating the encoded node keys.
+const NODE_KEY_TERMINATOR: [u8; 2] = [0x00, 0x00];
+
+/// The kinds of storage keys, that can be persisted in separate columns (families).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageColumn {
+    /// The nodes.
+    Nodes,
+    /// The node keys indexed by node index.
+    NodeKeys,
+    /// The hashes.
+    Hashes,
+    /// The versioned nodes.
+    VersionedNodes,
+    /// The versioned hashes.
+    VersionedHashes,
+    /// The versioned sizes.
+    VersionedSizes,
+    /// The versioned roots.
+    VersionedRoots,
+    /// The versions of the roots.
+    RootVersions,
+    /// The changes of each version.
+    VersionChanges,
+    /// The single entries (size, root, version) and the other unprefixed storage keys.
+    Metadata,
+}
+
+impl StorageColumn {
+    pub const ALL: [StorageColumn; 10] = [
+        StorageColumn::Nodes,
+        StorageColumn::NodeKeys,
+        StorageColumn::Hashes,
+        StorageColumn::VersionedNodes,
+        StorageColumn::VersionedHashes,
+        StorageColumn::VersionedSizes,
+        StorageColumn::VersionedRoots,
+        StorageColumn::RootVersions,
+        StorageColumn::VersionChanges,
+        StorageColumn::Metadata,
+    ];
+
+    /// Returns the [StorageColumn] of the given storage key, based on its prefix.
+    pub fn of(storage_key: &[u8]) -> Self {
+        Self::split(storage_key).0
+    }
+
+    /// Splits the given storage key into its [StorageColumn] and its key within the column, i.e.
+    /// without its prefix (or whole for the [StorageColumn::Metadata]).
+    pub fn split(storage_key: &[u8]) -> (Self, &[u8]) {
+        let Some((prefix, key)) = storage_key.split_first() else {
+            return (StorageColumn::Metadata, storage_key);
+        };
+
+        let column = match *prefix {
+            NODE_STORAGE_PREFIX => StorageColumn::Nodes,
+            NODE_KEY_STORAGE_PREFIX => StorageColumn::NodeKeys,
+            HASH_STORAGE_PREFIX => StorageColumn::Hashes,
+            VERSIONED_NODE_STORAGE_PREFIX => StorageColumn::VersionedNodes,
+            VERSIONED_HASH_STORAGE_PREFIX => StorageColumn::VersionedHashes,
+            VERSIONED_SIZE_STORAGE_PREFIX => StorageColumn::VersionedSizes,
+            VERSIONED_ROOT_STORAGE_PREFIX => StorageColumn::VersionedRoots,
+            ROOT_VERSION_STORAGE_PREFIX => StorageColumn::RootVersions,
+            VERSION_CHANGE_STORAGE_PREFIX => StorageColumn::VersionChanges,
+            _ => return (StorageColumn::Metadata, storage_key),
+        };
+
+        (column, key)
+    }
+
+    /// Returns the storage key of the given `key` within the [StorageColumn] (see [Self::split]).
+    pub fn join(&self, key: &[u8]) -> Vec<u8> {
+        let prefix = match self {
+            StorageColumn::Nodes => NODE_STORAGE_PREFIX,
+            StorageColumn::NodeKeys => NODE_KEY_STORAGE_PREFIX,
+            StorageColumn::Hashes => HASH_STORAGE_PREFIX,
+            StorageColumn::VersionedNodes => VERSIONED_NODE_STORAGE_PREFIX,
+            StorageColumn::VersionedHashes => VERSIONED_HASH_STORAGE_PREFIX,
+            StorageColumn::VersionedSizes => VERSIONED_SIZE_STORAGE_PREFIX,
+            StorageColumn::VersionedRoots => VERSIONED_ROOT_STORAGE_PREFIX,
+            StorageColumn::RootVersions => ROOT_VERSION_STORAGE_PREFIX,
+            StorageColumn::VersionChanges => VERSION_CHANGE_STORAGE_PREFIX,
+            StorageColumn::Metadata => return key.to_vec(),
+        };
+
+        [&[prefix], key].concat()
+    }
+
+    /// Returns the name of the [StorageColumn].
+    pub fn name(&self) -> &'static str {
+        match self {
+            StorageColumn::Nodes => "nodes",
+            StorageColumn::NodeKeys => "node_keys",
+            StorageColumn::Hashes => "hashes",
+            StorageColumn::VersionedNodes => "versioned_nodes",
+            StorageColumn::VersionedHashes => "versioned_hashes",
+            StorageColumn::VersionedSizes => "versioned_sizes",
+            StorageColumn::VersionedRoots => "versioned_roots",
+            StorageColumn::RootVersions => "root_versions",
+            StorageColumn::VersionChanges => "version_changes",
+            StorageColumn::Metadata => "metadata",
+        }
+    }
+}
 
 /// Returns the node storage key to use for persistence.
 pub fn node_storage_key(key: impl AsRef<[u8]>) -> Vec<u8> {
-    let as_ref = key.as_ref();
+    let mut v = vec![NODE_STORAGE_PREFIX];
+    push_node_key(&mut v, key.as_ref());
 
-    let mut v = vec![0; as_ref.len() + 1];
-    v[0] = NODE_STORAGE_PREFIX;
-    v[1..].copy_from_slice(as_ref);
+    v
+}
+
+/// Returns the storage key bounds of the nodes with keys within the (`start`; `end`) bounds.
+pub fn node_storage_key_bounds<K: AsRef<[u8]>>(
+    start: Bound<&K>,
+    end: Bound<&K>,
+) -> (Bound<Vec<u8>>, Bound<Vec<u8>>) {
+    let start = match start {
+        Bound::Unbounded => Bound::Included(vec![NODE_STORAGE_PREFIX]),
+        start => start.map(node_storage_key),
+    };
+
+    let end = match end {
+        Bound::Unbounded => Bound::Excluded(vec![NODE_STORAGE_PREFIX + 1]),
+        end => end.map(node_storage_key),
+    };
+
+    (start, end)
+}
+
+/// Returns the node key storage key (indexed by the node index) to use for persistence.
+pub fn node_key_storage_key(index: u64) -> Vec<u8> {
+    let mut v = vec![0; 1 + 8];
+    v[0] = NODE_KEY_STORAGE_PREFIX;
+    v[1..].copy_from_slice(&index.to_be_bytes());
 
     v
 }
 
+/// Returns the storage key bounds of the node keys with indices within the (`start`; `end`) bounds.
+pub fn node_key_storage_key_bounds(
+    start: Bound<u64>,
+    end: Bound<u64>,
+) -> (Bound<Vec<u8>>, Bound<Vec<u8>>) {
+    let start = match start {
+        Bound::Unbounded => Bound::Included(vec![NODE_KEY_STORAGE_PREFIX]),
+        start => start.map(node_key_storage_key),
+    };
+
+    let end = match end {
+        Bound::Unbounded => Bound::Excluded(vec![NODE_KEY_STORAGE_PREFIX + 1]),
+        end => end.map(node_key_storage_key),
+    };
+
+    (start, end)
+}
+
 /// Returns the hash storage key to use for persistence.
 pub fn hash_storage_key(level: u8, index: u64) -> Vec<u8> {
     let mut v = vec![0; 1 + 1 + 8];
@@ -41,3 +220,205 @@ pub fn size_storage_key() -> Vec<u8> {
 pub fn root_storage_key() -> Vec<u8> {
     vec![ROOT_STORAGE_PREFIX]
 }
+
+/// Returns the version storage key to use for persistence.
+pub fn version_storage_key() -> Vec<u8> {
+    vec![VERSION_STORAGE_PREFIX]
+}
+
+/// Returns the versioned node storage key to use for persistence.
+pub fn versioned_node_storage_key(key: impl AsRef<[u8]>, version: u64) -> Vec<u8> {
+    let mut v = vec![VERSIONED_NODE_STORAGE_PREFIX];
+    push_node_key(&mut v, key.as_ref());
+    v.extend_from_slice(&version.to_be_bytes());
+
+    v
+}
+
+/// Returns the node key and the version of the given versioned node storage key, or `None` if
+/// `storage_key` is not a versioned node storage key.
+pub fn split_versioned_node_storage_key(storage_key: &[u8]) -> Option<(Vec<u8>, u64)> {
+    let (prefix, encoded) = storage_key.split_first()?;
+    if *prefix != VERSIONED_NODE_STORAGE_PREFIX {
+        return None;
+    }
+
+    let mut key = vec![];
+    let mut bytes = encoded.iter();
+    loop {
+        match (bytes.next()?, bytes.as_slice().first()) {
+            (0x00, Some(&NODE_KEY_ESCAPE)) => {
+                key.push(0x00);
+                bytes.next();
+            }
+            (0x00, Some(0x00)) => {
+                bytes.next();
+                break;
+            }
+            (0x00, _) => return None,
+            (byte, _) => key.push(*byte),
+        }
+    }
+
+    let version = bytes.as_slice().try_into().ok().map(u64::from_be_bytes)?;
+    Some((key, version))
+}
+
+/// Appends the given node `key` to `v`, escaping its `0x00` bytes and terminating it.
+///
+/// NOTE: The terminator is lower than any escaped or regular byte, so a key is ordered before all
+///       the keys it is a prefix of, and the encoding preserves the lexicographical order.
+fn push_node_key(v: &mut Vec<u8>, key: &[u8]) {
+    v.reserve(key.len() + NODE_KEY_TERMINATOR.len());
+    for byte in key {
+        v.push(*byte);
+        if *byte == 0x00 {
+            v.push(NODE_KEY_ESCAPE);
+        }
+    }
+
+    v.extend_from_slice(&NODE_KEY_TERMINATOR);
+}
+
+/// Returns the versioned hash storage key to use for persistence.
+pub fn versioned_hash_storage_key(level: u8, index: u64, version: u64) -> Vec<u8> {
+    let mut v = vec![0; 1 + 1 + 8 + VERSION_SUFFIX_LEN];
+    v[0] = VERSIONED_HASH_STORAGE_PREFIX;
+    v[1] = level;
+    v[2..10].copy_from_slice(&index.to_be_bytes());
+    v[10..].copy_from_slice(&version.to_be_bytes());
+
+    v
+}
+
+/// Returns the (level; index; version) of the given versioned hash storage key, or `None` if
+/// `storage_key` is not a versioned hash storage key.
+pub fn split_versioned_hash_storage_key(storage_key: &[u8]) -> Option<(u8, u64, u64)> {
+    let [VERSIONED_HASH_STORAGE_PREFIX, level, rest @ ..] = storage_key else {
+        return None;
+    };
+
+    if rest.len() != 8 + VERSION_SUFFIX_LEN {
+        return None;
+    }
+
+    let (index, version) = rest.split_at(8);
+    Some((
+        *level,
+        u64::from_be_bytes(index.try_into().ok()?),
+        u64::from_be_bytes(version.try_into().ok()?),
+    ))
+}
+
+/// Returns the versioned size storage key to use for persistence.
+pub fn versioned_size_storage_key(version: u64) -> Vec<u8> {
+    let mut v = vec![0; 1 + VERSION_SUFFIX_LEN];
+    v[0] = VERSIONED_SIZE_STORAGE_PREFIX;
+    v[1..].copy_from_slice(&version.to_be_bytes());
+
+    v
+}
+
+/// Returns the versioned root storage key to use for persistence.
+pub fn versioned_root_storage_key(version: u64) -> Vec<u8> {
+    let mut v = vec![0; 1 + VERSION_SUFFIX_LEN];
+    v[0] = VERSIONED_ROOT_STORAGE_PREFIX;
+    v[1..].copy_from_slice(&version.to_be_bytes());
+
+    v
+}
+
+/// Returns the root version storage key to use for persistence.
+pub fn root_version_storage_key(root: impl AsRef<[u8]>) -> Vec<u8> {
+    let as_ref = root.as_ref();
+
+    let mut v = vec![0; as_ref.len() + 1];
+    v[0] = ROOT_VERSION_STORAGE_PREFIX;
+    v[1..].copy_from_slice(as_ref);
+
+    v
+}
+
+/// Returns the prefix shared by the change storage keys of the given `version`.
+pub fn version_changes_storage_prefix(version: u64) -> Vec<u8> {
+    let mut v = vec![0; 1 + VERSION_SUFFIX_LEN];
+    v[0] = VERSION_CHANGE_STORAGE_PREFIX;
+    v[1..].copy_from_slice(&version.to_be_bytes());
+
+    v
+}
+
+/// Returns the storage key registering that `versioned_key` was written at the given `version`.
+pub fn version_change_storage_key(version: u64, versioned_key: &[u8]) -> Vec<u8> {
+    let mut v = version_changes_storage_prefix(version);
+    v.extend_from_slice(versioned_key);
+
+    v
+}
+
+/// Returns the version and the versioned storage key of the given version change storage key, or
+/// `None` if `storage_key` is not a version change storage key.
+pub fn split_version_change_storage_key(storage_key: &[u8]) -> Option<(u64, &[u8])> {
+    let [VERSION_CHANGE_STORAGE_PREFIX, rest @ ..] = storage_key else {
+        return None;
+    };
+
+    if rest.len() < VERSION_SUFFIX_LEN {
+        return None;
+    }
+
+    let (version, versioned_key) = rest.split_at(VERSION_SUFFIX_LEN);
+    Some((u64::from_be_bytes(version.try_into().ok()?), versioned_key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_node_storage_key_order() {
+        let keys: [&[u8]; 6] = [b"", b"\x00", b"\x00\x00", b"\x00\x01", b"\x01", b"\x01\x00"];
+
+        // Ensure the encoding preserves the keys order and the versions stay grouped by key.
+        for (a, b) in keys.iter().zip(&keys[1..]) {
+            assert!(node_storage_key(a) < node_storage_key(b));
+            assert!(versioned_node_storage_key(a, u64::MAX) < versioned_node_storage_key(b, 0));
+        }
+
+        for key in keys {
+            assert_eq!(
+                split_versioned_node_storage_key(&versioned_node_storage_key(key, 42)),
+                Some((key.to_vec(), 42))
+            );
+        }
+    }
+
+    #[test]
+    fn test_storage_column_split() {
+        // Ensure the prefixed storage keys are split from their prefix and joined back.
+        for storage_key in [
+            node_storage_key([1, 2]),
+            node_key_storage_key(3),
+            hash_storage_key(4, 5),
+            versioned_hash_storage_key(4, 5, 6),
+            version_change_storage_key(7, &versioned_size_storage_key(7)),
+        ] {
+            let (column, key) = StorageColumn::split(&storage_key);
+            assert_ne!(column, StorageColumn::Metadata);
+            assert_eq!(key, &storage_key[1..]);
+            assert_eq!(column.join(key), storage_key);
+        }
+
+        // Ensure the single entries are persisted whole in the metadata column.
+        for storage_key in [
+            size_storage_key(),
+            root_storage_key(),
+            version_storage_key(),
+        ] {
+            assert_eq!(
+                StorageColumn::split(&storage_key),
+                (StorageColumn::Metadata, &storage_key[..])
+            );
+        }
+    }
+}
@@ -1,3 +1,704 @@
-mod btree;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{marker::PhantomData, ops::Bound};
+
+use super::{StorageError, StorageReader, StorageResult, StorageWriter};
+use codec::{decode_hash, decode_node, decode_node_key, decode_u64, encode_node};
+use keys::{
+    hash_storage_key, node_key_storage_key, node_key_storage_key_bounds, node_storage_key,
+    node_storage_key_bounds, root_storage_key, root_version_storage_key, size_storage_key,
+    version_change_storage_key, version_storage_key, versioned_hash_storage_key,
+    versioned_node_storage_key, versioned_root_storage_key, versioned_size_storage_key,
+};
+use keyspace_imt::{
+    node::ImtNode,
+    storage::{
+        versioned::{VersionedImtStorageReader, VersionedImtStorageWriter},
+        ImtStorageReader, ImtStorageWriter, StoredNodes,
+    },
+    Hash256, NodeKey, NodeValue,
+};
+
+mod codec;
 mod keys;
-mod sled;
+mod versioned;
+
+pub use keys::StorageColumn;
+
+/// The imt node keys that can be persisted by an [ImtStorage].
+///
+/// The keys are persisted as their raw bytes ([AsRef<\[u8\]>]), whose lexicographical order MUST
+/// match the keys order.
+pub trait StorageNodeKey:
+    NodeKey + Serialize + DeserializeOwned + for<'a> TryFrom<&'a [u8]>
+{
+}
+
+impl<T> StorageNodeKey for T where
+    T: NodeKey + Serialize + DeserializeOwned + for<'a> TryFrom<&'a [u8]>
+{
+}
+
+/// The imt node values that can be persisted by an [ImtStorage].
+pub trait StorageNodeValue: NodeValue + Serialize + DeserializeOwned {}
+
+impl<T> StorageNodeValue for T where T: NodeValue + Serialize + DeserializeOwned {}
+
+/// An imt storage persisting its `NodeK` keys and `NodeV` values in a raw (`Vec<u8>`; `Vec<u8>`)
+/// storage, e.g. a [super::btree::BTreeStorage] or one of its transactions.
+#[derive(Debug)]
+pub struct ImtStorage<S, NodeK, NodeV> {
+    storage: S,
+
+    /// The version of the writes, read once from the `storage` (or set by
+    /// [VersionedImtStorageWriter::set_version]) and cached for the lifetime of the [ImtStorage].
+    version: Option<u64>,
+
+    _phantom_data_k: PhantomData<NodeK>,
+    _phantom_data_v: PhantomData<NodeV>,
+}
+
+impl<S, NodeK, NodeV> ImtStorage<S, NodeK, NodeV> {
+    /// Creates a new [ImtStorage] over the given raw `storage`.
+    pub fn new(storage: S) -> Self {
+        Self {
+            storage,
+            version: None,
+            _phantom_data_k: PhantomData,
+            _phantom_data_v: PhantomData,
+        }
+    }
+
+    /// Consumes the [ImtStorage] and returns the underlying raw storage.
+    pub fn into_inner(self) -> S {
+        self.storage
+    }
+}
+
+impl<S, NodeK, NodeV> ImtStorageReader for ImtStorage<S, NodeK, NodeV>
+where
+    S: StorageReader<StorageKey = Vec<u8>, StorageValue = Vec<u8>>,
+    NodeK: StorageNodeKey,
+    NodeV: StorageNodeValue,
+{
+    type NodeK = NodeK;
+    type NodeV = NodeV;
+    type Error = StorageError;
+
+    fn get_node(&self, key: &NodeK) -> StorageResult<Option<ImtNode<NodeK, NodeV>>> {
+        self.storage
+            .get(&node_storage_key(key))?
+            .map(|v| decode_node(v.as_ref()))
+            .transpose()
+    }
+
+    fn get_ln_node(&self, key: &NodeK) -> StorageResult<Option<ImtNode<NodeK, NodeV>>> {
+        self.storage
+            .get_lt(&node_storage_key(key))?
+            .map(|(_k, v)| decode_node(v.as_ref()))
+            .transpose()
+    }
+
+    fn get_node_key(&self, index: u64) -> StorageResult<Option<NodeK>> {
+        self.storage
+            .get(&node_key_storage_key(index))?
+            .map(|v| decode_node_key(&v))
+            .transpose()
+    }
+
+    fn get_nodes_by_key<'a>(
+        &'a self,
+        start: Bound<&NodeK>,
+        end: Bound<&NodeK>,
+    ) -> StoredNodes<'a, Self> {
+        let (start, end) = node_storage_key_bounds(start, end);
+        Box::new(
+            self.storage
+                .range(start.as_ref(), end.as_ref())
+                .map(|entry| decode_node(&entry?.1)),
+        )
+    }
+
+    fn get_nodes_by_index(&self, start: Bound<u64>, end: Bound<u64>) -> StoredNodes<'_, Self> {
+        let (start, end) = node_key_storage_key_bounds(start, end);
+        Box::new(
+            self.storage
+                .range(start.as_ref(), end.as_ref())
+                .map(|entry| {
+                    let (storage_key, key) = entry?;
+                    let node = self.get_node(&decode_node_key(&key)?)?;
+
+                    // Skip the indices whose node has been removed (or re-inserted at a new index).
+                    Ok(node.filter(|node| node_key_storage_key(node.index) == storage_key))
+                })
+                .filter_map(Result::transpose),
+        )
+    }
+
+    fn get_hash(&self, level: u8, index: u64) -> StorageResult<Option<Hash256>> {
+        self.storage
+            .get(&hash_storage_key(level, index))?
+            .map(|v| decode_hash("hash", v))
+            .transpose()
+    }
+
+    fn get_size(&self) -> StorageResult<Option<u64>> {
+        self.storage
+            .get(&size_storage_key())?
+            .map(|v| decode_u64("size", v))
+            .transpose()
+    }
+
+    fn get_root(&self) -> StorageResult<Option<Hash256>> {
+        self.storage
+            .get(&root_storage_key())?
+            .map(|v| decode_hash("root", v))
+            .transpose()
+    }
+}
+
+impl<S, NodeK, NodeV> ImtStorageWriter for ImtStorage<S, NodeK, NodeV>
+where
+    S: StorageWriter<StorageKey = Vec<u8>, StorageValue = Vec<u8>>,
+    NodeK: StorageNodeKey,
+    NodeV: StorageNodeValue,
+{
+    fn set_node(&mut self, node: ImtNode<NodeK, NodeV>) -> StorageResult<()> {
+        let version = self.write_version()?;
+        self.set_versioned(
+            version,
+            versioned_node_storage_key(&node.key, version),
+            encode_node(&Some(&node))?,
+        )?;
+
+        // NOTE: Indices are never reused so the node keys are not versioned.
+        self.storage
+            .set(node_key_storage_key(node.index), node.key.as_ref().to_vec())?;
+
+        self.storage
+            .set(node_storage_key(&node.key), encode_node(&node)?)
+    }
+
+    fn remove_node(&mut self, key: &NodeK) -> StorageResult<()> {
+        let version = self.write_version()?;
+        self.set_versioned(
+            version,
+            versioned_node_storage_key(key, version),
+            encode_node(&None::<ImtNode<NodeK, NodeV>>)?,
+        )?;
+
+        self.storage.remove(&node_storage_key(key))
+    }
+
+    fn set_hash(&mut self, level: u8, index: u64, hash: Hash256) -> StorageResult<()> {
+        let version = self.write_version()?;
+        self.set_versioned(
+            version,
+            versioned_hash_storage_key(level, index, version),
+            hash.to_vec(),
+        )?;
+
+        self.storage
+            .set(hash_storage_key(level, index), hash.to_vec())
+    }
+
+    fn set_size(&mut self, size: u64) -> StorageResult<()> {
+        let version = self.write_version()?;
+        self.storage.set(
+            versioned_size_storage_key(version),
+            size.to_le_bytes().to_vec(),
+        )?;
+
+        self.storage
+            .set(size_storage_key(), size.to_le_bytes().to_vec())
+    }
+
+    fn set_root(&mut self, root: Hash256) -> StorageResult<()> {
+        let version = self.write_version()?;
+        self.storage
+            .set(versioned_root_storage_key(version), root.to_vec())?;
+        self.set_versioned(
+            version,
+            root_version_storage_key(root),
+            version.to_le_bytes().to_vec(),
+        )?;
+
+        self.storage.set(root_storage_key(), root.to_vec())
+    }
+}
+
+impl<S, NodeK, NodeV> VersionedImtStorageReader for ImtStorage<S, NodeK, NodeV>
+where
+    S: StorageReader<StorageKey = Vec<u8>, StorageValue = Vec<u8>>,
+    NodeK: StorageNodeKey,
+    NodeV: StorageNodeValue,
+{
+    fn get_version(&self) -> StorageResult<Option<u64>> {
+        versioned::get_version(&self.storage)
+    }
+
+    fn get_root_version(&self, root: &Hash256) -> StorageResult<Option<u64>> {
+        versioned::get_root_version(&self.storage, root)
+    }
+
+    fn get_node_at(
+        &self,
+        version: u64,
+        key: &NodeK,
+    ) -> StorageResult<Option<ImtNode<NodeK, NodeV>>> {
+        versioned::get_node_at(&self.storage, version, key)
+    }
+
+    fn get_ln_node_at(
+        &self,
+        version: u64,
+        key: &NodeK,
+    ) -> StorageResult<Option<ImtNode<NodeK, NodeV>>> {
+        versioned::get_ln_node_at(&self.storage, version, key)
+    }
+
+    fn get_hash_at(&self, version: u64, level: u8, index: u64) -> StorageResult<Option<Hash256>> {
+        versioned::get_hash_at(&self.storage, version, level, index)
+    }
+
+    fn get_size_at(&self, version: u64) -> StorageResult<Option<u64>> {
+        versioned::get_size_at(&self.storage, version)
+    }
+
+    fn get_root_at(&self, version: u64) -> StorageResult<Option<Hash256>> {
+        versioned::get_root_at(&self.storage, version)
+    }
+}
+
+impl<S, NodeK, NodeV> VersionedImtStorageWriter for ImtStorage<S, NodeK, NodeV>
+where
+    S: StorageWriter<StorageKey = Vec<u8>, StorageValue = Vec<u8>>,
+    NodeK: StorageNodeKey,
+    NodeV: StorageNodeValue,
+{
+    fn set_version(&mut self, version: u64) -> StorageResult<()> {
+        self.storage
+            .set(version_storage_key(), version.to_le_bytes().to_vec())?;
+        self.version = Some(version);
+
+        Ok(())
+    }
+
+    fn rollback(&mut self, version: Option<u64>) -> StorageResult<()> {
+        versioned::rollback::<_, NodeK, NodeV>(&mut self.storage, version)?;
+        self.version = version;
+
+        Ok(())
+    }
+}
+
+impl<S, NodeK, NodeV> ImtStorage<S, NodeK, NodeV>
+where
+    S: StorageWriter<StorageKey = Vec<u8>, StorageValue = Vec<u8>>,
+{
+    /// Discards the history of the versions before the given `version`, so that the imt can only be
+    /// read at (see [Imt::at_version]) or rolled back to `version` and the later versions.
+    ///
+    /// [Imt::at_version]: keyspace_imt::tree::Imt::at_version
+    pub fn prune(&mut self, version: u64) -> StorageResult<()> {
+        versioned::prune(&mut self.storage, version)
+    }
+
+    /// Returns the version under which the writes are registered, `0` if none was set yet.
+    fn write_version(&mut self) -> StorageResult<u64> {
+        if let Some(version) = self.version {
+            return Ok(version);
+        }
+
+        let version = versioned::get_version(&self.storage)?.unwrap_or_default();
+        self.version = Some(version);
+
+        Ok(version)
+    }
+
+    /// Sets the (`versioned_key`; `value`) pair and registers it as a change of the given `version`
+    /// (see [VersionedImtStorageWriter::rollback]).
+    fn set_versioned(
+        &mut self,
+        version: u64,
+        versioned_key: Vec<u8>,
+        value: Vec<u8>,
+    ) -> StorageResult<()> {
+        self.storage
+            .set(version_change_storage_key(version, &versioned_key), vec![])?;
+        self.storage.set(versioned_key, value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use keyspace_imt::{
+        proof::node::NodeProof,
+        storage::versioned::{VersionedImtStorageReader, VersionedImtStorageWriter},
+        tree::{Imt, ImtError},
+    };
+    use std::thread;
+    use tiny_keccak::Keccak;
+
+    use super::{keys::node_storage_key, *};
+    use crate::storage::{
+        btree::BTreeStorage, SnapshotStorage, StorageWriter, Transaction, TransactionalStorage,
+    };
+
+    /// Returns an [ImtStorage] of (`[u8; 32]`; `[u8; 32]`) nodes over the given raw `storage`.
+    fn imt_storage<S>(storage: S) -> ImtStorage<S, [u8; 32], [u8; 32]> {
+        ImtStorage::new(storage)
+    }
+
+    #[test]
+    fn test_versioned_reads() {
+        let mut storage = BTreeStorage::default();
+
+        // Write a first version of the imt.
+        let mut tx = storage.transaction();
+        let mut imt_tx = imt_storage(&mut tx);
+        imt_tx.set_version(0).expect("set_version failed");
+        let mut imt = Imt::writer(Keccak::v256, imt_tx).expect("imt writer failed");
+        imt.set_nodes([([1; 32], [42; 32]), ([3; 32], [42; 32])])
+            .expect("set_nodes failed");
+        let old_root = imt.root().expect("root failed");
+        tx.commit().expect("commit failed");
+
+        // Write a second version of the imt.
+        let mut tx = storage.transaction();
+        let mut imt_tx = imt_storage(&mut tx);
+        imt_tx.set_version(1).expect("set_version failed");
+        let mut imt = Imt::writer(Keccak::v256, imt_tx).expect("imt writer failed");
+        imt.set_nodes([([1; 32], [43; 32]), ([2; 32], [42; 32])])
+            .expect("set_nodes failed");
+        imt.delete_node([3; 32]).expect("delete [3] failed");
+        let new_root = imt.root().expect("root failed");
+        tx.commit().expect("commit failed");
+
+        // Ensure the old version is still readable.
+        let reader = imt_storage(&storage);
+        assert_eq!(
+            reader
+                .get_root_version(&old_root)
+                .expect("get_root_version failed"),
+            Some(0)
+        );
+        assert_eq!(
+            reader.get_root_at(0).expect("get_root_at failed"),
+            Some(old_root)
+        );
+        assert_eq!(reader.get_size_at(0).expect("get_size_at failed"), Some(3));
+        assert_eq!(
+            reader
+                .get_node_at(0, &[1; 32])
+                .expect("get_node_at failed")
+                .map(|n| n.value),
+            Some([42; 32])
+        );
+        assert_eq!(
+            reader.get_node_at(0, &[2; 32]).expect("get_node_at failed"),
+            None
+        );
+        assert_eq!(
+            reader
+                .get_ln_node_at(0, &[2; 32])
+                .expect("get_ln_node_at failed")
+                .map(|n| n.key),
+            Some([1; 32])
+        );
+        assert_eq!(
+            reader
+                .get_ln_node_at(0, &[4; 32])
+                .expect("get_ln_node_at failed")
+                .map(|n| n.key),
+            Some([3; 32])
+        );
+
+        // Ensure the new version is readable.
+        assert_eq!(
+            reader
+                .get_root_version(&new_root)
+                .expect("get_root_version failed"),
+            Some(1)
+        );
+        assert_eq!(
+            reader.get_root_at(1).expect("get_root_at failed"),
+            Some(new_root)
+        );
+        assert_eq!(
+            reader
+                .get_node_at(1, &[1; 32])
+                .expect("get_node_at failed")
+                .map(|n| n.value),
+            Some([43; 32])
+        );
+        assert_eq!(
+            reader.get_node_at(1, &[3; 32]).expect("get_node_at failed"),
+            None
+        );
+        assert_eq!(
+            reader
+                .get_ln_node_at(1, &[4; 32])
+                .expect("get_ln_node_at failed")
+                .map(|n| n.key),
+            Some([2; 32])
+        );
+
+        // Ensure proofs can be generated against the old root.
+        let imt = Imt::reader(Keccak::v256, reader).expect("imt reader failed");
+        let sut = imt.at_root(&old_root).expect("old root not found");
+        let proof = sut
+            .exclusion_proof([2; 32])
+            .expect("exclusion proof failed");
+        assert!(proof.verify(Keccak::v256).is_ok());
+        let proof = sut
+            .inclusion_proof([3; 32])
+            .expect("inclusion proof failed");
+        assert!(proof.verify(Keccak::v256).is_ok());
+
+        // Ensure the nodes can be iterated at the old and new roots.
+        let keys = sut
+            .nodes_by_key(..)
+            .map(|node| node.map(|node| node.key))
+            .collect::<Result<Vec<_>, _>>()
+            .expect("nodes_by_key failed");
+        assert_eq!(keys, [[0; 32], [1; 32], [3; 32]]);
+
+        let sut = imt.at_root(&new_root).expect("new root not found");
+        let keys = sut
+            .nodes_by_index(..)
+            .map(|node| node.map(|node| node.key))
+            .collect::<Result<Vec<_>, _>>()
+            .expect("nodes_by_index failed");
+        assert_eq!(keys, [[0; 32], [1; 32], [2; 32]]);
+    }
+
+    #[test]
+    fn test_rollback() {
+        let mut storage = BTreeStorage::default();
+        let empty_storage = bincode::serialize(&storage).expect("serialize failed");
+
+        // Write a first version of the imt.
+        let mut tx = storage.transaction();
+        let mut imt_tx = imt_storage(&mut tx);
+        imt_tx.set_version(0).expect("set_version failed");
+        let mut imt = Imt::writer(Keccak::v256, imt_tx).expect("imt writer failed");
+        imt.set_nodes([([1; 32], [42; 32]), ([3; 32], [42; 32])])
+            .expect("set_nodes failed");
+        let old_root = imt.root().expect("root failed");
+        tx.commit().expect("commit failed");
+        let old_storage = bincode::serialize(&storage).expect("serialize failed");
+
+        // Write two more versions of the imt.
+        for (version, key) in [(1, [2; 32]), (2, [4; 32])] {
+            let mut tx = storage.transaction();
+            let mut imt_tx = imt_storage(&mut tx);
+            imt_tx.set_version(version).expect("set_version failed");
+            let mut imt = Imt::writer(Keccak::v256, imt_tx).expect("imt writer failed");
+            imt.set_nodes([([1; 32], [43; 32]), (key, [42; 32])])
+                .expect("set_nodes failed");
+            imt.delete_node(key).expect("delete failed");
+            tx.commit().expect("commit failed");
+        }
+
+        // Rollback to the first version and ensure the storage is restored as it was.
+        let mut tx = storage.transaction();
+        imt_storage(&mut tx)
+            .rollback(Some(0))
+            .expect("rollback failed");
+        tx.commit().expect("commit failed");
+        assert_eq!(
+            bincode::serialize(&storage).expect("serialize failed"),
+            old_storage
+        );
+
+        let sut = Imt::reader(Keccak::v256, imt_storage(&storage)).expect("imt reader failed");
+        assert_eq!(sut.root().expect("root failed"), old_root);
+        sut.verify_integrity().expect("verify integrity failed");
+
+        // Rollback all the versions and ensure the storage is emptied.
+        imt_storage(&mut storage)
+            .rollback(None)
+            .expect("rollback failed");
+        assert_eq!(
+            bincode::serialize(&storage).expect("serialize failed"),
+            empty_storage
+        );
+    }
+
+    #[test]
+    fn test_prune() {
+        let mut storage = BTreeStorage::default();
+
+        // Write a few versions of the imt, the third one being empty.
+        let batches = [
+            vec![([1; 32], [42; 32]), ([3; 32], [42; 32])],
+            vec![([1; 32], [43; 32]), ([2; 32], [42; 32])],
+            vec![],
+            vec![([1; 32], [44; 32]), ([4; 32], [42; 32])],
+        ];
+        for (version, nodes) in batches.into_iter().enumerate() {
+            let mut tx = storage.transaction();
+            let mut imt_tx = imt_storage(&mut tx);
+            imt_tx
+                .set_version(version as u64)
+                .expect("set_version failed");
+            let mut imt = Imt::writer(Keccak::v256, imt_tx).expect("imt writer failed");
+            imt.set_nodes(nodes).expect("set_nodes failed");
+            tx.commit().expect("commit failed");
+        }
+
+        let reads = |storage: &BTreeStorage<Vec<u8>, Vec<u8>>, version| {
+            let imt = Imt::reader(Keccak::v256, imt_storage(storage)).expect("imt reader failed");
+            let sut = imt.at_version(version);
+            let nodes = sut
+                .nodes_by_key(..)
+                .map(|node| node.map(|node| (node.key[0], node.value[0])))
+                .collect::<Result<Vec<_>, _>>()
+                .expect("nodes_by_key failed");
+            (sut.root().expect("root failed"), nodes)
+        };
+        let old_root = reads(&storage, 0).0;
+        let expected_reads = [reads(&storage, 2), reads(&storage, 3)];
+        let entries = storage.range(Bound::Unbounded, Bound::Unbounded).count();
+
+        // Prune the versions before the third one.
+        let mut tx = storage.transaction();
+        imt_storage(&mut tx).prune(2).expect("prune failed");
+        tx.commit().expect("commit failed");
+        assert!(storage.range(Bound::Unbounded, Bound::Unbounded).count() < entries);
+
+        // Ensure the retained versions are still readable, but not the pruned ones.
+        assert_eq!([reads(&storage, 2), reads(&storage, 3)], expected_reads);
+
+        let imt = Imt::reader(Keccak::v256, imt_storage(&storage)).expect("imt reader failed");
+        let res = imt.at_root(&old_root);
+        assert!(matches!(res, Err(ImtError::RootNotFound(_))));
+        let sut = imt
+            .at_root(&expected_reads[0].0)
+            .expect("retained root not found");
+        assert_eq!(sut.root().expect("root failed"), expected_reads[0].0);
+
+        // Ensure the retained versions can still be rolled back to.
+        imt_storage(&mut storage)
+            .rollback(Some(2))
+            .expect("rollback failed");
+        let sut = Imt::reader(Keccak::v256, imt_storage(&storage)).expect("imt reader failed");
+        assert_eq!(sut.root().expect("root failed"), expected_reads[0].0);
+        sut.verify_integrity().expect("verify integrity failed");
+    }
+
+    #[test]
+    fn test_corrupted_node() {
+        let mut storage = BTreeStorage::default();
+        let mut imt =
+            Imt::writer(Keccak::v256, imt_storage(&mut storage)).expect("imt writer failed");
+        imt.insert_node([1; 32], [42; 32])
+            .expect("insert [1] failed");
+
+        // Corrupt the stored node and ensure reading it errors instead of panicking.
+        storage
+            .set(node_storage_key([1; 32]), vec![0xff])
+            .expect("set failed");
+
+        let imt = Imt::reader(Keccak::v256, imt_storage(&storage)).expect("imt reader failed");
+        let res = imt.inclusion_proof([1; 32]);
+        assert!(matches!(res, Err(ImtError::Storage(_))));
+    }
+
+    #[test]
+    fn test_snapshot_reads() {
+        // Instanciate an imt with a few nodes.
+        let mut storage = BTreeStorage::default();
+        let mut imt =
+            Imt::writer(Keccak::v256, imt_storage(&mut storage)).expect("imt writer failed");
+        imt.set_nodes([([1; 32], [42; 32]), ([3; 32], [42; 32])])
+            .expect("set_nodes failed");
+        let old_root = imt.root().expect("root failed");
+
+        let snapshot = storage.snapshot();
+
+        // Generate a proof from the snapshot while a transaction mutates the imt.
+        let proof = thread::scope(|s| {
+            let reader = s.spawn(|| {
+                let sut =
+                    Imt::reader(Keccak::v256, imt_storage(&snapshot)).expect("imt reader failed");
+                sut.node_proof([1; 32]).expect("node proof failed")
+            });
+
+            let mut tx = storage.transaction();
+            let mut imt =
+                Imt::writer(Keccak::v256, imt_storage(&mut tx)).expect("imt writer failed");
+            imt.update_node([1; 32], [43; 32])
+                .expect("update [1] failed");
+            imt.insert_node([2; 32], [42; 32])
+                .expect("insert [2] failed");
+            tx.commit().expect("commit failed");
+
+            reader.join().expect("reader panicked")
+        });
+
+        // Ensure the proof is against the root commited before the snapshot was taken.
+        let NodeProof::Inclusion(proof) = proof else {
+            panic!("expected an inclusion proof");
+        };
+        assert_eq!(proof.root, old_root);
+        assert_eq!(proof.node.value, [42; 32]);
+        proof.verify(Keccak::v256).expect("verify failed");
+
+        let sut = Imt::reader(Keccak::v256, imt_storage(&snapshot)).expect("imt reader failed");
+        assert_eq!(sut.root().expect("root failed"), old_root);
+        let imt = Imt::reader(Keccak::v256, imt_storage(&storage)).expect("imt reader failed");
+        assert_ne!(imt.root().expect("root failed"), old_root);
+    }
+
+    #[test]
+    fn test_variable_width_keys() {
+        let mut storage = BTreeStorage::default();
+
+        // Write a first version of the imt with keys that are prefixes of each other.
+        let mut tx = storage.transaction();
+        let mut imt_tx = ImtStorage::<_, Vec<u8>, Vec<u8>>::new(&mut tx);
+        imt_tx.set_version(0).expect("set_version failed");
+        let mut imt = Imt::writer(Keccak::v256, imt_tx).expect("imt writer failed");
+        imt.set_nodes([(vec![1], vec![42]), (vec![1, 0, 0], vec![42; 64])])
+            .expect("set_nodes failed");
+        let old_root = imt.root().expect("root failed");
+        tx.commit().expect("commit failed");
+
+        // Write a second version of the imt.
+        let mut tx = storage.transaction();
+        let mut imt_tx = ImtStorage::<_, Vec<u8>, Vec<u8>>::new(&mut tx);
+        imt_tx.set_version(1).expect("set_version failed");
+        let mut imt = Imt::writer(Keccak::v256, imt_tx).expect("imt writer failed");
+        imt.insert_node(vec![1, 0], vec![43])
+            .expect("insert [1, 0] failed");
+        imt.delete_node(vec![1, 0, 0])
+            .expect("delete [1, 0, 0] failed");
+        tx.commit().expect("commit failed");
+
+        // Ensure the low nullifiers are found at both versions.
+        let sut = ImtStorage::<_, Vec<u8>, Vec<u8>>::new(&storage);
+        let ln_key = |version, key: &[u8]| {
+            sut.get_ln_node_at(version, &key.to_vec())
+                .expect("get_ln_node_at failed")
+                .map(|n| n.key)
+        };
+        assert_eq!(ln_key(0, &[1, 0]), Some(vec![1]));
+        assert_eq!(ln_key(0, &[1, 0, 0, 1]), Some(vec![1, 0, 0]));
+        assert_eq!(ln_key(1, &[1, 0, 0]), Some(vec![1, 0]));
+        assert_eq!(ln_key(1, &[1, 0, 0, 1]), Some(vec![1, 0]));
+
+        // Ensure proofs can be generated against the old root.
+        let imt = Imt::reader(Keccak::v256, sut).expect("imt reader failed");
+        let sut = imt.at_root(&old_root).expect("old root not found");
+        let proof = sut
+            .exclusion_proof(vec![1, 0])
+            .expect("exclusion proof failed");
+        assert!(proof.verify(Keccak::v256).is_ok());
+        let proof = sut
+            .inclusion_proof(vec![1, 0, 0])
+            .expect("inclusion proof failed");
+        assert_eq!(proof.node.value, vec![42; 64]);
+        assert!(proof.verify(Keccak::v256).is_ok());
+    }
+}
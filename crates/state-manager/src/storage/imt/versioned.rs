@@ -0,0 +1,332 @@
+use super::{
+    codec::{decode_hash, decode_node, decode_node_key, decode_u64, encode_node},
+    keys::{
+        hash_storage_key, node_key_storage_key, node_storage_key, root_storage_key,
+        root_version_storage_key, size_storage_key, split_version_change_storage_key,
+        split_versioned_hash_storage_key, split_versioned_node_storage_key,
+        version_changes_storage_prefix, version_storage_key, versioned_hash_storage_key,
+        versioned_node_storage_key, versioned_root_storage_key, versioned_size_storage_key,
+        StorageColumn, VERSION_SUFFIX_LEN,
+    },
+    StorageNodeKey, StorageNodeValue,
+};
+use crate::storage::{StorageError, StorageReader, StorageResult, StorageWriter};
+use keyspace_imt::{node::ImtNode, Hash256};
+
+// Helpers shared by the storages implementing the versioned imt storage traits on top of
+// a (`Vec<u8>`; `Vec<u8>`) [StorageReader].
+
+/// Returns the current imt version.
+pub(super) fn get_version<S>(storage: &S) -> StorageResult<Option<u64>>
+where
+    S: StorageReader<StorageKey = Vec<u8>, StorageValue = Vec<u8>>,
+{
+    storage
+        .get(&version_storage_key())?
+        .map(|v| decode_u64("version", v))
+        .transpose()
+}
+
+/// Returns the version at which the given imt `root` was registered.
+pub(super) fn get_root_version<S>(storage: &S, root: &Hash256) -> StorageResult<Option<u64>>
+where
+    S: StorageReader<StorageKey = Vec<u8>, StorageValue = Vec<u8>>,
+{
+    storage
+        .get(&root_version_storage_key(root))?
+        .map(|v| decode_u64("version", v))
+        .transpose()
+}
+
+/// Returns the [ImtNode] for the given `key` at the given `version`.
+pub(super) fn get_node_at<S, K, V>(
+    storage: &S,
+    version: u64,
+    key: &K,
+) -> StorageResult<Option<ImtNode<K, V>>>
+where
+    S: StorageReader<StorageKey = Vec<u8>, StorageValue = Vec<u8>>,
+    K: StorageNodeKey,
+    V: StorageNodeValue,
+{
+    match get_at(storage, versioned_node_storage_key(key, version))? {
+        Some(v) => decode_node::<Option<_>>(v.as_ref()),
+        None => Ok(None),
+    }
+}
+
+/// Returns the low nullifier [ImtNode] for the given `key` at the given `version`.
+pub(super) fn get_ln_node_at<S, K, V>(
+    storage: &S,
+    version: u64,
+    key: &K,
+) -> StorageResult<Option<ImtNode<K, V>>>
+where
+    S: StorageReader<StorageKey = Vec<u8>, StorageValue = Vec<u8>>,
+    K: StorageNodeKey,
+    V: StorageNodeValue,
+{
+    // Walk down the node keys until finding one that exists at `version`.
+    let mut cursor = versioned_node_storage_key(key, 0);
+    loop {
+        let Some((k, _)) = storage.get_lt(&cursor)? else {
+            return Ok(None);
+        };
+
+        if k.first() != cursor.first() {
+            return Ok(None);
+        }
+
+        let (ln_key, _) = split_versioned_node_storage_key(&k).ok_or_else(|| {
+            StorageError::Deserialization("imt node storage key", format!("{:?}", k))
+        })?;
+
+        if let Some(ln_node) = get_node_at(storage, version, &decode_node_key::<K>(&ln_key)?)? {
+            return Ok(Some(ln_node));
+        }
+
+        cursor = versioned_node_storage_key(ln_key, 0);
+    }
+}
+
+/// Returns the [Hash256] cached for the given (`level`; `index`) pair at the given `version`.
+pub(super) fn get_hash_at<S>(
+    storage: &S,
+    version: u64,
+    level: u8,
+    index: u64,
+) -> StorageResult<Option<Hash256>>
+where
+    S: StorageReader<StorageKey = Vec<u8>, StorageValue = Vec<u8>>,
+{
+    get_at(storage, versioned_hash_storage_key(level, index, version))?
+        .map(|v| decode_hash("hash", v))
+        .transpose()
+}
+
+/// Returns the imt size at the given `version`.
+pub(super) fn get_size_at<S>(storage: &S, version: u64) -> StorageResult<Option<u64>>
+where
+    S: StorageReader<StorageKey = Vec<u8>, StorageValue = Vec<u8>>,
+{
+    get_at(storage, versioned_size_storage_key(version))?
+        .map(|v| decode_u64("size", v))
+        .transpose()
+}
+
+/// Returns the imt root at the given `version`.
+pub(super) fn get_root_at<S>(storage: &S, version: u64) -> StorageResult<Option<Hash256>>
+where
+    S: StorageReader<StorageKey = Vec<u8>, StorageValue = Vec<u8>>,
+{
+    get_at(storage, versioned_root_storage_key(version))?
+        .map(|v| decode_hash("root", v))
+        .transpose()
+}
+
+/// Reverts the imt to its state at the end of the given `version` (or to an empty imt when `None`),
+/// removing all the entries written in the later versions.
+pub(super) fn rollback<S, K, V>(storage: &mut S, version: Option<u64>) -> StorageResult<()>
+where
+    S: StorageWriter<StorageKey = Vec<u8>, StorageValue = Vec<u8>>,
+    K: StorageNodeKey,
+    V: StorageNodeValue,
+{
+    let Some(current_version) = get_version(storage)? else {
+        return Ok(());
+    };
+
+    let first_discarded = version.map_or(0, |version| version + 1);
+    if first_discarded > current_version {
+        return Ok(());
+    }
+
+    let current_size = storage
+        .get(&size_storage_key())?
+        .map(|v| decode_u64("size", v))
+        .transpose()?;
+
+    for discarded in (first_discarded..=current_version).rev() {
+        // Walk down the changes of the discarded version, restoring the written nodes and hashes.
+        let prefix = version_changes_storage_prefix(discarded);
+        let mut cursor = version_changes_storage_prefix(discarded + 1);
+        while let Some((change_key, _)) = storage.get_lt(&cursor)? {
+            let Some(versioned_key) = change_key.strip_prefix(prefix.as_slice()) else {
+                break;
+            };
+
+            if let Some((key, _)) = split_versioned_node_storage_key(versioned_key) {
+                let node = match version {
+                    Some(version) => {
+                        get_node_at::<_, K, V>(storage, version, &decode_node_key(&key)?)?
+                    }
+                    None => None,
+                };
+                let node = node.as_ref().map(encode_node).transpose()?;
+                set_or_remove(storage, node_storage_key(&key), node)?;
+            } else if let Some((level, index, _)) = split_versioned_hash_storage_key(versioned_key)
+            {
+                let hash = match version {
+                    Some(version) => get_hash_at(storage, version, level, index)?,
+                    None => None,
+                };
+                set_or_remove(
+                    storage,
+                    hash_storage_key(level, index),
+                    hash.map(|hash| hash.to_vec()),
+                )?;
+            }
+            // NOTE: The other changes (i.e. the root registrations) only need to be removed.
+
+            storage.remove(&versioned_key.to_vec())?;
+            storage.remove(&change_key)?;
+            cursor = change_key;
+        }
+
+        storage.remove(&versioned_root_storage_key(discarded))?;
+        storage.remove(&versioned_size_storage_key(discarded))?;
+    }
+
+    let (size, root) = match version {
+        Some(version) => (
+            get_size_at(storage, version)?,
+            get_root_at(storage, version)?,
+        ),
+        None => (None, None),
+    };
+
+    // NOTE: Indices are never reused so the discarded nodes are the ones with the last indices.
+    for index in size.unwrap_or_default()..current_size.unwrap_or_default() {
+        storage.remove(&node_key_storage_key(index))?;
+    }
+
+    set_or_remove(
+        storage,
+        size_storage_key(),
+        size.map(|size| size.to_le_bytes().to_vec()),
+    )?;
+    set_or_remove(storage, root_storage_key(), root.map(|root| root.to_vec()))?;
+    set_or_remove(
+        storage,
+        version_storage_key(),
+        version.map(|version| version.to_le_bytes().to_vec()),
+    )?;
+
+    // The root registration is lost if a discarded version registered the same root (e.g. an empty batch).
+    if let (Some(root), Some(version)) = (root, version) {
+        if get_root_version(storage, &root)?.is_none() {
+            storage.set(
+                root_version_storage_key(root),
+                version.to_le_bytes().to_vec(),
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Discards the history that is only needed to read the versions before the given `version`.
+///
+/// Only the last write at or before `version` of each element is kept, as it is the one read at
+/// `version`, and the changes of the previous versions are removed so that they can no longer be
+/// rolled back to.
+pub(super) fn prune<S>(storage: &mut S, version: u64) -> StorageResult<()>
+where
+    S: StorageWriter<StorageKey = Vec<u8>, StorageValue = Vec<u8>>,
+{
+    // Remove the sizes and roots of the previous versions.
+    prune_before(storage, versioned_size_storage_key(version))?;
+    let roots_floor = prune_before(storage, versioned_root_storage_key(version))?;
+
+    // Walk down the changes of the previous versions, removing the overwritten nodes and hashes.
+    let mut cursor = version_changes_storage_prefix(version);
+    while let Some((change_key, _)) = storage.get_lt(&cursor)? {
+        let Some((_, versioned_key)) = split_version_change_storage_key(&change_key) else {
+            break;
+        };
+
+        if StorageColumn::of(versioned_key) == StorageColumn::RootVersions {
+            // NOTE: A root registration is only kept while its version root is.
+            let root_version = storage
+                .get(&versioned_key.to_vec())?
+                .map(|v| decode_u64("version", v))
+                .transpose()?;
+            if root_version < roots_floor {
+                storage.remove(&versioned_key.to_vec())?;
+            }
+        } else {
+            let element_len = versioned_key.len() - VERSION_SUFFIX_LEN;
+            let read_key = [&versioned_key[..element_len], &version.to_be_bytes()].concat();
+            if get_entry_at(storage, read_key)?.map(|(k, _)| k).as_deref() != Some(versioned_key) {
+                storage.remove(&versioned_key.to_vec())?;
+            }
+        }
+
+        storage.remove(&change_key)?;
+        cursor = change_key;
+    }
+
+    Ok(())
+}
+
+/// Removes the writes of the single element versioned by `versioned_key` (e.g. the size) that
+/// precede the one read at its version, and returns the version of the one read (if any).
+fn prune_before<S>(storage: &mut S, versioned_key: Vec<u8>) -> StorageResult<Option<u64>>
+where
+    S: StorageWriter<StorageKey = Vec<u8>, StorageValue = Vec<u8>>,
+{
+    let Some((mut cursor, _)) = get_entry_at(storage, versioned_key)? else {
+        return Ok(None);
+    };
+
+    let mut version = [0; VERSION_SUFFIX_LEN];
+    version.copy_from_slice(&cursor[cursor.len() - VERSION_SUFFIX_LEN..]);
+
+    while let Some((k, _)) = storage.get_lt(&cursor)? {
+        if k.len() != cursor.len() || k.first() != cursor.first() {
+            break;
+        }
+
+        storage.remove(&k)?;
+        cursor = k;
+    }
+
+    Ok(Some(u64::from_be_bytes(version)))
+}
+
+/// Sets the `key` to the given `value`, or removes it when `None`.
+fn set_or_remove<S>(storage: &mut S, key: Vec<u8>, value: Option<Vec<u8>>) -> StorageResult<()>
+where
+    S: StorageWriter<StorageKey = Vec<u8>, StorageValue = Vec<u8>>,
+{
+    match value {
+        Some(value) => storage.set(key, value),
+        None => storage.remove(&key),
+    }
+}
+
+/// Returns the value stored under the closest versioned storage key lower or equal to `versioned_key`
+/// that belongs to the same element (i.e. only differs by its version suffix).
+fn get_at<S>(storage: &S, versioned_key: Vec<u8>) -> StorageResult<Option<Vec<u8>>>
+where
+    S: StorageReader<StorageKey = Vec<u8>, StorageValue = Vec<u8>>,
+{
+    Ok(get_entry_at(storage, versioned_key)?.map(|(_, v)| v))
+}
+
+/// Same as [get_at] but returns the whole (versioned storage key; value) entry.
+fn get_entry_at<S>(storage: &S, versioned_key: Vec<u8>) -> StorageResult<Option<(Vec<u8>, Vec<u8>)>>
+where
+    S: StorageReader<StorageKey = Vec<u8>, StorageValue = Vec<u8>>,
+{
+    let element_len = versioned_key.len() - VERSION_SUFFIX_LEN;
+
+    // NOTE: Any key suffixed with an extra byte is greater than `versioned_key` but lower than
+    //       the key of the next version.
+    let mut upper_bound = versioned_key.clone();
+    upper_bound.push(0);
+
+    Ok(storage.get_lt(&upper_bound)?.filter(|(k, _)| {
+        k.len() == versioned_key.len() && k[..element_len] == versioned_key[..element_len]
+    }))
+}
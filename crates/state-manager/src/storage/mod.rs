@@ -1,23 +1,111 @@
-mod imt;
+use std::{collections::btree_map, iter::Peekable, ops::Bound};
+use thiserror::Error;
 
 pub mod btree;
+pub mod imt;
+#[cfg(feature = "rocksdb")]
+pub mod rocksdb;
 pub mod sled;
 
+#[derive(Error, Debug)]
+pub enum StorageError {
+    #[error("storage backend error: {0}")]
+    Backend(String),
+    #[error("failed to serialize {0}: {1}")]
+    Serialization(&'static str, String),
+    #[error("failed to deserialize {0}: {1}")]
+    Deserialization(&'static str, String),
+}
+
+pub type StorageResult<T> = Result<T, StorageError>;
+
+/// An ordered iterator over (key; value) pairs of a storage.
+pub type StorageIter<'a, K, V> = Box<dyn Iterator<Item = StorageResult<(K, V)>> + 'a>;
+
 /// Trait providing ordered storage read access.
 pub trait StorageReader {
     type StorageKey;
     type StorageValue;
 
     /// Fetches the `key` value from the storage.
-    fn get(&self, key: &Self::StorageKey) -> Option<Self::StorageValue>;
+    fn get(&self, key: &Self::StorageKey) -> StorageResult<Option<Self::StorageValue>>;
     /// Fetches the closest (key; value) pair value right below the given `key`.
-    fn get_lt(&self, key: &Self::StorageKey) -> Option<(Self::StorageKey, Self::StorageValue)>;
+    fn get_lt(
+        &self,
+        key: &Self::StorageKey,
+    ) -> StorageResult<Option<(Self::StorageKey, Self::StorageValue)>>;
+    /// Fetches the (key; value) pairs within the (`start`; `end`) bounds, in key order.
+    fn range<'a>(
+        &'a self,
+        start: Bound<&Self::StorageKey>,
+        end: Bound<&Self::StorageKey>,
+    ) -> StorageIter<'a, Self::StorageKey, Self::StorageValue>;
+}
+
+impl<T: StorageReader> StorageReader for &T {
+    type StorageKey = T::StorageKey;
+    type StorageValue = T::StorageValue;
+
+    fn get(&self, key: &Self::StorageKey) -> StorageResult<Option<Self::StorageValue>> {
+        T::get(*self, key)
+    }
+
+    fn get_lt(
+        &self,
+        key: &Self::StorageKey,
+    ) -> StorageResult<Option<(Self::StorageKey, Self::StorageValue)>> {
+        T::get_lt(*self, key)
+    }
+
+    fn range<'a>(
+        &'a self,
+        start: Bound<&Self::StorageKey>,
+        end: Bound<&Self::StorageKey>,
+    ) -> StorageIter<'a, Self::StorageKey, Self::StorageValue> {
+        T::range(*self, start, end)
+    }
+}
+
+impl<T: StorageReader> StorageReader for &mut T {
+    type StorageKey = T::StorageKey;
+    type StorageValue = T::StorageValue;
+
+    fn get(&self, key: &Self::StorageKey) -> StorageResult<Option<Self::StorageValue>> {
+        T::get(*self, key)
+    }
+
+    fn get_lt(
+        &self,
+        key: &Self::StorageKey,
+    ) -> StorageResult<Option<(Self::StorageKey, Self::StorageValue)>> {
+        T::get_lt(*self, key)
+    }
+
+    fn range<'a>(
+        &'a self,
+        start: Bound<&Self::StorageKey>,
+        end: Bound<&Self::StorageKey>,
+    ) -> StorageIter<'a, Self::StorageKey, Self::StorageValue> {
+        T::range(*self, start, end)
+    }
 }
 
 /// Trait providing storage write access.
 pub trait StorageWriter: StorageReader {
     /// Sets the (key; value) pair in storage.
-    fn set(&mut self, key: Self::StorageKey, value: Self::StorageValue);
+    fn set(&mut self, key: Self::StorageKey, value: Self::StorageValue) -> StorageResult<()>;
+    /// Removes the `key` from storage.
+    fn remove(&mut self, key: &Self::StorageKey) -> StorageResult<()>;
+}
+
+impl<T: StorageWriter> StorageWriter for &mut T {
+    fn set(&mut self, key: Self::StorageKey, value: Self::StorageValue) -> StorageResult<()> {
+        T::set(*self, key, value)
+    }
+
+    fn remove(&mut self, key: &Self::StorageKey) -> StorageResult<()> {
+        T::remove(*self, key)
+    }
 }
 
 /// Trait to implement for storages that allow atomic batch updates.
@@ -30,11 +118,85 @@ pub trait TransactionalStorage {
     fn transaction(&mut self) -> Self::T<'_>;
 }
 
+/// Trait to implement for storages that can provide read-only snapshots of their commited state.
+pub trait SnapshotStorage {
+    type Snapshot: StorageReader;
+
+    /// Returns a [Self::Snapshot] of the commited state.
+    ///
+    /// The snapshot is not affected by the [Transaction]s commited after it was taken, so it can be
+    /// read concurrently with an in-flight [Transaction].
+    fn snapshot(&self) -> Self::Snapshot;
+}
+
 /// A storage transaction that can be commited atomically or discarded.
 pub trait Transaction: StorageWriter {
     /// Consumes the [Transaction] and apply its changes to the storage.
-    fn commit(self);
+    fn commit(self) -> StorageResult<()>;
 
     /// Consumes the [Transaction] without applying its changes to the storage.
     fn discard(self);
 }
+
+/// Merges the `buffer` entries of a [Transaction] (removed keys as `None` tombstones) with the
+/// `stored` entries, both in key order.
+///
+/// The buffer entries shadow the stored entries with the same key.
+pub(crate) fn merge_buffered<'a, K, V>(
+    buffer: btree_map::Range<'a, K, Option<V>>,
+    stored: StorageIter<'a, K, V>,
+) -> StorageIter<'a, K, V>
+where
+    K: Clone + Ord,
+    V: Clone,
+{
+    Box::new(MergeBuffered {
+        buffer: buffer.peekable(),
+        stored: stored.peekable(),
+    })
+}
+
+struct MergeBuffered<'a, K, V> {
+    buffer: Peekable<btree_map::Range<'a, K, Option<V>>>,
+    stored: Peekable<StorageIter<'a, K, V>>,
+}
+
+impl<K, V> Iterator for MergeBuffered<'_, K, V>
+where
+    K: Clone + Ord,
+    V: Clone,
+{
+    type Item = StorageResult<(K, V)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let stored_key = match self.stored.peek() {
+                Some(Ok((key, _))) => Some(key),
+                Some(Err(_)) => return self.stored.next(),
+                None => None,
+            };
+
+            match (self.buffer.peek(), stored_key) {
+                (None, _) => return self.stored.next(),
+                (Some((buffer_key, _)), Some(stored_key)) if stored_key < *buffer_key => {
+                    return self.stored.next()
+                }
+                (Some((buffer_key, buffer_value)), stored_key) => {
+                    if stored_key == Some(*buffer_key) {
+                        self.stored.next();
+                    }
+
+                    let entry = buffer_value
+                        .as_ref()
+                        .map(|value| Ok(((*buffer_key).clone(), value.clone())));
+                    self.buffer.next();
+
+                    // Skip the removed entries.
+                    if entry.is_some() {
+                        return entry;
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,684 @@
+use rocksdb::{
+    ColumnFamily, ColumnFamilyDescriptor, IteratorMode, Options, ReadOptions, Snapshot, WriteBatch,
+    WriteOptions, DB,
+};
+use std::{
+    collections::BTreeMap, iter, marker::PhantomData, mem, ops::Bound, path::Path, sync::Arc,
+};
+
+use super::{
+    imt::StorageColumn, merge_buffered, SnapshotStorage, StorageError, StorageIter, StorageReader,
+    StorageResult, StorageWriter, Transaction, TransactionalStorage,
+};
+
+/// A storage implementation over a RocksDB [DB].
+///
+/// The storage keys are persisted without their prefix in the column family of their
+/// [StorageColumn] (see [StorageColumn::split]), so that the imt nodes, hashes and metadata can be
+/// compacted and backed up separately. As a consequence `get_lt` only returns keys from the same
+/// [StorageColumn] as the given key, and `range` only returns keys from the same [StorageColumn] as
+/// its start bound (or its end bound when the start is unbounded).
+///
+/// The entries are ordered by the bytes of their keys, so the [Ord] implementation of `K` MUST
+/// match its byte ordering (as it is the case for `Vec<u8>`).
+#[derive(Debug)]
+pub struct RocksStorage<K, V> {
+    db: Arc<DB>,
+
+    _phantom_data_k: PhantomData<K>,
+    _phantom_data_v: PhantomData<V>,
+}
+
+impl<K, V> RocksStorage<K, V> {
+    /// Opens (or creates) the RocksDB [DB] at the given `path`, with one column family per
+    /// [StorageColumn], and creates a new [RocksStorage].
+    pub fn open(path: impl AsRef<Path>) -> StorageResult<Self> {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.create_missing_column_families(true);
+
+        let cfs = StorageColumn::ALL
+            .iter()
+            .map(|column| ColumnFamilyDescriptor::new(column.name(), Options::default()));
+        let db = DB::open_cf_descriptors(&opts, path, cfs).map_err(backend_error)?;
+
+        Ok(RocksStorage {
+            db: Arc::new(db),
+            _phantom_data_k: PhantomData,
+            _phantom_data_v: PhantomData,
+        })
+    }
+
+    /// Returns the column family handle in which the given storage `key` is persisted, along with
+    /// its key within the column family.
+    fn cf<'k>(&self, key: &'k [u8]) -> StorageResult<(&ColumnFamily, &'k [u8])> {
+        split_cf(&self.db, key)
+    }
+
+    /// Returns a [RocksReader] over the latest commited entries.
+    fn reader(&self) -> RocksReader<'_> {
+        RocksReader {
+            db: &self.db,
+            snapshot: None,
+        }
+    }
+}
+
+impl<K, V> StorageReader for RocksStorage<K, V>
+where
+    K: AsRef<[u8]> + for<'b> From<&'b [u8]>,
+    V: for<'b> From<&'b [u8]>,
+{
+    type StorageKey = K;
+    type StorageValue = V;
+
+    fn get(&self, key: &Self::StorageKey) -> StorageResult<Option<Self::StorageValue>> {
+        self.reader().get(key)
+    }
+
+    fn get_lt(
+        &self,
+        key: &Self::StorageKey,
+    ) -> StorageResult<Option<(Self::StorageKey, Self::StorageValue)>> {
+        self.reader().get_lt(key)
+    }
+
+    fn range<'a>(
+        &'a self,
+        start: Bound<&Self::StorageKey>,
+        end: Bound<&Self::StorageKey>,
+    ) -> StorageIter<'a, Self::StorageKey, Self::StorageValue> {
+        self.reader().range(start, end)
+    }
+}
+
+impl<K, V> SnapshotStorage for RocksStorage<K, V>
+where
+    K: AsRef<[u8]> + for<'b> From<&'b [u8]>,
+    V: for<'b> From<&'b [u8]>,
+{
+    type Snapshot = RocksSnapshot<K, V>;
+
+    fn snapshot(&self) -> Self::Snapshot {
+        // SAFETY: The snapshot borrows the DB, which is kept alive (and never moved) by the `Arc`
+        //         stored alongside it in the `RocksSnapshot`, and dropped after it.
+        let snapshot =
+            unsafe { mem::transmute::<Snapshot<'_>, Snapshot<'static>>(self.db.snapshot()) };
+
+        RocksSnapshot {
+            snapshot,
+            db: Arc::clone(&self.db),
+            _phantom_data_k: PhantomData,
+            _phantom_data_v: PhantomData,
+        }
+    }
+}
+
+/// A read-only RocksDB [Snapshot] of the commited state of a [RocksStorage].
+pub struct RocksSnapshot<K, V> {
+    // NOTE: The snapshot MUST be declared (and thus dropped) before the DB it borrows.
+    snapshot: Snapshot<'static>,
+    db: Arc<DB>,
+
+    _phantom_data_k: PhantomData<K>,
+    _phantom_data_v: PhantomData<V>,
+}
+
+impl<K, V> StorageReader for RocksSnapshot<K, V>
+where
+    K: AsRef<[u8]> + for<'b> From<&'b [u8]>,
+    V: for<'b> From<&'b [u8]>,
+{
+    type StorageKey = K;
+    type StorageValue = V;
+
+    fn get(&self, key: &Self::StorageKey) -> StorageResult<Option<Self::StorageValue>> {
+        self.reader().get(key)
+    }
+
+    fn get_lt(
+        &self,
+        key: &Self::StorageKey,
+    ) -> StorageResult<Option<(Self::StorageKey, Self::StorageValue)>> {
+        self.reader().get_lt(key)
+    }
+
+    fn range<'a>(
+        &'a self,
+        start: Bound<&Self::StorageKey>,
+        end: Bound<&Self::StorageKey>,
+    ) -> StorageIter<'a, Self::StorageKey, Self::StorageValue> {
+        self.reader().range(start, end)
+    }
+}
+
+impl<K, V> RocksSnapshot<K, V> {
+    /// Returns a [RocksReader] over the entries commited when the snapshot was taken.
+    fn reader(&self) -> RocksReader<'_> {
+        RocksReader {
+            db: &self.db,
+            snapshot: Some(&self.snapshot),
+        }
+    }
+}
+
+/// Reads the entries of a RocksDB [DB], as of the given [Snapshot] (if any).
+struct RocksReader<'a> {
+    db: &'a DB,
+    snapshot: Option<&'a Snapshot<'static>>,
+}
+
+impl<'a> RocksReader<'a> {
+    /// Returns the [ReadOptions] reading from the [RocksReader::snapshot] (if any).
+    fn read_opts(&self) -> ReadOptions {
+        let mut read_opts = ReadOptions::default();
+        if let Some(snapshot) = self.snapshot {
+            read_opts.set_snapshot(snapshot);
+        }
+
+        read_opts
+    }
+
+    fn get<K, V>(&self, key: &K) -> StorageResult<Option<V>>
+    where
+        K: AsRef<[u8]>,
+        V: for<'b> From<&'b [u8]>,
+    {
+        let (cf, key) = split_cf(self.db, key.as_ref())?;
+        let value = self
+            .db
+            .get_cf_opt(cf, key, &self.read_opts())
+            .map_err(backend_error)?;
+        Ok(value.map(|v| V::from(&v)))
+    }
+
+    fn get_lt<K, V>(&self, key: &K) -> StorageResult<Option<(K, V)>>
+    where
+        K: AsRef<[u8]> + for<'b> From<&'b [u8]>,
+        V: for<'b> From<&'b [u8]>,
+    {
+        let (column, key) = StorageColumn::split(key.as_ref());
+
+        // The upper bound is exclusive, so the last entry is the one right below `key`.
+        let mut read_opts = self.read_opts();
+        read_opts.set_iterate_upper_bound(key);
+
+        let entry = self
+            .db
+            .iterator_cf_opt(column_cf(self.db, column)?, read_opts, IteratorMode::End)
+            .next()
+            .transpose()
+            .map_err(backend_error)?;
+
+        Ok(entry.map(|(k, v)| (K::from(&column.join(&k)), V::from(&v))))
+    }
+
+    fn range<K, V>(&self, start: Bound<&K>, end: Bound<&K>) -> StorageIter<'a, K, V>
+    where
+        K: AsRef<[u8]> + for<'b> From<&'b [u8]> + 'a,
+        V: for<'b> From<&'b [u8]> + 'a,
+    {
+        let column = range_column(start, end);
+
+        // Returns the key within the column of the given bound, or `None` if it is outside of it.
+        let column_key = |bound: Bound<&K>| match bound {
+            Bound::Included(key) | Bound::Excluded(key) => {
+                match StorageColumn::split(key.as_ref()) {
+                    (key_column, key) if key_column == column => Some(key.to_vec()),
+                    _ => None,
+                }
+            }
+            Bound::Unbounded => None,
+        };
+
+        // NOTE: The key right after `key` is `key || 0x00`.
+        let mut read_opts = self.read_opts();
+        if let Some(key) = column_key(start) {
+            match start {
+                Bound::Excluded(_) => read_opts.set_iterate_lower_bound([&key[..], &[0]].concat()),
+                _ => read_opts.set_iterate_lower_bound(key),
+            }
+        }
+        if let Some(key) = column_key(end) {
+            match end {
+                Bound::Included(_) => read_opts.set_iterate_upper_bound([&key[..], &[0]].concat()),
+                _ => read_opts.set_iterate_upper_bound(key),
+            }
+        }
+
+        let cf = match column_cf(self.db, column) {
+            Ok(cf) => cf,
+            Err(e) => return Box::new(iter::once(Err(e))),
+        };
+
+        Box::new(
+            self.db
+                .iterator_cf_opt(cf, read_opts, IteratorMode::Start)
+                .map(move |entry| {
+                    entry
+                        .map(|(k, v)| (K::from(&column.join(&k)), V::from(&v)))
+                        .map_err(backend_error)
+                }),
+        )
+    }
+}
+
+impl<K, V> StorageWriter for RocksStorage<K, V>
+where
+    K: AsRef<[u8]> + for<'b> From<&'b [u8]>,
+    V: AsRef<[u8]> + for<'b> From<&'b [u8]>,
+{
+    fn set(&mut self, key: Self::StorageKey, value: Self::StorageValue) -> StorageResult<()> {
+        let (cf, key) = self.cf(key.as_ref())?;
+        self.db.put_cf(cf, key, value).map_err(backend_error)
+    }
+
+    fn remove(&mut self, key: &Self::StorageKey) -> StorageResult<()> {
+        let (cf, key) = self.cf(key.as_ref())?;
+        self.db.delete_cf(cf, key).map_err(backend_error)
+    }
+}
+
+impl<K, V> TransactionalStorage for RocksStorage<K, V>
+where
+    K: Clone + Ord + AsRef<[u8]> + for<'b> From<&'b [u8]>,
+    V: Clone + AsRef<[u8]> + for<'b> From<&'b [u8]>,
+{
+    type T<'a>
+        = RocksTransaction<'a, K, V>
+    where
+        K: 'a,
+        V: 'a;
+
+    fn transaction(&mut self) -> Self::T<'_> {
+        RocksTransaction::new(self)
+    }
+}
+
+/// A storage transaction that can be created from a [RocksStorage].
+///
+/// The changes are buffered in memory (removed keys as `None` tombstones) and applied atomically
+/// as a single synced [WriteBatch] when the transaction is commited.
+pub struct RocksTransaction<'a, K, V> {
+    storage: &'a mut RocksStorage<K, V>,
+    buffer: BTreeMap<K, Option<V>>,
+}
+
+impl<'a, K, V> RocksTransaction<'a, K, V> {
+    /// Creates a new [RocksTransaction].
+    pub fn new(storage: &'a mut RocksStorage<K, V>) -> Self {
+        RocksTransaction {
+            storage,
+            buffer: BTreeMap::new(),
+        }
+    }
+}
+
+impl<'a, K, V> StorageReader for RocksTransaction<'a, K, V>
+where
+    K: Clone + Ord + AsRef<[u8]> + for<'b> From<&'b [u8]>,
+    V: Clone + for<'b> From<&'b [u8]>,
+{
+    type StorageKey = K;
+    type StorageValue = V;
+
+    fn get(&self, key: &Self::StorageKey) -> StorageResult<Option<Self::StorageValue>> {
+        match self.buffer.get(key) {
+            Some(value) => Ok(value.clone()),
+            None => self.storage.get(key),
+        }
+    }
+
+    fn get_lt(
+        &self,
+        key: &Self::StorageKey,
+    ) -> StorageResult<Option<(Self::StorageKey, Self::StorageValue)>> {
+        // Only consider the buffered entries of the same column as the stored ones.
+        let column = StorageColumn::of(key.as_ref());
+        let mut buffer_entries = self
+            .buffer
+            .range(..key)
+            .rev()
+            .filter(|(k, _)| StorageColumn::of(k.as_ref()) == column);
+
+        let mut buffer_entry = buffer_entries.next();
+        let mut storage_entry = self.storage.get_lt(key)?;
+
+        loop {
+            match (buffer_entry, &storage_entry) {
+                (None, None) => return Ok(None),
+                (None, Some(_)) => return Ok(storage_entry),
+                (Some((buffer_key, _)), Some((storage_key, _))) if storage_key > buffer_key => {
+                    return Ok(storage_entry)
+                }
+                (Some((buffer_key, buffer_value)), _) => {
+                    // The buffer entry shadows the storage entry with the same key (if any).
+                    if storage_entry
+                        .as_ref()
+                        .is_some_and(|(storage_key, _)| storage_key == buffer_key)
+                    {
+                        storage_entry = self.storage.get_lt(buffer_key)?;
+                    }
+
+                    match buffer_value {
+                        Some(value) => return Ok(Some((buffer_key.clone(), value.clone()))),
+                        // Skip the removed entries.
+                        None => buffer_entry = buffer_entries.next(),
+                    }
+                }
+            }
+        }
+    }
+
+    fn range<'b>(
+        &'b self,
+        start: Bound<&Self::StorageKey>,
+        end: Bound<&Self::StorageKey>,
+    ) -> StorageIter<'b, Self::StorageKey, Self::StorageValue> {
+        // Only consider the buffered entries of the same column as the stored ones.
+        let column = range_column(start, end);
+        let buffer = self.buffer.range((start, end));
+        let stored = self.storage.range(start, end);
+        Box::new(merge_buffered(buffer, stored).filter(
+            move |entry| !matches!(entry, Ok((k, _)) if StorageColumn::of(k.as_ref()) != column),
+        ))
+    }
+}
+
+impl<'a, K, V> StorageWriter for RocksTransaction<'a, K, V>
+where
+    K: Clone + Ord + AsRef<[u8]> + for<'b> From<&'b [u8]>,
+    V: Clone + for<'b> From<&'b [u8]>,
+{
+    fn set(&mut self, key: Self::StorageKey, value: Self::StorageValue) -> StorageResult<()> {
+        self.buffer.insert(key, Some(value));
+        Ok(())
+    }
+
+    fn remove(&mut self, key: &Self::StorageKey) -> StorageResult<()> {
+        self.buffer.insert(key.clone(), None);
+        Ok(())
+    }
+}
+
+impl<'a, K, V> Transaction for RocksTransaction<'a, K, V>
+where
+    K: Clone + Ord + AsRef<[u8]> + for<'b> From<&'b [u8]>,
+    V: Clone + AsRef<[u8]> + for<'b> From<&'b [u8]>,
+{
+    fn commit(self) -> StorageResult<()> {
+        let mut batch = WriteBatch::default();
+        for (k, v) in &self.buffer {
+            let (cf, k) = self.storage.cf(k.as_ref())?;
+            match v {
+                Some(v) => batch.put_cf(cf, k, v),
+                None => batch.delete_cf(cf, k),
+            }
+        }
+
+        // Sync the write ahead log so that a commited transaction survives a crash.
+        let mut write_opts = WriteOptions::default();
+        write_opts.set_sync(true);
+
+        self.storage
+            .db
+            .write_opt(batch, &write_opts)
+            .map_err(backend_error)
+    }
+
+    fn discard(self) {}
+}
+
+/// Returns the column family handle of the given `column`.
+fn column_cf(db: &DB, column: StorageColumn) -> StorageResult<&ColumnFamily> {
+    let name = column.name();
+    db.cf_handle(name)
+        .ok_or_else(|| StorageError::Backend(format!("missing column family {name}")))
+}
+
+/// Returns the column family handle in which the given storage `key` is persisted, along with its
+/// key within the column family.
+fn split_cf<'d, 'k>(db: &'d DB, key: &'k [u8]) -> StorageResult<(&'d ColumnFamily, &'k [u8])> {
+    let (column, key) = StorageColumn::split(key);
+    Ok((column_cf(db, column)?, key))
+}
+
+/// Returns the [StorageColumn] of the given range bounds.
+///
+/// It is the column of the start bound, or of the end bound when the start is unbounded, and the
+/// metadata column for a fully unbounded range. A bound outside of this column is ignored, e.g.
+/// the end bound of the nodes range (the first hash storage key).
+fn range_column<K: AsRef<[u8]>>(start: Bound<&K>, end: Bound<&K>) -> StorageColumn {
+    match (start, end) {
+        (Bound::Included(key) | Bound::Excluded(key), _)
+        | (_, Bound::Included(key) | Bound::Excluded(key)) => StorageColumn::of(key.as_ref()),
+        (Bound::Unbounded, Bound::Unbounded) => StorageColumn::Metadata,
+    }
+}
+
+fn backend_error(err: rocksdb::Error) -> StorageError {
+    StorageError::Backend(err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use keyspace_imt::{
+        storage::versioned::{VersionedImtStorageReader, VersionedImtStorageWriter},
+        tree::Imt,
+    };
+    use std::{env, path::PathBuf};
+    use tiny_keccak::Keccak;
+
+    use crate::storage::{btree::BTreeStorage, imt::ImtStorage};
+
+    use super::*;
+
+    /// A [RocksStorage] in a temporary directory, destroyed when dropped.
+    struct TempRocksStorage {
+        path: PathBuf,
+        storage: Option<RocksStorage<Vec<u8>, Vec<u8>>>,
+    }
+
+    impl TempRocksStorage {
+        fn new(name: &str) -> Self {
+            let path = env::temp_dir().join(format!("keyspace-{name}-{}", std::process::id()));
+            let storage = RocksStorage::open(&path).expect("rocksdb open failed");
+
+            Self {
+                path,
+                storage: Some(storage),
+            }
+        }
+
+        fn storage(&mut self) -> &mut RocksStorage<Vec<u8>, Vec<u8>> {
+            self.storage.as_mut().expect("storage is open")
+        }
+    }
+
+    impl Drop for TempRocksStorage {
+        fn drop(&mut self) {
+            drop(self.storage.take());
+            let _ = DB::destroy(&Options::default(), &self.path);
+        }
+    }
+
+    #[test]
+    fn test_transaction_get_lt() {
+        let mut temp = TempRocksStorage::new("rocksdb-get-lt");
+        let storage = temp.storage();
+
+        // The node storage keys (prefixed with 0) and the hash storage keys (prefixed with 1) are
+        // persisted in separate columns.
+        for k in [[0, 1], [0, 3], [0, 5], [1, 4]] {
+            storage.set(k.to_vec(), k.to_vec()).expect("set failed");
+        }
+
+        let mut tx = storage.transaction();
+        tx.set(vec![0, 4], vec![4]).expect("set failed");
+        tx.set(vec![1, 6], vec![6]).expect("set failed");
+        tx.remove(&vec![0, 3]).expect("remove failed");
+
+        // Buffered entries shadow the stored ones, removed entries are skipped and the entries
+        // of the other columns are ignored.
+        let entry = |k: Vec<u8>, v: Vec<u8>| Some((k, v));
+        assert_eq!(
+            tx.get_lt(&vec![0, 6]).expect("get_lt failed"),
+            entry(vec![0, 5], vec![0, 5])
+        );
+        assert_eq!(tx.get_lt(&vec![10]).expect("get_lt failed"), None);
+        assert_eq!(
+            tx.get_lt(&vec![0, 5]).expect("get_lt failed"),
+            entry(vec![0, 4], vec![4])
+        );
+        assert_eq!(
+            tx.get_lt(&vec![0, 4]).expect("get_lt failed"),
+            entry(vec![0, 1], vec![0, 1])
+        );
+        assert_eq!(tx.get_lt(&vec![1, 4]).expect("get_lt failed"), None);
+        assert_eq!(
+            tx.get_lt(&vec![1, 7]).expect("get_lt failed"),
+            entry(vec![1, 6], vec![6])
+        );
+
+        tx.commit().expect("commit failed");
+
+        assert_eq!(storage.get(&vec![0, 3]).expect("get failed"), None);
+        assert_eq!(
+            storage.get_lt(&vec![0, 5]).expect("get_lt failed"),
+            entry(vec![0, 4], vec![4])
+        );
+    }
+
+    #[test]
+    fn test_transaction_range() {
+        let mut temp = TempRocksStorage::new("rocksdb-range");
+        let storage = temp.storage();
+
+        for k in [[0, 1], [0, 3], [0, 5], [1, 4]] {
+            storage.set(k.to_vec(), k.to_vec()).expect("set failed");
+        }
+
+        let mut tx = storage.transaction();
+        tx.set(vec![0, 4], vec![4]).expect("set failed");
+        tx.set(vec![1, 2], vec![2]).expect("set failed");
+        tx.remove(&vec![0, 3]).expect("remove failed");
+
+        // Buffered entries shadow the stored ones, removed entries are skipped and the entries
+        // of the other columns are ignored.
+        let entries = tx
+            .range(Bound::Excluded(&vec![0, 1]), Bound::Included(&vec![1, 4]))
+            .collect::<StorageResult<Vec<_>>>()
+            .expect("range failed");
+        assert_eq!(entries, [(vec![0, 4], vec![4]), (vec![0, 5], vec![0, 5])]);
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let mut temp = TempRocksStorage::new("rocksdb-snapshot");
+        let storage = temp.storage();
+
+        for k in [[0, 1], [0, 3]] {
+            storage.set(k.to_vec(), k.to_vec()).expect("set failed");
+        }
+
+        let sut = storage.snapshot();
+
+        // Ensure the snapshot is not affected by the writes commited after it was taken.
+        let mut tx = storage.transaction();
+        tx.set(vec![0, 1], vec![1]).expect("set failed");
+        tx.set(vec![0, 2], vec![2]).expect("set failed");
+        tx.remove(&vec![0, 3]).expect("remove failed");
+        tx.commit().expect("commit failed");
+
+        let entry = |k: Vec<u8>, v: Vec<u8>| Some((k, v));
+        assert_eq!(sut.get(&vec![0, 1]).expect("get failed"), Some(vec![0, 1]));
+        assert_eq!(sut.get(&vec![0, 2]).expect("get failed"), None);
+        assert_eq!(
+            sut.get_lt(&vec![0, 4]).expect("get_lt failed"),
+            entry(vec![0, 3], vec![0, 3])
+        );
+
+        let entries = sut
+            .range(Bound::Included(&vec![0]), Bound::Excluded(&vec![1]))
+            .collect::<StorageResult<Vec<_>>>()
+            .expect("range failed");
+        assert_eq!(
+            entries,
+            [(vec![0, 1], vec![0, 1]), (vec![0, 3], vec![0, 3])]
+        );
+
+        assert_eq!(
+            storage.snapshot().get(&vec![0, 1]).expect("get failed"),
+            Some(vec![1])
+        );
+    }
+
+    #[test]
+    fn test_imt_matches_btree() {
+        let mut temp = TempRocksStorage::new("rocksdb-imt");
+        let mut btree_storage = BTreeStorage::new();
+
+        // Instanciate an imt with a few nodes on both storages, within a commited transaction.
+        let rocks_root = insert_nodes(temp.storage());
+        let btree_root = insert_nodes(&mut btree_storage);
+        assert_eq!(rocks_root, btree_root);
+
+        let sut = Imt::reader(
+            Keccak::v256,
+            ImtStorage::<_, [u8; 32], [u8; 32]>::new(temp.storage()),
+        )
+        .expect("imt reader failed");
+        assert_eq!(sut.root().expect("root failed"), rocks_root);
+        sut.verify_integrity().expect("verify integrity failed");
+    }
+
+    #[test]
+    fn test_imt_rollback() {
+        let mut temp = TempRocksStorage::new("rocksdb-imt-rollback");
+        let storage = temp.storage();
+        let root = insert_nodes(storage);
+
+        // Register a second imt version.
+        let mut tx = storage.transaction();
+        let mut imt_tx = ImtStorage::<_, [u8; 32], [u8; 32]>::new(&mut tx);
+        imt_tx.set_version(1).expect("set_version failed");
+        let mut imt = Imt::writer(Keccak::v256, imt_tx).expect("imt writer failed");
+        imt.insert_node([4; 32], [4; 32]).expect("insert failed");
+        tx.commit().expect("commit failed");
+
+        // Ensure the first version is restored once the second one is rolled back.
+        let mut tx = storage.transaction();
+        ImtStorage::<_, [u8; 32], [u8; 32]>::new(&mut tx)
+            .rollback(Some(0))
+            .expect("rollback failed");
+        tx.commit().expect("commit failed");
+
+        let imt_storage = ImtStorage::<_, [u8; 32], [u8; 32]>::new(temp.storage());
+        assert_eq!(
+            imt_storage.get_version().expect("get_version failed"),
+            Some(0)
+        );
+        let sut = Imt::reader(Keccak::v256, imt_storage).expect("imt reader failed");
+        assert_eq!(sut.root().expect("root failed"), root);
+        sut.verify_integrity().expect("verify integrity failed");
+    }
+
+    fn insert_nodes<S>(storage: &mut S) -> [u8; 32]
+    where
+        S: TransactionalStorage,
+        for<'a> S::T<'a>: StorageWriter<StorageKey = Vec<u8>, StorageValue = Vec<u8>>,
+    {
+        let mut tx = storage.transaction();
+        let mut imt_tx = ImtStorage::new(&mut tx);
+        imt_tx.set_version(0).expect("set_version failed");
+        let mut imt = Imt::writer(Keccak::v256, imt_tx).expect("imt writer failed");
+        for key in [[3; 32], [1; 32], [2; 32]] {
+            imt.insert_node(key, key).expect("insert failed");
+        }
+        imt.delete_node([1; 32]).expect("delete failed");
+
+        let root = imt.root().expect("root failed");
+        tx.commit().expect("commit failed");
+        root
+    }
+}
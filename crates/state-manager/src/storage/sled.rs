@@ -1,183 +1,553 @@
-// use sled::{self, IVec};
-// use std::collections::BTreeMap;
-
-// use super::{StorageReader, StorageWriter, Transaction, TransactionalStorage};
-
-// TODO: Fix implementation to work with arbitrary keys and values.
-
-// /// A storage implementation over a [sled::Db].
-// #[derive(Debug)]
-// pub struct SledStorage<K, V> {
-//     db: sled::Db,
-
-//     _phantom_data_k: std::marker::PhantomData<K>,
-//     _phantom_data_v: std::marker::PhantomData<V>,
-// }
-
-// impl<K, V> SledStorage<K, V> {
-//     /// Creates a new [SledStorage].
-//     pub fn new(db: sled::Db) -> Self {
-//         SledStorage {
-//             db,
-//             _phantom_data_k: std::marker::PhantomData,
-//             _phantom_data_v: std::marker::PhantomData,
-//         }
-//     }
-// }
-
-// impl<K, V> StorageReader for SledStorage<K, V>
-// where
-//     K: AsRef<[u8]> + From<IVec>,
-//     V: From<IVec>,
-// {
-//     type StorageKey = K;
-//     type StorageValue = V;
-
-//     fn get(&self, key: &Self::StorageKey) -> Option<Self::StorageValue> {
-//         self.db.get(key).expect("sled get failed").map(|v| v.into())
-//     }
-
-//     fn get_lt(&self, key: &Self::StorageKey) -> Option<(Self::StorageKey, Self::StorageValue)> {
-//         self.db
-//             .get_lt(key)
-//             .expect("sled get_lt failed")
-//             .map(|(k, v)| (k.into(), v.into()))
-//     }
-// }
-
-// impl<K, V> StorageWriter for SledStorage<K, V>
-// where
-//     K: AsRef<[u8]> + From<IVec>,
-//     V: From<IVec> + Into<IVec>,
-// {
-//     fn set(&mut self, key: Self::StorageKey, value: Self::StorageValue) {
-//         self.db.insert(key, value).expect("sled set failed");
-//     }
-// }
-
-// impl<K, V> TransactionalStorage for SledStorage<K, V>
-// where
-//     K: Clone + Ord + AsRef<[u8]> + From<IVec> + Into<IVec>,
-//     V: Clone + From<IVec> + Into<IVec>,
-// {
-//     type T<'a> = SledTransaction<'a, K, V> where K: 'a, V: 'a;
-
-//     fn transaction(&mut self) -> Self::T<'_> {
-//         SledTransaction::new(&mut self.db)
-//     }
-// }
-
-// /// A storage transaction that can be created from a [SledStorage].
-// pub struct SledTransaction<'a, K, V> {
-//     db: &'a mut sled::Db,
-//     batch: sled::Batch,
-//     buffer: BTreeMap<K, V>,
-// }
-
-// impl<'a, K, V> SledTransaction<'a, K, V> {
-//     /// Creates a new [SledTransaction].
-//     pub fn new(db: &'a mut sled::Db) -> Self {
-//         SledTransaction {
-//             db,
-//             batch: sled::Batch::default(),
-//             buffer: BTreeMap::new(),
-//         }
-//     }
-// }
-
-// impl<'a, K, V> StorageReader for SledTransaction<'a, K, V>
-// where
-//     K: Clone + Ord + AsRef<[u8]> + From<IVec>,
-//     V: Clone + From<IVec>,
-// {
-//     type StorageKey = K;
-//     type StorageValue = V;
-
-//     fn get(&self, key: &Self::StorageKey) -> Option<Self::StorageValue> {
-//         self.buffer
-//             .get(key)
-//             .cloned()
-//             .or_else(|| self.db.get(key).expect("sled get failed").map(Into::into))
-//     }
-
-//     fn get_lt(&self, key: &Self::StorageKey) -> Option<(Self::StorageKey, Self::StorageValue)> {
-//         self.buffer
-//             .range(..key)
-//             .next_back()
-//             .map(|r| (r.0.clone(), r.1.clone()))
-//             .or_else(|| {
-//                 self.db
-//                     .get_lt(key)
-//                     .expect("sled get_lt failed")
-//                     .map(|(k, v)| (k.into(), v.into()))
-//             })
-//     }
-// }
-
-// impl<'a, K, V> StorageWriter for SledTransaction<'a, K, V>
-// where
-//     K: Clone + Ord + AsRef<[u8]> + From<IVec> + Into<IVec>,
-//     V: Clone + From<IVec> + Into<IVec>,
-// {
-//     fn set(&mut self, key: Self::StorageKey, value: Self::StorageValue) {
-//         self.batch.insert(key.clone(), value.clone());
-//         self.buffer.insert(key, value);
-//     }
-// }
-
-// impl<'a, K, V> Transaction for SledTransaction<'a, K, V>
-// where
-//     K: Clone + Ord + AsRef<[u8]> + From<IVec> + Into<IVec>,
-//     V: Clone + From<IVec> + Into<IVec>,
-// {
-//     fn commit(self) {
-//         self.db.apply_batch(self.batch).unwrap();
-//     }
-
-//     fn discard(self) {}
-// }
-
-// #[cfg(test)]
-// mod test {
-
-//     use super::*;
-
-//     #[test]
-//     fn test_transaction_commit() {
-//         // let db = sled::open("/tmp/my_db").unwrap();
-//         // let mut storage = SledStorage::new(db);
-
-//         // Start a transaction
-//         // let mut txn = storage.transaction();
-
-//         // Perform operations
-//         // storage.set("key1", "value1");
-//         // txn.set("key2", "value2");
-
-//         // // Read within transaction
-//         // assert_eq!(
-//         //     txn.get("key1").map(|v| v.as_ref().to_vec()),
-//         //     Some(b"value1".to_vec())
-//         // );
-
-//         // // Use get_lt
-//         // let result = txn.get_lt("key2");
-//         // assert_eq!(
-//         //     result.map(|(k, v)| (k.as_ref().to_vec(), v.as_ref().to_vec())),
-//         //     Some((b"key1".to_vec(), b"value1".to_vec()))
-//         // );
-
-//         // // Commit transaction
-//         // txn.commit();
-
-//         // assert_eq!(
-//         //     storage.get("key1").map(|v| v.as_ref().to_vec()),
-//         //     Some(b"value1".to_vec())
-//         // );
-
-//         // assert_eq!(
-//         //     storage.get("key2").map(|v| v.as_ref().to_vec()),
-//         //     Some(b"value2".to_vec())
-//         // );
-//     }
-// }
+use sled::{Batch, Db, IVec};
+use std::{
+    collections::BTreeMap,
+    marker::PhantomData,
+    ops::Bound,
+    sync::{Arc, Mutex, PoisonError, RwLock, Weak},
+};
+
+use super::{
+    merge_buffered, SnapshotStorage, StorageError, StorageIter, StorageReader, StorageResult,
+    StorageWriter, Transaction, TransactionalStorage,
+};
+
+/// The commited entries overwritten since a [SledSnapshot] was taken (`None` if they were missing).
+type Overwritten<K, V> = RwLock<BTreeMap<K, Option<V>>>;
+
+/// The (weak) references to the live [SledSnapshot]s of a [SledStorage].
+type SnapshotRefs<K, V> = Arc<Mutex<Vec<Weak<Overwritten<K, V>>>>>;
+
+/// A storage implementation over a [sled::Db].
+///
+/// The entries are ordered by the bytes of their keys, so the [Ord] implementation of `K` MUST
+/// match its byte ordering (as it is the case for `Vec<u8>`).
+#[derive(Debug, Clone)]
+pub struct SledStorage<K, V> {
+    db: Db,
+    /// The live [SledSnapshot]s, to which the entries overwritten by a [SledTransaction] are copied.
+    snapshots: SnapshotRefs<K, V>,
+
+    _phantom_data_k: PhantomData<K>,
+    _phantom_data_v: PhantomData<V>,
+}
+
+impl<K, V> SledStorage<K, V> {
+    /// Creates a new [SledStorage].
+    pub fn new(db: Db) -> Self {
+        SledStorage {
+            db,
+            snapshots: Default::default(),
+            _phantom_data_k: PhantomData,
+            _phantom_data_v: PhantomData,
+        }
+    }
+
+    /// Returns the [SledSnapshot]s still alive, forgetting about the dropped ones.
+    fn live_snapshots(&self) -> Vec<Arc<Overwritten<K, V>>> {
+        let mut snapshots = self
+            .snapshots
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        snapshots.retain(|snapshot| snapshot.strong_count() > 0);
+        snapshots.iter().filter_map(Weak::upgrade).collect()
+    }
+
+    /// Opens (or creates) the [sled::Db] at the given `path` and creates a new [SledStorage].
+    pub fn open(path: &str) -> StorageResult<Self> {
+        let db = sled::open(path).map_err(backend_error)?;
+        Ok(Self::new(db))
+    }
+}
+
+impl<K, V> StorageReader for SledStorage<K, V>
+where
+    K: AsRef<[u8]> + for<'b> From<&'b [u8]>,
+    V: for<'b> From<&'b [u8]>,
+{
+    type StorageKey = K;
+    type StorageValue = V;
+
+    fn get(&self, key: &Self::StorageKey) -> StorageResult<Option<Self::StorageValue>> {
+        let value = self.db.get(key).map_err(backend_error)?;
+        Ok(value.map(|v| V::from(&v)))
+    }
+
+    fn get_lt(
+        &self,
+        key: &Self::StorageKey,
+    ) -> StorageResult<Option<(Self::StorageKey, Self::StorageValue)>> {
+        let entry = self.db.get_lt(key).map_err(backend_error)?;
+        Ok(entry.map(decode_entry))
+    }
+
+    fn range<'a>(
+        &'a self,
+        start: Bound<&Self::StorageKey>,
+        end: Bound<&Self::StorageKey>,
+    ) -> StorageIter<'a, Self::StorageKey, Self::StorageValue> {
+        let range = (start.map(AsRef::as_ref), end.map(AsRef::as_ref));
+        Box::new(
+            self.db
+                .range::<&[u8], _>(range)
+                .map(|entry| entry.map(decode_entry).map_err(backend_error)),
+        )
+    }
+}
+
+impl<K, V> StorageWriter for SledStorage<K, V>
+where
+    K: AsRef<[u8]> + for<'b> From<&'b [u8]>,
+    V: AsRef<[u8]> + for<'b> From<&'b [u8]>,
+{
+    fn set(&mut self, key: Self::StorageKey, value: Self::StorageValue) -> StorageResult<()> {
+        self.db
+            .insert(key.as_ref(), value.as_ref())
+            .map_err(backend_error)?;
+        Ok(())
+    }
+
+    fn remove(&mut self, key: &Self::StorageKey) -> StorageResult<()> {
+        self.db.remove(key).map_err(backend_error)?;
+        Ok(())
+    }
+}
+
+impl<K, V> TransactionalStorage for SledStorage<K, V>
+where
+    K: Clone + Ord + AsRef<[u8]> + for<'b> From<&'b [u8]>,
+    V: Clone + AsRef<[u8]> + for<'b> From<&'b [u8]>,
+{
+    type T<'a>
+        = SledTransaction<'a, K, V>
+    where
+        K: 'a,
+        V: 'a;
+
+    fn transaction(&mut self) -> Self::T<'_> {
+        SledTransaction::new(self)
+    }
+}
+
+impl<K, V> SnapshotStorage for SledStorage<K, V>
+where
+    K: Clone + Ord + AsRef<[u8]> + for<'b> From<&'b [u8]>,
+    V: Clone + for<'b> From<&'b [u8]>,
+{
+    type Snapshot = SledSnapshot<K, V>;
+
+    fn snapshot(&self) -> Self::Snapshot {
+        let overwritten = Arc::new(Overwritten::default());
+        self.snapshots
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(Arc::downgrade(&overwritten));
+
+        SledSnapshot {
+            storage: self.clone(),
+            overwritten,
+        }
+    }
+}
+
+/// A read-only snapshot of the commited state of a [SledStorage].
+///
+/// The [SledStorage] copies the entries it overwrites to its live snapshots, so that they keep
+/// reading the entries as they were when the snapshot was taken.
+#[derive(Debug, Clone)]
+pub struct SledSnapshot<K, V> {
+    storage: SledStorage<K, V>,
+    overwritten: Arc<Overwritten<K, V>>,
+}
+
+impl<K, V> StorageReader for SledSnapshot<K, V>
+where
+    K: Clone + Ord + AsRef<[u8]> + for<'b> From<&'b [u8]>,
+    V: Clone + for<'b> From<&'b [u8]>,
+{
+    type StorageKey = K;
+    type StorageValue = V;
+
+    fn get(&self, key: &Self::StorageKey) -> StorageResult<Option<Self::StorageValue>> {
+        let overwritten = self
+            .overwritten
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        match overwritten.get(key) {
+            Some(value) => Ok(value.clone()),
+            None => self.storage.get(key),
+        }
+    }
+
+    fn get_lt(
+        &self,
+        key: &Self::StorageKey,
+    ) -> StorageResult<Option<(Self::StorageKey, Self::StorageValue)>> {
+        let overwritten = self
+            .overwritten
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        get_lt_buffered(&self.storage.db, &overwritten, key)
+    }
+
+    fn range<'b>(
+        &'b self,
+        start: Bound<&Self::StorageKey>,
+        end: Bound<&Self::StorageKey>,
+    ) -> StorageIter<'b, Self::StorageKey, Self::StorageValue> {
+        // NOTE: The entries are collected so that no commit is blocked by a pending iteration.
+        let overwritten = self
+            .overwritten
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        let entries = merge_buffered(
+            overwritten.range((start, end)),
+            self.storage.range(start, end),
+        )
+        .collect::<Vec<_>>();
+
+        Box::new(entries.into_iter())
+    }
+}
+
+/// A storage transaction that can be created from a [SledStorage].
+///
+/// The changes are buffered in memory (removed keys as `None` tombstones) and applied atomically
+/// as a single [sled::Batch] when the transaction is commited.
+pub struct SledTransaction<'a, K, V> {
+    storage: &'a mut SledStorage<K, V>,
+    buffer: BTreeMap<K, Option<V>>,
+}
+
+impl<'a, K, V> SledTransaction<'a, K, V> {
+    /// Creates a new [SledTransaction].
+    pub fn new(storage: &'a mut SledStorage<K, V>) -> Self {
+        SledTransaction {
+            storage,
+            buffer: BTreeMap::new(),
+        }
+    }
+}
+
+impl<'a, K, V> StorageReader for SledTransaction<'a, K, V>
+where
+    K: Clone + Ord + AsRef<[u8]> + for<'b> From<&'b [u8]>,
+    V: Clone + for<'b> From<&'b [u8]>,
+{
+    type StorageKey = K;
+    type StorageValue = V;
+
+    fn get(&self, key: &Self::StorageKey) -> StorageResult<Option<Self::StorageValue>> {
+        match self.buffer.get(key) {
+            Some(value) => Ok(value.clone()),
+            None => self.storage.get(key),
+        }
+    }
+
+    fn get_lt(
+        &self,
+        key: &Self::StorageKey,
+    ) -> StorageResult<Option<(Self::StorageKey, Self::StorageValue)>> {
+        get_lt_buffered(&self.storage.db, &self.buffer, key)
+    }
+
+    fn range<'b>(
+        &'b self,
+        start: Bound<&Self::StorageKey>,
+        end: Bound<&Self::StorageKey>,
+    ) -> StorageIter<'b, Self::StorageKey, Self::StorageValue> {
+        merge_buffered(
+            self.buffer.range((start, end)),
+            self.storage.range(start, end),
+        )
+    }
+}
+
+impl<'a, K, V> StorageWriter for SledTransaction<'a, K, V>
+where
+    K: Clone + Ord + AsRef<[u8]> + for<'b> From<&'b [u8]>,
+    V: Clone + for<'b> From<&'b [u8]>,
+{
+    fn set(&mut self, key: Self::StorageKey, value: Self::StorageValue) -> StorageResult<()> {
+        self.buffer.insert(key, Some(value));
+        Ok(())
+    }
+
+    fn remove(&mut self, key: &Self::StorageKey) -> StorageResult<()> {
+        self.buffer.insert(key.clone(), None);
+        Ok(())
+    }
+}
+
+impl<'a, K, V> Transaction for SledTransaction<'a, K, V>
+where
+    K: Clone + Ord + AsRef<[u8]> + for<'b> From<&'b [u8]>,
+    V: Clone + AsRef<[u8]> + for<'b> From<&'b [u8]>,
+{
+    fn commit(self) -> StorageResult<()> {
+        let mut batch = Batch::default();
+        for (k, v) in &self.buffer {
+            match v {
+                Some(v) => batch.insert(k.as_ref(), v.as_ref()),
+                None => batch.remove(k.as_ref()),
+            }
+        }
+
+        // Copy the overwritten entries to the live snapshots before applying the batch, and block
+        // their reads until it is applied so that they never observe it.
+        let snapshots = self.storage.live_snapshots();
+        let mut snapshots = snapshots
+            .iter()
+            .map(|snapshot| snapshot.write().unwrap_or_else(PoisonError::into_inner))
+            .collect::<Vec<_>>();
+        if !snapshots.is_empty() {
+            for key in self.buffer.keys() {
+                let value = self.storage.get(key)?;
+                for overwritten in &mut snapshots {
+                    overwritten
+                        .entry(key.clone())
+                        .or_insert_with(|| value.clone());
+                }
+            }
+        }
+
+        self.storage.db.apply_batch(batch).map_err(backend_error)?;
+        drop(snapshots);
+
+        // Flush so that a commited transaction survives a crash.
+        self.storage.db.flush().map_err(backend_error)?;
+
+        Ok(())
+    }
+
+    fn discard(self) {}
+}
+
+/// Fetches the closest (key; value) pair right below the given `key` from the `db` entries, shadowed
+/// by the `buffer` entries (removed keys as `None` tombstones).
+fn get_lt_buffered<K, V>(
+    db: &Db,
+    buffer: &BTreeMap<K, Option<V>>,
+    key: &K,
+) -> StorageResult<Option<(K, V)>>
+where
+    K: Clone + Ord + AsRef<[u8]> + for<'b> From<&'b [u8]>,
+    V: Clone + for<'b> From<&'b [u8]>,
+{
+    let mut buffer_entries = buffer.range(..key).rev();
+    let mut storage_entries = db.range(..key.as_ref()).rev();
+
+    let mut buffer_entry = buffer_entries.next();
+    let mut storage_entry = storage_entries.next().transpose().map_err(backend_error)?;
+
+    loop {
+        match (buffer_entry, &storage_entry) {
+            (None, None) => return Ok(None),
+            (None, Some(_)) => return Ok(storage_entry.map(decode_entry)),
+            (Some((buffer_key, _)), Some((storage_key, _)))
+                if storage_key.as_ref() > buffer_key.as_ref() =>
+            {
+                return Ok(storage_entry.map(decode_entry))
+            }
+            (Some((buffer_key, buffer_value)), _) => {
+                // The buffer entry shadows the storage entry with the same key (if any).
+                if storage_entry
+                    .as_ref()
+                    .is_some_and(|(storage_key, _)| storage_key.as_ref() == buffer_key.as_ref())
+                {
+                    storage_entry = storage_entries.next().transpose().map_err(backend_error)?;
+                }
+
+                match buffer_value {
+                    Some(value) => return Ok(Some((buffer_key.clone(), value.clone()))),
+                    // Skip the removed entries.
+                    None => buffer_entry = buffer_entries.next(),
+                }
+            }
+        }
+    }
+}
+
+/// Converts a raw sled (key; value) entry.
+fn decode_entry<K, V>((k, v): (IVec, IVec)) -> (K, V)
+where
+    K: for<'b> From<&'b [u8]>,
+    V: for<'b> From<&'b [u8]>,
+{
+    (K::from(&k), V::from(&v))
+}
+
+fn backend_error(err: sled::Error) -> StorageError {
+    StorageError::Backend(err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use keyspace_imt::tree::Imt;
+    use tiny_keccak::Keccak;
+
+    use crate::storage::{btree::BTreeStorage, imt::ImtStorage};
+
+    use super::*;
+
+    fn sled_storage() -> SledStorage<Vec<u8>, Vec<u8>> {
+        let db = sled::Config::new()
+            .temporary(true)
+            .open()
+            .expect("sled open failed");
+
+        SledStorage::new(db)
+    }
+
+    #[test]
+    fn test_transaction_get_lt() {
+        let mut storage = sled_storage();
+        for (k, v) in [(1, b"a"), (3, b"c"), (5, b"e")] {
+            storage.set(vec![k], v.to_vec()).expect("set failed");
+        }
+
+        let mut tx = storage.transaction();
+        tx.set(vec![4], b"d".to_vec()).expect("set failed");
+        tx.set(vec![5], b"f".to_vec()).expect("set failed");
+        tx.remove(&vec![3]).expect("remove failed");
+
+        // Buffered entries shadow the stored ones and removed entries are skipped.
+        let entry = |k: u8, v: &[u8]| Some((vec![k], v.to_vec()));
+        assert_eq!(tx.get_lt(&vec![6]).expect("get_lt failed"), entry(5, b"f"));
+        assert_eq!(tx.get_lt(&vec![5]).expect("get_lt failed"), entry(4, b"d"));
+        assert_eq!(tx.get_lt(&vec![4]).expect("get_lt failed"), entry(1, b"a"));
+        assert_eq!(tx.get_lt(&vec![1]).expect("get_lt failed"), None);
+        assert_eq!(tx.get(&vec![3]).expect("get failed"), None);
+
+        // Ensure nothing is written before the transaction is commited.
+        assert_eq!(
+            storage.get(&vec![3]).expect("get failed"),
+            Some(b"c".to_vec())
+        );
+
+        let mut tx = storage.transaction();
+        tx.remove(&vec![3]).expect("remove failed");
+        tx.set(vec![4], b"d".to_vec()).expect("set failed");
+        tx.commit().expect("commit failed");
+
+        assert_eq!(storage.get(&vec![3]).expect("get failed"), None);
+        assert_eq!(
+            storage.get_lt(&vec![5]).expect("get_lt failed"),
+            entry(4, b"d")
+        );
+    }
+
+    #[test]
+    fn test_transaction_range() {
+        let mut storage = sled_storage();
+        for (k, v) in [(1, b"a"), (3, b"c"), (5, b"e"), (7, b"g")] {
+            storage.set(vec![k], v.to_vec()).expect("set failed");
+        }
+
+        let mut tx = storage.transaction();
+        tx.set(vec![4], b"d".to_vec()).expect("set failed");
+        tx.set(vec![5], b"f".to_vec()).expect("set failed");
+        tx.remove(&vec![3]).expect("remove failed");
+
+        // Buffered entries shadow the stored ones and removed entries are skipped.
+        let entries = tx
+            .range(Bound::Excluded(&vec![1]), Bound::Included(&vec![7]))
+            .collect::<StorageResult<Vec<_>>>()
+            .expect("range failed");
+        let expected: [(Vec<u8>, Vec<u8>); 3] = [
+            (vec![4], b"d".to_vec()),
+            (vec![5], b"f".to_vec()),
+            (vec![7], b"g".to_vec()),
+        ];
+        assert_eq!(entries, expected);
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let mut storage = sled_storage();
+        for (k, v) in [(1, b"a"), (3, b"c")] {
+            storage.set(vec![k], v.to_vec()).expect("set failed");
+        }
+
+        let sut = storage.snapshot();
+
+        // Ensure the snapshot is not affected by the writes commited after it was taken.
+        let mut tx = storage.transaction();
+        tx.set(vec![1], b"b".to_vec()).expect("set failed");
+        tx.set(vec![2], b"d".to_vec()).expect("set failed");
+        tx.remove(&vec![3]).expect("remove failed");
+        tx.commit().expect("commit failed");
+
+        let entry = |k: u8, v: &[u8]| Some((vec![k], v.to_vec()));
+        assert_eq!(sut.get(&vec![1]).expect("get failed"), Some(b"a".to_vec()));
+        assert_eq!(sut.get(&vec![2]).expect("get failed"), None);
+        assert_eq!(sut.get_lt(&vec![4]).expect("get_lt failed"), entry(3, b"c"));
+        assert_eq!(sut.get_lt(&vec![3]).expect("get_lt failed"), entry(1, b"a"));
+
+        let entries = sut
+            .range(Bound::Unbounded, Bound::Unbounded)
+            .collect::<StorageResult<Vec<_>>>()
+            .expect("range failed");
+        let expected: [(Vec<u8>, Vec<u8>); 2] =
+            [(vec![1], b"a".to_vec()), (vec![3], b"c".to_vec())];
+        assert_eq!(entries, expected);
+
+        assert_eq!(
+            storage.snapshot().get(&vec![1]).expect("get failed"),
+            Some(b"b".to_vec())
+        );
+    }
+
+    #[test]
+    fn test_transaction_discard() {
+        let mut storage = sled_storage();
+        storage.set(vec![1], b"a".to_vec()).expect("set failed");
+
+        let mut tx = storage.transaction();
+        tx.set(vec![1], b"b".to_vec()).expect("set failed");
+        tx.set(vec![2], b"c".to_vec()).expect("set failed");
+        tx.discard();
+
+        assert_eq!(
+            storage.get(&vec![1]).expect("get failed"),
+            Some(b"a".to_vec())
+        );
+        assert_eq!(storage.get(&vec![2]).expect("get failed"), None);
+    }
+
+    #[test]
+    fn test_imt_matches_btree() {
+        let mut sled_storage = sled_storage();
+        let mut btree_storage = BTreeStorage::new();
+
+        // Instanciate an imt with a few nodes on both storages, within a commited transaction.
+        let sled_root = insert_nodes(&mut sled_storage);
+        let btree_root = insert_nodes(&mut btree_storage);
+        assert_eq!(sled_root, btree_root);
+
+        let sut = Imt::reader(
+            Keccak::v256,
+            ImtStorage::<_, [u8; 32], [u8; 32]>::new(&sled_storage),
+        )
+        .expect("imt reader failed");
+        assert_eq!(sut.root().expect("root failed"), sled_root);
+        sut.verify_integrity().expect("verify integrity failed");
+    }
+
+    fn insert_nodes<S>(storage: &mut S) -> [u8; 32]
+    where
+        S: TransactionalStorage,
+        for<'a> S::T<'a>: StorageWriter<StorageKey = Vec<u8>, StorageValue = Vec<u8>>,
+    {
+        let mut tx = storage.transaction();
+        let mut imt =
+            Imt::writer(Keccak::v256, ImtStorage::new(&mut tx)).expect("imt writer failed");
+        for key in [[3; 32], [1; 32], [2; 32]] {
+            imt.insert_node(key, key).expect("insert failed");
+        }
+        imt.delete_node([1; 32]).expect("delete failed");
+
+        let root = imt.root().expect("root failed");
+        tx.commit().expect("commit failed");
+        root
+    }
+}
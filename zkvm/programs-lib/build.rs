@@ -0,0 +1,33 @@
+use std::{env, path::PathBuf};
+
+/// The SP1 circuits version whose PLONK verifier key the forced proofs are verified against.
+const SP1_CIRCUIT_VERSION: &str = "v2.0.0";
+
+/// Locates the SP1 PLONK verifier key embedded for the `SP1ForcedProof` verification.
+///
+/// It is read from `SP1_PLONK_VK_PATH` when set, and otherwise from the circuits installed by
+/// `sp1up` in `$HOME/.sp1/circuits`.
+fn main() {
+    println!("cargo:rerun-if-env-changed=SP1_PLONK_VK_PATH");
+
+    let vk_path = env::var_os("SP1_PLONK_VK_PATH")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| {
+            let home = env::var_os("HOME").expect("HOME must be set to locate the SP1 circuits");
+            PathBuf::from(home)
+                .join(".sp1")
+                .join("circuits")
+                .join(SP1_CIRCUIT_VERSION)
+                .join("plonk_vk.bin")
+        });
+
+    assert!(
+        vk_path.is_file(),
+        "missing SP1 PLONK verifier key at {}: install the SP1 {SP1_CIRCUIT_VERSION} circuits \
+         or set SP1_PLONK_VK_PATH",
+        vk_path.display()
+    );
+
+    println!("cargo:rerun-if-changed={}", vk_path.display());
+    println!("cargo:rustc-env=SP1_PLONK_VK_PATH={}", vk_path.display());
+}
@@ -1,2 +1,24 @@
+use anyhow::{bail, Result};
+
+use keyspace_imt::proof::mutate::MutateProof;
+
+use crate::Hash256;
+
 pub mod sp1;
 pub mod sp1_forced;
+
+/// Extracts the `(keyspace_id, current_value, new_value)` record update committed to by the SP1
+/// record programs from the imt [MutateProof].
+///
+/// The record programs (and the L1 KeyStore contract) only commit to value transitions, where a
+/// delete would be indistinguishable from an update to the zero value. Deletes are thus rejected
+/// until they get a dedicated encoding.
+pub fn record_update(
+    imt_mutate_proof: &MutateProof<Hash256, Hash256>,
+) -> Result<(Hash256, Hash256, Hash256)> {
+    match imt_mutate_proof {
+        MutateProof::Insert(insert) => Ok((insert.node.key, insert.node.key, insert.node.value)),
+        MutateProof::Update(update) => Ok((update.node.key, update.node.value, update.new_value)),
+        MutateProof::Delete(_) => bail!("delete proofs are not supported by the record programs"),
+    }
+}
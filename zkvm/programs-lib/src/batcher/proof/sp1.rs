@@ -4,6 +4,7 @@ use serde::{Deserialize, Serialize};
 
 use keyspace_imt::proof::mutate::MutateProof;
 
+use super::record_update;
 use crate::{authorization_key, keyspace_value, Hash256};
 
 pub type SP1ProofVerify = fn(&[u32; 8], &Hash256);
@@ -34,10 +35,7 @@ impl SP1Proof {
         imt_mutate_proof: &MutateProof<Hash256, Hash256>,
         sp1_verify: SP1ProofVerify,
     ) -> Result<()> {
-        let (keyspace_id, current_value, new_value) = match imt_mutate_proof {
-            MutateProof::Insert(insert) => (insert.node.key, insert.node.key, insert.node.value),
-            MutateProof::Update(update) => (update.node.key, update.node.value, update.new_value),
-        };
+        let (keyspace_id, current_value, new_value) = record_update(imt_mutate_proof)?;
 
         // Ensure the provided inputs match with the `current_value`.
         let authorization_key = authorization_key(&self.record_vk_hash, Some(&self.forced_vk_hash));
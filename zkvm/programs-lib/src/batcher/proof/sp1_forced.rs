@@ -6,9 +6,11 @@ use serde::{Deserialize, Serialize};
 
 use keyspace_imt::proof::mutate::MutateProof;
 
+use super::record_update;
 use crate::{authorization_key, keyspace_value, Hash256};
 
-const SP1_PLONK_VK_BYTES: &[u8] = include_bytes!("/home/vscode/.sp1/circuits/v2.0.0/plonk_vk.bin");
+/// The SP1 PLONK verifier key, located by the build script (see `SP1_PLONK_VK_PATH`).
+const SP1_PLONK_VK_BYTES: &[u8] = include_bytes!(env!("SP1_PLONK_VK_PATH"));
 
 /// An SP1 proof to use for forced transactions.
 #[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
@@ -41,11 +43,19 @@ impl SP1ForcedProof {
 
     /// Verifies the [SP1ForcedProof].
     pub fn verify(&self, imt_mutate_proof: &MutateProof<Hash256, Hash256>) -> Result<()> {
-        let (keyspace_id, current_value, new_value) = match imt_mutate_proof {
-            MutateProof::Insert(insert) => (insert.node.key, insert.node.key, insert.node.value),
-            MutateProof::Update(update) => (update.node.key, update.node.value, update.new_value),
-        };
+        let (keyspace_id, current_value, new_value) = record_update(imt_mutate_proof)?;
 
+        self.verify_record_update(keyspace_id, current_value, new_value)
+    }
+
+    /// Verifies the [SP1ForcedProof] for the given record update (e.g. the one of a forced
+    /// transaction submitted to the L1 KeyStore contract).
+    pub fn verify_record_update(
+        &self,
+        keyspace_id: Hash256,
+        current_value: Hash256,
+        new_value: Hash256,
+    ) -> Result<()> {
         // Ensure the provided inputs match with the `current_value`.
         let authorization_key = authorization_key(&self.record_vk_hash, Some(&self.forced_vk_hash));
         ensure!(
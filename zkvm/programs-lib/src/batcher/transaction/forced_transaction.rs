@@ -2,8 +2,11 @@ use anyhow::{ensure, Result};
 use serde::{Deserialize, Serialize};
 use tiny_keccak::{Hasher, Keccak};
 
-use crate::{batcher::proof::sp1_forced::SP1ForcedProof, Hash256};
-use keyspace_imt::proof::mutate::MutateProof;
+use crate::{
+    batcher::proof::{record_update, sp1_forced::SP1ForcedProof},
+    Hash256,
+};
+use keyspace_imt::proof::{mutate::MutateProof, ProofResult};
 
 /// A forced transaction is submitted directly to the K1 KeyStore contract and acts
 /// as a censorship resistance mechanism.
@@ -51,10 +54,7 @@ impl ForcedTransaction {
         }
 
         // Extract the record update from the imt MutateProof.
-        let (keyspace_id, current_value, new_value) = match &self.imt_mutate_proof {
-            MutateProof::Insert(insert) => (insert.node.key, insert.node.key, insert.node.value),
-            MutateProof::Update(update) => (update.node.key, update.node.value, update.new_value),
-        };
+        let (keyspace_id, current_value, new_value) = record_update(&self.imt_mutate_proof)?;
 
         // Compute the transaction commitment.
         let mut k = Keccak::v256();
@@ -76,7 +76,7 @@ impl ForcedTransaction {
     }
 
     /// Verifies the imt [MutateProof].
-    pub fn verify_imt_mutate(&self, old_root: &Hash256) -> Result<Hash256> {
+    pub fn verify_imt_mutate(&self, old_root: &Hash256) -> ProofResult<Hash256> {
         self.imt_mutate_proof.verify(Keccak::v256, *old_root)
     }
 }
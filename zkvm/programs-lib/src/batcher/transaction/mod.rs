@@ -74,11 +74,11 @@ impl Transaction {
         match self {
             Transaction::Sequenced(sequenced_transaction) => {
                 sequenced_transaction.verify_proof(sp1_verify)?;
-                sequenced_transaction.verify_imt_mutate(old_root)
+                Ok(sequenced_transaction.verify_imt_mutate(old_root)?)
             }
             Transaction::Forced(forced_transaction) => {
                 if forced_transaction.verify_proof().is_ok() {
-                    forced_transaction.verify_imt_mutate(old_root)
+                    Ok(forced_transaction.verify_imt_mutate(old_root)?)
                 } else {
                     Ok(*old_root)
                 }
@@ -3,10 +3,13 @@ use serde::{Deserialize, Serialize};
 use tiny_keccak::{Hasher, Keccak};
 
 use crate::{
-    batcher::proof::sp1::{SP1Proof, SP1ProofVerify},
+    batcher::proof::{
+        record_update,
+        sp1::{SP1Proof, SP1ProofVerify},
+    },
     Hash256,
 };
-use keyspace_imt::proof::mutate::MutateProof;
+use keyspace_imt::proof::{mutate::MutateProof, ProofResult};
 
 /// A sequenced transaction is submitted to the KeySpace node.
 ///
@@ -51,10 +54,7 @@ impl SequencedTransaction {
         }
 
         // Extract the record update from the imt MutateProof.
-        let (keyspace_id, current_value, new_value) = match &self.imt_mutate_proof {
-            MutateProof::Insert(insert) => (insert.node.key, insert.node.key, insert.node.value),
-            MutateProof::Update(update) => (update.node.key, update.node.value, update.new_value),
-        };
+        let (keyspace_id, current_value, new_value) = record_update(&self.imt_mutate_proof)?;
 
         // Compute the transaction commitment.
         let mut k = Keccak::v256();
@@ -75,7 +75,7 @@ impl SequencedTransaction {
     }
 
     /// Verifies the imt [MutateProof].
-    pub fn verify_imt_mutate(&self, old_root: &Hash256) -> Result<Hash256> {
+    pub fn verify_imt_mutate(&self, old_root: &Hash256) -> ProofResult<Hash256> {
         self.imt_mutate_proof.verify(Keccak::v256, *old_root)
     }
 }
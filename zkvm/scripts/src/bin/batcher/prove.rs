@@ -9,7 +9,7 @@ use keyspace_imt::tree::Imt;
 use keyspace_programs_lib::batcher::{
     inputs::Inputs, proof::sp1::SP1Proof as KeySpaceSP1Proof, transaction::Transaction,
 };
-use keyspace_state_manager::storage::btree::BTreeStorage;
+use keyspace_state_manager::storage::{btree::BTreeStorage, imt::ImtStorage};
 use scripts::{load_record_proof, read_forced_vk_hash};
 
 const ELF: &[u8] = include_bytes!("../../../../batcher/elf/riscv32im-succinct-zkvm-elf");
@@ -34,9 +34,9 @@ fn main() {
 
     let mut stdin = SP1Stdin::new();
 
-    let storage = BTreeStorage::default();
-    let mut imt = Imt::writer(Keccak::v256, storage);
-    let old_root = imt.root();
+    let storage = ImtStorage::<_, [u8; 32], [u8; 32]>::new(BTreeStorage::default());
+    let mut imt = Imt::writer(Keccak::v256, storage).expect("failed to create imt");
+    let old_root = imt.root().expect("failed to read imt root");
     println!("imt old root: 0x{}", hex::encode(old_root));
 
     let mut proof_files = read_dir("proofs/sp1/")
@@ -93,12 +93,12 @@ fn main() {
         })
         .collect::<Vec<_>>();
 
-    let new_root = imt.root();
+    let new_root = imt.root().expect("failed to read imt root");
     println!("imt new root: 0x{}", hex::encode(new_root));
 
     let inputs = Inputs {
         old_root,
-        new_root: imt.root(),
+        new_root,
         txs_commitment,
         txs,
     };